@@ -18,7 +18,7 @@ use std::io;
 use std::net::{Ipv4Addr, IpAddr, SocketAddr, ToSocketAddrs};
 use std::option::IntoIter;
 
-use hab_net::config::{GitHubCfg, GitHubOAuth, RouterAddr, RouterCfg};
+use hab_net::config::{GitHubCfg, GitHubEndpoint, GitHubOAuth, RouterAddr, RouterCfg};
 use hab_core::config::ConfigFile;
 
 use error::Error;
@@ -34,10 +34,15 @@ pub struct Config {
 
 impl ConfigFile for Config {
     type Error = Error;
+    const ENV_PREFIX: &'static str = "HAB_BUILDER_ADMIN";
+
+    fn service_name() -> &'static str {
+        "builder-admin"
+    }
 }
 
 impl GitHubOAuth for Config {
-    fn github_url(&self) -> &str {
+    fn github_url(&self) -> &GitHubEndpoint {
         &self.github.url
     }
 
@@ -48,6 +53,10 @@ impl GitHubOAuth for Config {
     fn github_client_secret(&self) -> &str {
         &self.github.client_secret
     }
+
+    fn github_idle_timeout(&self) -> u64 {
+        self.github.idle_timeout
+    }
 }
 
 impl RouterCfg for Config {
@@ -117,7 +126,7 @@ mod tests {
         assert_eq!(&format!("{}", config.http.listen), "::1");
         assert_eq!(config.http.port, 8080);
         assert_eq!(&format!("{}", config.routers[0]), "172.18.0.2:9632");
-        assert_eq!(config.github.url, "https://api.github.com");
+        assert_eq!(config.github.url.api_base(), "https://api.github.com");
         assert_eq!(config.github.client_id, "0c2f738a7d0bd300de10");
         assert_eq!(config.github.client_secret,
                    "438223113eeb6e7edf2d2f91a232b72de72b9bdf");
@@ -18,6 +18,7 @@ use std::io;
 use std::result;
 
 use hab_core;
+use hab_net;
 use hyper;
 use protobuf;
 use zmq;
@@ -29,6 +30,7 @@ pub enum Error {
     HyperError(hyper::error::Error),
     HTTP(hyper::status::StatusCode),
     IO(io::Error),
+    NetError(hab_net::Error),
     Protobuf(protobuf::ProtobufError),
     RequiredConfigField(&'static str),
     Zmq(zmq::Error),
@@ -44,6 +46,7 @@ impl fmt::Display for Error {
             Error::HyperError(ref e) => format!("{}", e),
             Error::HTTP(ref e) => format!("{}", e),
             Error::IO(ref e) => format!("{}", e),
+            Error::NetError(ref e) => format!("{}", e),
             Error::Protobuf(ref e) => format!("{}", e),
             Error::RequiredConfigField(ref e) => {
                 format!("Missing required field in configuration, {}", e)
@@ -62,6 +65,7 @@ impl error::Error for Error {
             Error::HyperError(ref err) => err.description(),
             Error::HTTP(_) => "Non-200 HTTP response.",
             Error::IO(ref err) => err.description(),
+            Error::NetError(ref err) => err.description(),
             Error::Protobuf(ref err) => err.description(),
             Error::RequiredConfigField(_) => "Missing required field in configuration.",
             Error::Zmq(ref err) => err.description(),
@@ -87,6 +91,12 @@ impl From<io::Error> for Error {
     }
 }
 
+impl From<hab_net::Error> for Error {
+    fn from(err: hab_net::Error) -> Self {
+        Error::NetError(err)
+    }
+}
+
 impl From<protobuf::ProtobufError> for Error {
     fn from(err: protobuf::ProtobufError) -> Error {
         Error::Protobuf(err)
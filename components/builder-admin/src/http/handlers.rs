@@ -71,7 +71,10 @@ pub fn account_show(req: &mut Request) -> IronResult<Response> {
     };
     let mut account_get_id = AccountGetId::new();
     account_get_id.set_id(id);
-    let mut conn = Broker::connect().unwrap();
+    let mut conn = match Broker::try_connect() {
+        Some(conn) => conn,
+        None => return Ok(Response::with(status::ServiceUnavailable)),
+    };
     match conn.route::<AccountGetId, Account>(&account_get_id) {
         Ok(account) => Ok(render_json(status::Ok, &account)),
         Err(err) => Ok(render_net_error(&err)),
@@ -109,7 +112,10 @@ fn search_account(key: String, value: String) -> IronResult<Response> {
                 Err(_) => return Ok(Response::with(status::BadRequest)),
             };
             account_get_id.set_id(id);
-            let mut conn = Broker::connect().unwrap();
+            let mut conn = match Broker::try_connect() {
+                Some(conn) => conn,
+                None => return Ok(Response::with(status::ServiceUnavailable)),
+            };
             match conn.route::<AccountGetId, Account>(&account_get_id) {
                 Ok(account) => Ok(render_json(status::Ok, &account)),
                 Err(err) => Ok(render_net_error(&err)),
@@ -118,7 +124,10 @@ fn search_account(key: String, value: String) -> IronResult<Response> {
         "name" => {
             let mut account_get = AccountGet::new();
             account_get.set_name(value);
-            let mut conn = Broker::connect().unwrap();
+            let mut conn = match Broker::try_connect() {
+                Some(conn) => conn,
+                None => return Ok(Response::with(status::ServiceUnavailable)),
+            };
             match conn.route::<AccountGet, Account>(&account_get) {
                 Ok(account) => Ok(render_json(status::Ok, &account)),
                 Err(err) => Ok(render_net_error(&err)),
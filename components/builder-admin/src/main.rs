@@ -17,6 +17,7 @@ extern crate clap;
 extern crate env_logger;
 extern crate habitat_builder_admin as admin;
 extern crate habitat_core as hab_core;
+extern crate habitat_net as hab_net;
 #[macro_use]
 extern crate log;
 
@@ -24,10 +25,10 @@ use std::process;
 use std::str::FromStr;
 
 use hab_core::config::ConfigFile;
+use hab_net::config::RouterCfg;
 use admin::{Config, Error, Result};
 
 const VERSION: &'static str = include_str!(concat!(env!("OUT_DIR"), "/VERSION"));
-const CFG_DEFAULT_PATH: &'static str = "/hab/svc/builder-admin/config.toml";
 
 fn main() {
     env_logger::init().unwrap();
@@ -63,7 +64,12 @@ fn config_from_args(matches: &clap::ArgMatches) -> Result<Config> {
     let args = matches.subcommand_matches(cmd).unwrap();
     let mut config = match args.value_of("config") {
         Some(cfg_path) => try!(Config::from_file(cfg_path)),
-        None => Config::from_file(CFG_DEFAULT_PATH).unwrap_or(Config::default()),
+        None => {
+            match Config::default_url() {
+                Some(url) => try!(Config::from_url(&url)),
+                None => Config::from_file(Config::default_path()).unwrap_or(Config::default()),
+            }
+        }
     };
     if let Some(port) = args.value_of("port") {
         if u16::from_str(port)
@@ -72,6 +78,7 @@ fn config_from_args(matches: &clap::ArgMatches) -> Result<Config> {
             return Err(Error::BadPort(port.to_string()));
         }
     }
+    try!(config.validate_routers());
     Ok(config)
 }
 
@@ -15,9 +15,10 @@
 //! Contains core functionality for the Application's main server.
 
 use std::sync::Arc;
+use std::time::Duration;
 
 use hab_net::config::RouterCfg;
-use hab_net::routing::Broker;
+use hab_net::routing::{Broker, PING_TIMEOUT_MS};
 use hab_net::server::NetIdent;
 
 use config::Config;
@@ -44,6 +45,8 @@ impl Server {
     pub fn run(&mut self) -> Result<()> {
         let cfg1 = self.config.clone();
         let broker = Broker::run(Self::net_ident(), self.config.route_addrs());
+        try!(Broker::connect_with_timeout(self.config.route_addrs(),
+                                          Duration::from_millis(PING_TIMEOUT_MS)));
         let http = try!(http::run(cfg1));
 
         println!("Builder Admin listening on {}:{}",
@@ -19,12 +19,15 @@ use std::io;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr, ToSocketAddrs};
 use std::option::IntoIter;
 
-use hab_net::config::{GitHubCfg, GitHubOAuth, RouterAddr, RouterCfg};
+use hab_net::config::{GitHubCfg, GitHubEndpoint, GitHubOAuth, IpNet, RouterAddr, RouterCfg};
 use hab_core::config::ConfigFile;
 use depot;
 
 use error::Error;
 
+/// Default limit, in bytes, on the size of request bodies read by the API handlers.
+pub const DEFAULT_MAX_REQUEST_BODY: usize = 1024 * 1024;
+
 #[derive(Debug, Deserialize)]
 #[serde(default)]
 pub struct Config {
@@ -32,13 +35,22 @@ pub struct Config {
     /// List of net addresses for routing servers to connect to
     pub routers: Vec<RouterAddr>,
     pub github: GitHubCfg,
+    /// GitHub organizations whose members may sign in. Leave empty to allow any GitHub user.
+    pub allowed_github_orgs: Vec<String>,
     pub ui: UiCfg,
+    pub jobs: JobsCfg,
     /// Depot's configuration
     pub depot: depot::config::Config,
     /// Whether to log events for funnel metrics
     pub events_enabled: bool,
     /// Where to record log events for funnel metrics
     pub log_dir: String,
+    /// Address ranges of reverse proxies trusted to report the client's address in the
+    /// `X-Forwarded-For` or `X-Real-IP` headers
+    pub trusted_proxy_ranges: Vec<IpNet>,
+    /// Largest request body, in bytes, the API handlers will read. Larger requests are answered
+    /// with a `413 Payload Too Large`.
+    pub max_request_body: usize,
 }
 
 impl Default for Config {
@@ -47,20 +59,29 @@ impl Default for Config {
             http: HttpCfg::default(),
             routers: vec![RouterAddr::default()],
             github: GitHubCfg::default(),
+            allowed_github_orgs: vec![],
             ui: UiCfg::default(),
+            jobs: JobsCfg::default(),
             depot: depot::config::Config::default(),
             events_enabled: false,
             log_dir: env::temp_dir().to_string_lossy().into_owned(),
+            trusted_proxy_ranges: vec![],
+            max_request_body: DEFAULT_MAX_REQUEST_BODY,
         }
     }
 }
 
 impl ConfigFile for Config {
     type Error = Error;
+    const ENV_PREFIX: &'static str = "HAB_BUILDER_API";
+
+    fn service_name() -> &'static str {
+        "builder-api"
+    }
 }
 
 impl GitHubOAuth for Config {
-    fn github_url(&self) -> &str {
+    fn github_url(&self) -> &GitHubEndpoint {
         &self.github.url
     }
 
@@ -71,6 +92,10 @@ impl GitHubOAuth for Config {
     fn github_client_secret(&self) -> &str {
         &self.github.client_secret
     }
+
+    fn github_idle_timeout(&self) -> u64 {
+        self.github.idle_timeout
+    }
 }
 
 impl RouterCfg for Config {
@@ -114,6 +139,14 @@ pub struct UiCfg {
     pub root: Option<String>,
 }
 
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct JobsCfg {
+    /// Maximum number of in-flight jobs a single origin may have. New jobs for an origin at
+    /// this limit are rejected. A value of 0 allows an unlimited number of jobs.
+    pub max_per_origin: usize,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -121,6 +154,10 @@ mod tests {
     #[test]
     fn config_from_file() {
         let content = r#"
+        allowed_github_orgs = ["habitat-sh"]
+        trusted_proxy_ranges = ["10.0.0.0/8", "fd00::/8"]
+        max_request_body = 4096
+
         [http]
         listen = "0:0:0:0:0:0:0:1"
         port = 9636
@@ -128,6 +165,9 @@ mod tests {
         [ui]
         root = "/some/path"
 
+        [jobs]
+        max_per_origin = 5
+
         [depot]
         path = "/hab/svc/hab-depot/data"
         insecure = true
@@ -158,11 +198,17 @@ mod tests {
         assert_eq!(&format!("{}", config.http.listen), "::1");
         assert_eq!(config.http.port, 9636);
         assert_eq!(&format!("{}", config.routers[0]), "172.18.0.2:9632");
-        assert_eq!(config.github.url, "https://api.github.com");
+        assert_eq!(config.github.url.api_base(), "https://api.github.com");
         assert_eq!(config.github.client_id, "0c2f738a7d0bd300de10");
         assert_eq!(config.github.client_secret,
                    "438223113eeb6e7edf2d2f91a232b72de72b9bdf");
         assert_eq!(config.ui.root, Some("/some/path".to_string()));
+        assert_eq!(config.jobs.max_per_origin, 5);
+        assert_eq!(config.allowed_github_orgs, vec!["habitat-sh".to_string()]);
+        assert_eq!(config.trusted_proxy_ranges,
+                   vec!["10.0.0.0/8".parse::<IpNet>().unwrap(),
+                        "fd00::/8".parse::<IpNet>().unwrap()]);
+        assert_eq!(config.max_request_body, 4096);
     }
 
     #[test]
@@ -174,5 +220,86 @@ mod tests {
 
         let config = Config::from_raw(&content).unwrap();
         assert_eq!(config.http.port, 9000);
+        assert!(config.allowed_github_orgs.is_empty());
+        assert!(config.trusted_proxy_ranges.is_empty());
+        assert_eq!(config.max_request_body, DEFAULT_MAX_REQUEST_BODY);
+    }
+
+    #[test]
+    fn config_with_invalid_trusted_proxy_range() {
+        let content = r#"
+        trusted_proxy_ranges = ["10.0.0.0/33"]
+        "#;
+
+        assert!(Config::from_raw(&content).is_err());
+    }
+
+    #[test]
+    fn config_with_empty_routers_is_invalid() {
+        let content = r#"
+        routers = []
+        "#;
+
+        let config = Config::from_raw(&content).unwrap();
+        assert!(config.validate_routers().is_err());
+    }
+
+    #[test]
+    fn config_with_routers_is_valid() {
+        let content = r#"
+        [[routers]]
+        host = "172.18.0.2"
+        port = 9632
+        "#;
+
+        let config = Config::from_raw(&content).unwrap();
+        assert!(config.validate_routers().is_ok());
+    }
+
+    #[test]
+    fn config_from_env() {
+        let vars = [("HAB_BUILDER_API_HTTP__LISTEN", "0:0:0:0:0:0:0:1"),
+                    ("HAB_BUILDER_API_HTTP__PORT", "9000"),
+                    ("HAB_BUILDER_API_ROUTERS", r#"[{ host = "172.18.0.2", port = 9632 }]"#),
+                    ("HAB_BUILDER_API_GITHUB__CLIENT_ID", "0c2f738a7d0bd300de10"),
+                    ("HAB_BUILDER_API_EVENTS_ENABLED", "true"),
+                    ("HAB_BUILDER_API_UI__ROOT", "")];
+        for &(key, value) in vars.iter() {
+            env::set_var(key, value);
+        }
+        let config = Config::from_env();
+        for &(key, _) in vars.iter() {
+            env::remove_var(key);
+        }
+
+        let config = config.unwrap();
+        assert_eq!(&format!("{}", config.http.listen), "::1");
+        assert_eq!(config.http.port, 9000);
+        assert_eq!(&format!("{}", config.routers[0]), "172.18.0.2:9632");
+        assert_eq!(config.github.client_id, "0c2f738a7d0bd300de10");
+        assert_eq!(config.github.url, GitHubCfg::default().url);
+        assert!(config.events_enabled);
+        assert_eq!(config.ui.root, None);
+    }
+
+    #[test]
+    fn config_enterprise_github_url() {
+        let content = r#"
+        [github]
+        url = "https://github.example.com"
+        "#;
+
+        let config = Config::from_raw(&content).unwrap();
+        assert_eq!(config.github_url().api_base(), "https://github.example.com/api/v3");
+    }
+
+    #[test]
+    fn config_malformed_github_url() {
+        let content = r#"
+        [github]
+        url = "github.example.com"
+        "#;
+
+        assert!(Config::from_raw(&content).is_err());
     }
 }
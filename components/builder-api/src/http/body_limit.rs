@@ -0,0 +1,97 @@
+// Copyright (c) 2016-2017 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Limit on the size of request bodies read by the API handlers.
+
+use std::error;
+use std::fmt;
+use std::io;
+
+use bodyparser;
+use iron::headers::ContentLength;
+use iron::middleware::BeforeMiddleware;
+use iron::prelude::*;
+use iron::status;
+use persistent;
+
+/// Rejects requests whose declared `Content-Length` is over the limit with a
+/// `413 Payload Too Large`.
+///
+/// Link `max_body_length()` with the same limit so that a chunked body which grows past it fails
+/// to parse instead of being read into memory.
+#[derive(Clone, Copy, Debug)]
+pub struct BodyLimit {
+    max: usize,
+}
+
+impl BodyLimit {
+    pub fn new(max: usize) -> Self {
+        BodyLimit { max: max }
+    }
+
+    /// Returns middleware handing the limit to bodyparser.
+    pub fn max_body_length(&self) -> persistent::Read<bodyparser::MaxBodyLength> {
+        persistent::Read::<bodyparser::MaxBodyLength>::one(self.max)
+    }
+}
+
+// The message of the I/O error bodyparser stops reading with once a body grows past
+// `MaxBodyLength`.
+const BODY_TOO_BIG: &'static str = "Body is too big";
+
+/// Returns the status to answer a request whose body bodyparser couldn't read with.
+///
+/// A body which grew past `MaxBodyLength` is answered with a `413 Payload Too Large` and one
+/// which couldn't be read otherwise, such as a body cut short by the client, with a
+/// `400 Bad Request`. Bodies which aren't valid JSON are unprocessable.
+pub fn body_error_status(err: &bodyparser::BodyError) -> status::Status {
+    match err.cause {
+        bodyparser::BodyErrorCause::IoError(ref e) if e.kind() == io::ErrorKind::Other &&
+                                                      e.to_string() == BODY_TOO_BIG => {
+            status::PayloadTooLarge
+        }
+        bodyparser::BodyErrorCause::IoError(_) => status::BadRequest,
+        _ => status::UnprocessableEntity,
+    }
+}
+
+impl BeforeMiddleware for BodyLimit {
+    fn before(&self, req: &mut Request) -> IronResult<()> {
+        if let Some(&ContentLength(len)) = req.headers.get::<ContentLength>() {
+            if len > self.max as u64 {
+                let err = BodyTooLarge(len, self.max);
+                return Err(IronError::new(err, status::PayloadTooLarge));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct BodyTooLarge(u64, usize);
+
+impl fmt::Display for BodyTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+               "Request body of {} bytes is over the limit of {} bytes",
+               self.0,
+               self.1)
+    }
+}
+
+impl error::Error for BodyTooLarge {
+    fn description(&self) -> &str {
+        "Request body is over the size limit"
+    }
+}
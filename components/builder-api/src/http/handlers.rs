@@ -15,51 +15,85 @@
 //! A collection of handlers for the HTTP server's router
 
 use std::env;
+use std::path::{Component, Path};
 
-use base64;
 use bodyparser;
 use depot::server::check_origin_access;
+use hab_core::crypto::keys::is_valid_origin_name;
 use hab_core::package::Plan;
 use hab_core::event::*;
 use hab_net;
 use hab_net::http::controller::*;
 use hab_net::routing::Broker;
+use hyper;
 use iron::prelude::*;
 use iron::status;
 use iron::typemap;
 use persistent;
-use protocol::jobsrv::{Job, JobGet, JobSpec};
+use protocol::jobsrv::{Job, JobGet, JobSpec, JobState};
 use protocol::originsrv::*;
 use protocol::sessionsrv;
 use protocol::net::{self, NetOk, ErrCode};
 use router::Router;
 
+use super::body_limit::body_error_status;
+use super::helpers::{get_or_create_origin, query_param, route_message};
+use super::throttle::JobThrottle;
+
 define_event_log!();
 
-#[derive(Clone, Serialize, Deserialize)]
+/// GitHub organizations whose members are allowed to sign in. An empty list allows any GitHub user.
+pub struct AllowedOrgs(pub Vec<String>);
+
+impl AllowedOrgs {
+    /// Returns true if a user belonging to the given organizations is allowed to sign in.
+    pub fn allows(&self, user_orgs: &[String]) -> bool {
+        self.0.is_empty() || user_orgs.iter().any(|org| self.0.contains(org))
+    }
+}
+
+impl typemap::Key for AllowedOrgs {
+    type Value = Self;
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
 struct JobCreateReq {
     project_id: String,
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 struct ProjectCreateReq {
     origin: String,
     plan_path: String,
     github: GitHubProject,
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct ExternalProjectCreateReq {
+    #[serde(default)]
+    origin: String,
+    plan_path: String,
+    vcs_url: String,
+    vcs_type: String,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
 struct ProjectUpdateReq {
     plan_path: String,
     github: GitHubProject,
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 struct GitHubProject {
     organization: String,
     repo: String,
 }
 
+#[derive(Serialize)]
+struct ProjectNotFound {
+    error: String,
+}
+
 pub fn github_authenticate(req: &mut Request) -> IronResult<Response> {
     let code = {
         let params = req.extensions.get::<Router>().unwrap();
@@ -77,11 +111,28 @@ pub fn github_authenticate(req: &mut Request) -> IronResult<Response> {
                        account: session.get_id().to_string(),
                    });
 
-        return Ok(render_json(status::Ok, &session));
+        return Ok(render_message(req, status::Ok, &session));
     }
 
     match github.authenticate(&code) {
         Ok(token) => {
+            let allowed = req.get::<persistent::Read<AllowedOrgs>>().unwrap();
+            if !allowed.0.is_empty() {
+                match github.orgs(&token) {
+                    Ok(orgs) => {
+                        let orgs: Vec<String> = orgs.into_iter().map(|org| org.login).collect();
+                        if !allowed.allows(&orgs) {
+                            let err = net::err(ErrCode::ACCESS_DENIED, "rg:auth:1");
+                            return Ok(render_message(req, status::Forbidden, &err));
+                        }
+                    }
+                    Err(e) => {
+                        error!("unable to list github organizations, err={:?}", e);
+                        let err = net::err(ErrCode::BUG, "rg:auth:2");
+                        return Ok(render_net_error_message(req, &err));
+                    }
+                }
+            }
             let session = try!(session_create(&github, &token));
 
             log_event!(req,
@@ -90,13 +141,13 @@ pub fn github_authenticate(req: &mut Request) -> IronResult<Response> {
                            account: session.get_id().to_string(),
                        });
 
-            Ok(render_json(status::Ok, &session))
+            Ok(render_message(req, status::Ok, &session))
         }
-        Err(hab_net::Error::Net(err)) => Ok(render_net_error(&err)),
+        Err(hab_net::Error::Net(err)) => Ok(render_net_error_message(req, &err)),
         Err(e) => {
             error!("unhandled github authentication, err={:?}", e);
             let err = net::err(ErrCode::BUG, "rg:auth:0");
-            Ok(render_net_error(&err))
+            Ok(render_net_error_message(req, &err))
         }
     }
 }
@@ -106,31 +157,60 @@ pub fn job_create(req: &mut Request) -> IronResult<Response> {
     {
         match req.get::<bodyparser::Struct<JobCreateReq>>() {
             Ok(Some(body)) => project_get.set_name(body.project_id),
+            Err(ref err) => return Ok(Response::with(body_error_status(err))),
             _ => return Ok(Response::with(status::UnprocessableEntity)),
         }
     }
     // TODO: SA - Eliminate need to clone the session
     let session = req.extensions.get::<Authenticated>().unwrap().clone();
-    let mut conn = Broker::connect().unwrap();
-    let project = match conn.route::<OriginProjectGet, OriginProject>(&project_get) {
+    let project = match route_message::<OriginProjectGet, OriginProject>(req, &project_get) {
         Ok(project) => project,
-        Err(err) => return Ok(render_net_error(&err)),
+        Err(err) => return Ok(render_net_error_message(req, &err)),
     };
 
+    // Only this origin's jobs are locked while they're checked with the job server, and until
+    // the new job is recorded.
+    let throttle = req.get::<persistent::Read<JobThrottle>>().unwrap();
+    let origin_jobs = throttle.origin_jobs(project.get_origin_name());
+    let mut origin_jobs = origin_jobs
+        .as_ref()
+        .map(|jobs| jobs.lock().expect("job throttle lock is poisoned"));
+    if let Some(ref mut jobs) = origin_jobs {
+        let admitted = jobs.admit(|id| {
+            let mut job_get = JobGet::new();
+            job_get.set_id(id);
+            match route_message::<JobGet, Job>(req, &job_get) {
+                Ok(job) => {
+                    match job.get_state() {
+                        JobState::Pending | JobState::Dispatched | JobState::Processing => true,
+                        _ => false,
+                    }
+                }
+                Err(err) => err.get_code() != ErrCode::ENTITY_NOT_FOUND,
+            }
+        });
+        if !admitted {
+            return Ok(Response::with((status::TooManyRequests, "rg:jc:1")));
+        }
+    }
+
     let mut job_spec: JobSpec = JobSpec::new();
     job_spec.set_owner_id(session.get_id());
     job_spec.set_project(project);
 
-    match conn.route::<JobSpec, Job>(&job_spec) {
+    match route_message::<JobSpec, Job>(req, &job_spec) {
         Ok(job) => {
+            if let Some(ref mut jobs) = origin_jobs {
+                jobs.record(job.get_id());
+            }
             log_event!(req,
                        Event::JobCreate {
                            package: job.get_project().get_id().to_string(),
                            account: session.get_id().to_string(),
                        });
-            Ok(render_json(status::Created, &job))
+            Ok(render_message(req, status::Created, &job))
         }
-        Err(err) => Ok(render_net_error(&err)),
+        Err(err) => Ok(render_net_error_message(req, &err)),
     }
 }
 
@@ -140,12 +220,15 @@ pub fn job_show(req: &mut Request) -> IronResult<Response> {
         Ok(id) => id,
         Err(_) => return Ok(Response::with(status::BadRequest)),
     };
-    let mut conn = Broker::connect().unwrap();
+    let mut conn = match Broker::try_connect() {
+        Some(conn) => conn,
+        None => return Ok(Response::with(status::ServiceUnavailable)),
+    };
     let mut request = JobGet::new();
     request.set_id(id);
     match conn.route::<JobGet, Job>(&request) {
-        Ok(job) => Ok(render_json(status::Ok, &job)),
-        Err(err) => Ok(render_net_error(&err)),
+        Ok(job) => Ok(render_message(req, status::Ok, &job)),
+        Err(err) => Ok(render_net_error_message(req, &err)),
     }
 }
 
@@ -156,25 +239,52 @@ pub fn status(_req: &mut Request) -> IronResult<Response> {
     Ok(Response::with(status::Ok))
 }
 
+/// List the repositories of a GitHub organization which the current user can see
+pub fn github_repos(req: &mut Request) -> IronResult<Response> {
+    let (token, org) = {
+        let session = req.extensions.get::<Authenticated>().unwrap();
+        let params = req.extensions.get::<Router>().unwrap();
+        (session.get_token().to_string(), params.find("org").unwrap().to_string())
+    };
+    let github = req.get::<persistent::Read<GitHubCli>>().unwrap();
+    match github.list_repos(&token, &org) {
+        Ok(repos) => Ok(render_json(status::Ok, &repos)),
+        Err(hab_net::Error::GitHubAPI(hyper::status::StatusCode::NotFound, _)) => {
+            Ok(Response::with(status::NotFound))
+        }
+        Err(e) => {
+            error!("unable to list github repositories, org={}, err={:?}", org, e);
+            let err = net::err(ErrCode::BUG, "rg:repos:1");
+            Ok(render_net_error_message(req, &err))
+        }
+    }
+}
+
 pub fn list_account_invitations(req: &mut Request) -> IronResult<Response> {
     let session = req.extensions.get::<Authenticated>().unwrap();
-    let mut conn = Broker::connect().unwrap();
+    let mut conn = match Broker::try_connect() {
+        Some(conn) => conn,
+        None => return Ok(Response::with(status::ServiceUnavailable)),
+    };
     let mut request = sessionsrv::AccountInvitationListRequest::new();
     request.set_account_id(session.get_id());
     match conn.route::<sessionsrv::AccountInvitationListRequest, sessionsrv::AccountInvitationListResponse>(&request) {
-        Ok(invites) => Ok(render_json(status::Ok, &invites)),
-        Err(err) => Ok(render_net_error(&err)),
+        Ok(invites) => Ok(render_message(req, status::Ok, &invites)),
+        Err(err) => Ok(render_net_error_message(req, &err)),
     }
 }
 
 pub fn list_user_origins(req: &mut Request) -> IronResult<Response> {
     let session = req.extensions.get::<Authenticated>().unwrap();
-    let mut conn = Broker::connect().unwrap();
+    let mut conn = match Broker::try_connect() {
+        Some(conn) => conn,
+        None => return Ok(Response::with(status::ServiceUnavailable)),
+    };
     let mut request = sessionsrv::AccountOriginListRequest::new();
     request.set_account_id(session.get_id());
     match conn.route::<sessionsrv::AccountOriginListRequest, sessionsrv::AccountOriginListResponse>(&request) {
-        Ok(invites) => Ok(render_json(status::Ok, &invites)),
-        Err(err) => Ok(render_net_error(&err)),
+        Ok(invites) => Ok(render_message(req, status::Ok, &invites)),
+        Err(err) => Ok(render_net_error_message(req, &err)),
     }
 }
 
@@ -185,12 +295,17 @@ pub fn project_create(req: &mut Request) -> IronResult<Response> {
     let mut origin_get = OriginGet::new();
     let github = req.get::<persistent::Read<GitHubCli>>().unwrap();
     let session = req.extensions.get::<Authenticated>().unwrap().clone();
+    let create_origin = query_param(req, "create_origin").map_or(false, |v| v == "true");
     let (organization, repo) = match req.get::<bodyparser::Struct<ProjectCreateReq>>() {
         Ok(Some(body)) => {
             if body.origin.len() <= 0 {
                 return Ok(Response::with((status::UnprocessableEntity,
                                           "Missing value for field: `origin`")));
             }
+            if create_origin && !is_valid_origin_name(&body.origin) {
+                return Ok(Response::with((status::UnprocessableEntity,
+                                          "Invalid value for field: `origin`")));
+            }
             if body.plan_path.len() <= 0 {
                 return Ok(Response::with((status::UnprocessableEntity,
                                           "Missing value for field: `plan_path`")));
@@ -206,32 +321,50 @@ pub fn project_create(req: &mut Request) -> IronResult<Response> {
             origin_get.set_name(body.origin);
             project.set_plan_path(body.plan_path);
             project.set_vcs_type(String::from("git"));
-            match github.repo(&session.get_token(),
-                              &body.github.organization,
-                              &body.github.repo) {
-                Ok(repo) => project.set_vcs_data(repo.clone_url),
-                Err(_) => return Ok(Response::with((status::UnprocessableEntity, "rg:pc:1"))),
+            match github.find_repo(&session.get_token(),
+                                   &body.github.organization,
+                                   &body.github.repo) {
+                Ok(Some(repo)) => project.set_vcs_data(repo.clone_url),
+                Ok(None) => return Ok(Response::with((status::UnprocessableEntity, "rg:pc:1"))),
+                Err(e) => {
+                    error!("Unable to look up GitHub repository: {}", e);
+                    return Ok(Response::with((status::BadGateway, "rg:pc:6")));
+                }
             }
             (body.github.organization, body.github.repo)
         }
+        Err(ref err) => return Ok(Response::with(body_error_status(err))),
         _ => return Ok(Response::with(status::UnprocessableEntity)),
     };
-    let mut conn = Broker::connect().unwrap();
-    let origin = match conn.route::<OriginGet, Origin>(&origin_get) {
-        Ok(response) => response,
-        Err(err) => return Ok(render_net_error(&err)),
+    // Projects may be created for repositories owned by the user themselves or by an
+    // organization they belong to.
+    if organization != session.get_name() {
+        match github.is_org_member(&session.get_token(), &organization, &session.get_name()) {
+            Ok(true) => (),
+            Ok(false) => return Ok(Response::with(status::Forbidden)),
+            Err(e) => {
+                error!("Unable to verify GitHub organization membership: {:?}", e);
+                return Ok(Response::with((status::BadGateway, "rg:pc:5")));
+            }
+        }
+    }
+    let mut conn = match Broker::try_connect() {
+        Some(conn) => conn,
+        None => return Ok(Response::with(status::ServiceUnavailable)),
     };
     match github.contents(&session.get_token(),
                           &organization,
                           &repo,
                           &project.get_plan_path()) {
         Ok(contents) => {
-            match base64::decode(&contents.content) {
+            match contents.decode() {
                 Ok(ref bytes) => {
                     match Plan::from_bytes(bytes) {
                         Ok(plan) => {
-                            project.set_origin_name(String::from(origin.get_name()));
-                            project.set_origin_id(origin.get_id());
+                            let errors = plan.validate();
+                            if !errors.is_empty() {
+                                return Ok(render_json(status::UnprocessableEntity, &errors));
+                            }
                             project.set_package_name(String::from(plan.name));
                         }
                         Err(_) => {
@@ -240,14 +373,31 @@ pub fn project_create(req: &mut Request) -> IronResult<Response> {
                     }
                 }
                 Err(e) => {
-                    error!("Base64 decode failure: {:?}", e);
+                    error!("GitHub contents decode failure: {:?}", e);
                     return Ok(Response::with((status::UnprocessableEntity, "rg:pc:4")));
                 }
             }
         }
+        Err(hab_net::Error::Net(err)) => return Ok(render_net_error_message(req, &err)),
         Err(_) => return Ok(Response::with((status::UnprocessableEntity, "rg:pc:2"))),
     }
 
+    // The origin is only created once the plan has been found to be valid, so a rejected
+    // request doesn't leave an origin behind.
+    let origin_result = if create_origin {
+        get_or_create_origin(req,
+                             origin_get.get_name(),
+                             session.get_id(),
+                             session.get_name())
+    } else {
+        conn.route::<OriginGet, Origin>(&origin_get)
+    };
+    let origin = match origin_result {
+        Ok(response) => response,
+        Err(err) => return Ok(render_net_error_message(req, &err)),
+    };
+    project.set_origin_name(String::from(origin.get_name()));
+    project.set_origin_id(origin.get_id());
     project.set_owner_id(session.get_id());
     request.set_project(project);
     match conn.route::<OriginProjectCreate, OriginProject>(&request) {
@@ -258,9 +408,82 @@ pub fn project_create(req: &mut Request) -> IronResult<Response> {
                            package: request.get_project().get_id().to_string(),
                            account: session.get_id().to_string(),
                        });
-            Ok(render_json(status::Created, &response))
+            Ok(render_message(req, status::Created, &response))
         }
-        Err(err) => Ok(render_net_error(&err)),
+        Err(err) => Ok(render_net_error_message(req, &err)),
+    }
+}
+
+/// Create a new project in the given origin for a plan hosted outside of GitHub. The plan can't
+/// be read ahead of time so the package name is derived from the plan's location in the repository.
+pub fn project_create_external(req: &mut Request) -> IronResult<Response> {
+    let (session_id, origin) = {
+        let session = req.extensions.get::<Authenticated>().unwrap();
+        let params = req.extensions.get::<Router>().unwrap();
+        (session.get_id(), params.find("origin").unwrap().to_owned())
+    };
+    let mut project = OriginProject::new();
+    match req.get::<bodyparser::Struct<ExternalProjectCreateReq>>() {
+        Ok(Some(body)) => {
+            if body.origin.len() > 0 && body.origin != origin {
+                return Ok(Response::with((status::UnprocessableEntity,
+                                          "Mismatched value for field: `origin`")));
+            }
+            if body.vcs_url.len() <= 0 {
+                return Ok(Response::with((status::UnprocessableEntity,
+                                          "Missing value for field: `vcs_url`")));
+            }
+            if !is_supported_vcs_url(&body.vcs_url) {
+                return Ok(Response::with((status::UnprocessableEntity,
+                                          "Invalid value for field: `vcs_url`")));
+            }
+            if body.vcs_type != "git" {
+                return Ok(Response::with((status::UnprocessableEntity,
+                                          "Unsupported value for field: `vcs_type`")));
+            }
+            match package_name_from_plan_path(&body.plan_path, &body.vcs_url) {
+                Some(name) => project.set_package_name(name),
+                None => {
+                    return Ok(Response::with((status::UnprocessableEntity,
+                                              "Invalid value for field: `plan_path`")))
+                }
+            }
+            project.set_plan_path(body.plan_path);
+            project.set_vcs_type(body.vcs_type);
+            project.set_vcs_data(body.vcs_url);
+        }
+        Err(ref err) => return Ok(Response::with(body_error_status(err))),
+        _ => return Ok(Response::with(status::UnprocessableEntity)),
+    }
+    if !try!(check_origin_access(req, session_id, &origin)) {
+        return Ok(Response::with(status::Forbidden));
+    }
+    let mut origin_get = OriginGet::new();
+    origin_get.set_name(origin);
+    let mut conn = match Broker::try_connect() {
+        Some(conn) => conn,
+        None => return Ok(Response::with(status::ServiceUnavailable)),
+    };
+    let origin = match conn.route::<OriginGet, Origin>(&origin_get) {
+        Ok(response) => response,
+        Err(err) => return Ok(render_net_error_message(req, &err)),
+    };
+    project.set_origin_name(String::from(origin.get_name()));
+    project.set_origin_id(origin.get_id());
+    project.set_owner_id(session_id);
+    let mut request = OriginProjectCreate::new();
+    request.set_project(project);
+    match conn.route::<OriginProjectCreate, OriginProject>(&request) {
+        Ok(response) => {
+            log_event!(req,
+                       Event::ProjectCreate {
+                           origin: origin.get_name().to_string(),
+                           package: response.get_id().to_string(),
+                           account: session_id.to_string(),
+                       });
+            Ok(render_message(req, status::Created, &response))
+        }
+        Err(err) => Ok(render_net_error_message(req, &err)),
     }
 }
 
@@ -285,14 +508,20 @@ pub fn project_delete(req: &mut Request) -> IronResult<Response> {
     }
 
     project_del.set_requestor_id(session_id);
-    let mut conn = Broker::connect().unwrap();
+    let mut conn = match Broker::try_connect() {
+        Some(conn) => conn,
+        None => return Ok(Response::with(status::ServiceUnavailable)),
+    };
     match conn.route::<OriginProjectDelete, NetOk>(&project_del) {
         Ok(_) => Ok(Response::with(status::NoContent)),
-        Err(err) => Ok(render_net_error(&err)),
+        Err(err) => Ok(render_net_error_message(req, &err)),
     }
 }
 
 /// Update the given project
+///
+/// A project can't be moved to a different repository by an update. Changing the underlying
+/// repository requires deleting and recreating the project.
 pub fn project_update(req: &mut Request) -> IronResult<Response> {
     let mut request = OriginProjectUpdate::new();
     let mut project = OriginProject::new();
@@ -322,21 +551,31 @@ pub fn project_update(req: &mut Request) -> IronResult<Response> {
             }
             project.set_vcs_type(String::from("git"));
             project.set_plan_path(body.plan_path);
-            match github.repo(&session_token, &body.github.organization, &body.github.repo) {
-                Ok(repo) => project.set_vcs_data(repo.clone_url),
-                Err(_) => return Ok(Response::with((status::UnprocessableEntity, "rg:pu:1"))),
+            match github.find_repo(&session_token,
+                                   &body.github.organization,
+                                   &body.github.repo) {
+                Ok(Some(repo)) => project.set_vcs_data(repo.clone_url),
+                Ok(None) => return Ok(Response::with((status::UnprocessableEntity, "rg:pu:1"))),
+                Err(e) => {
+                    error!("Unable to look up GitHub repository: {}", e);
+                    return Ok(Response::with((status::BadGateway, "rg:pu:7")));
+                }
             }
             (body.github.organization, body.github.repo)
         }
+        Err(ref err) => return Ok(Response::with(body_error_status(err))),
         _ => return Ok(Response::with(status::UnprocessableEntity)),
     };
-    let mut conn = Broker::connect().unwrap();
+    let mut conn = match Broker::try_connect() {
+        Some(conn) => conn,
+        None => return Ok(Response::with(status::ServiceUnavailable)),
+    };
     match github.contents(&session_token,
                           &organization,
                           &repo,
                           &project.get_plan_path()) {
         Ok(contents) => {
-            match base64::decode(&contents.content) {
+            match contents.decode() {
                 Ok(ref bytes) => {
                     match Plan::from_bytes(bytes) {
                         Ok(plan) => {
@@ -361,11 +600,25 @@ pub fn project_update(req: &mut Request) -> IronResult<Response> {
                         }
                     }
                 }
-                Err(_) => return Ok(Response::with((status::UnprocessableEntity, "rg:pu:4"))),
+                Err(e) => {
+                    error!("GitHub contents decode failure: {:?}", e);
+                    return Ok(Response::with((status::UnprocessableEntity, "rg:pu:4")));
+                }
             }
         }
+        Err(hab_net::Error::Net(err)) => return Ok(render_net_error_message(req, &err)),
         Err(_) => return Ok(Response::with((status::UnprocessableEntity, "rg:pu:5"))),
     }
+    let mut project_get = OriginProjectGet::new();
+    project_get.set_name(format!("{}/{}", project.get_origin_name(), project.get_package_name()));
+    match conn.route::<OriginProjectGet, OriginProject>(&project_get) {
+        Ok(existing) => {
+            if !clone_url_matches_project(project.get_vcs_data(), &existing) {
+                return Ok(Response::with((status::UnprocessableEntity, "rg:pu:6")));
+            }
+        }
+        Err(err) => return Ok(render_net_error_message(req, &err)),
+    }
     // JW TODO: owner_id should *not* be changing but we aren't using it just yet. FIXME before
     // making the project API public.
     project.set_owner_id(session_id);
@@ -373,22 +626,400 @@ pub fn project_update(req: &mut Request) -> IronResult<Response> {
     request.set_project(project);
     match conn.route::<OriginProjectUpdate, NetOk>(&request) {
         Ok(_) => Ok(Response::with(status::NoContent)),
-        Err(err) => Ok(render_net_error(&err)),
+        Err(err) => Ok(render_net_error_message(req, &err)),
     }
 }
 
 /// Display the the given project's details
 pub fn project_show(req: &mut Request) -> IronResult<Response> {
     let mut project_get = OriginProjectGet::new();
-    let params = req.extensions.get::<Router>().unwrap();
     {
+        let params = req.extensions.get::<Router>().unwrap();
         let origin = params.find("origin").unwrap();
         let name = params.find("name").unwrap();
         project_get.set_name(format!("{}/{}", origin, name));
     }
-    let mut conn = Broker::connect().unwrap();
-    match conn.route::<OriginProjectGet, OriginProject>(&project_get) {
-        Ok(project) => Ok(render_json(status::Ok, &project)),
-        Err(err) => Ok(render_net_error(&err)),
+    match route_message::<OriginProjectGet, OriginProject>(req, &project_get) {
+        Ok(project) => Ok(render_message(req, status::Ok, &project)),
+        // Protobuf clients decode the NetError itself; everyone else gets a readable message.
+        Err(ref err) if err.get_code() == ErrCode::ENTITY_NOT_FOUND => {
+            match req.extensions.get::<AcceptHeader>() {
+                Some(&ResponseFormat::Protobuf) => Ok(render_proto(status::NotFound, err)),
+                _ => {
+                    let body = ProjectNotFound {
+                        error: format!("project {} not found", project_get.get_name()),
+                    };
+                    Ok(render_json(status::NotFound, &body))
+                }
+            }
+        }
+        Err(err) => Ok(render_net_error_message(req, &err)),
+    }
+}
+
+// Returns true if the given clone URL points at the same repository as the project's current
+// clone URL. Hosts, owners and repository names are compared case insensitively, as GitHub does.
+fn clone_url_matches_project(new_url: &str, project: &OriginProject) -> bool {
+    match (repo_ident(new_url), repo_ident(project.get_vcs_data())) {
+        (Some(new), Some(existing)) => new == existing,
+        _ => false,
+    }
+}
+
+// Returns the lowercased `host/owner/repo` of a clone URL such as
+// `https://github.com/habitat-sh/core-plans.git` or `git@github.com:habitat-sh/core-plans.git`.
+fn repo_ident(url: &str) -> Option<String> {
+    let (host, path) = match split_vcs_url(url) {
+        Some(parts) => parts,
+        None => return None,
+    };
+    let path = path.trim_right_matches('/').trim_right_matches(".git");
+    let mut parts = path.rsplit('/');
+    match (parts.next(), parts.next()) {
+        (Some(repo), Some(owner)) if host.len() > 0 && repo.len() > 0 && owner.len() > 0 => {
+            Some(format!("{}/{}/{}", host, owner, repo).to_lowercase())
+        }
+        _ => None,
+    }
+}
+
+// Splits a clone URL into its host and path. Only `https://host/path` and the SSH `git@host:path`
+// form are recognized.
+fn split_vcs_url(vcs_url: &str) -> Option<(&str, &str)> {
+    if vcs_url.starts_with("https://") {
+        let rest = &vcs_url["https://".len()..];
+        rest.find('/').map(|end| (&rest[..end], &rest[end + 1..]))
+    } else if vcs_url.starts_with("git@") {
+        let rest = &vcs_url["git@".len()..];
+        rest.find(':').map(|end| (&rest[..end], &rest[end + 1..]))
+    } else {
+        None
+    }
+}
+
+// Returns true if the URL is one the workers can clone from: either `https://host/path` or an
+// SSH location in the `git@host:path` form.
+fn is_supported_vcs_url(vcs_url: &str) -> bool {
+    let (host, path) = match split_vcs_url(vcs_url) {
+        Some(parts) => parts,
+        None => return false,
+    };
+    host.len() > 0 && !host.contains('/') && !host.contains('@') && path.len() > 0 &&
+    !vcs_url.chars().any(|c| c.is_whitespace())
+}
+
+// Returns the package name for a plan at the given path within a repository, or `None` if the
+// path doesn't point at a plan. Plans are named after the directory containing them, skipping a
+// `habitat/` directory, and plans at the root of a repository are named after the repository.
+fn package_name_from_plan_path(plan_path: &str, vcs_url: &str) -> Option<String> {
+    let path = Path::new(plan_path);
+    match path.file_name().and_then(|f| f.to_str()) {
+        Some("plan.sh") | Some("plan.ps1") => (),
+        _ => return None,
+    }
+    for component in path.components() {
+        match component {
+            Component::Normal(_) => (),
+            _ => return None,
+        }
+    }
+    let name = path.parent()
+        .into_iter()
+        .flat_map(|p| p.components().rev())
+        .filter_map(|c| c.as_os_str().to_str())
+        .find(|c| *c != "habitat")
+        .or_else(|| {
+                     vcs_url.trim_right_matches('/')
+                         .rsplit(|c| c == '/' || c == ':')
+                         .next()
+                         .map(|r| r.trim_right_matches(".git"))
+                 });
+    match name {
+        Some(name) if name.len() > 0 => Some(name.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Mutex;
+
+    use std::env;
+
+    use depot::server::TestableBroker;
+    use hab_core::event::EventLogger;
+    use hab_net::http::controller::{AcceptHeader, Authenticated};
+    use iron::{Chain, Handler, Headers};
+    use iron::headers::{ContentLength, ContentType};
+    use iron::prelude::*;
+    use iron::status;
+    use iron_test::{request, response};
+    use persistent;
+    use protobuf;
+    use protocol::jobsrv::{Job, JobSpec};
+    use protocol::net::{self, ErrCode, NetError};
+    use protocol::originsrv::{OriginProject, OriginProjectGet};
+    use protocol::sessionsrv::Session;
+
+    use super::{clone_url_matches_project, is_supported_vcs_url, job_create,
+                package_name_from_plan_path, project_show, AllowedOrgs, EventLog};
+    use super::super::body_limit::BodyLimit;
+    use super::super::throttle::JobThrottle;
+
+    fn project_show_request(broker: TestableBroker, headers: Headers) -> IronResult<Response> {
+        let broker = Mutex::new(Some(broker));
+        let handler = move |req: &mut Request| {
+            req.extensions.insert::<TestableBroker>(broker.lock().unwrap().take().unwrap());
+            let router = router!(project: get "/projects/:origin/:name" => project_show);
+            router.handle(req)
+        };
+        let mut chain = Chain::new(handler);
+        chain.link_before(AcceptHeader);
+        request::get("http://localhost:9636/projects/core/nginx", headers, &chain)
+    }
+
+    fn job_create_request(broker: TestableBroker,
+                          headers: Headers,
+                          body: &str,
+                          max_request_body: usize)
+                          -> IronResult<Response> {
+        let broker = Mutex::new(Some(broker));
+        let handler = move |req: &mut Request| {
+            req.extensions.insert::<TestableBroker>(broker.lock().unwrap().take().unwrap());
+            req.extensions.insert::<Authenticated>(Session::new());
+            job_create(req)
+        };
+        let limit = BodyLimit::new(max_request_body);
+        let mut chain = Chain::new(handler);
+        chain.link_before(AcceptHeader);
+        chain.link_before(limit);
+        chain.link_before(limit.max_body_length());
+        chain.link(persistent::Read::<JobThrottle>::both(JobThrottle::new(0)));
+        chain.link(persistent::Read::<EventLog>::both(EventLogger::new(env::temp_dir(), false)));
+        request::post("http://localhost:9636/jobs", headers, body, &chain)
+    }
+
+    fn json_headers(body: &str) -> Headers {
+        let mut headers = Headers::new();
+        headers.set(ContentType::json());
+        headers.set(ContentLength(body.len() as u64));
+        headers
+    }
+
+    const VCS_URL: &'static str = "https://git.internal/org/repo.git";
+
+    #[test]
+    fn package_name_from_plan_directory() {
+        assert_eq!(package_name_from_plan_path("components/foo/plan.sh", VCS_URL),
+                   Some("foo".to_string()));
+        assert_eq!(package_name_from_plan_path("foo/habitat/plan.ps1", VCS_URL),
+                   Some("foo".to_string()));
+    }
+
+    #[test]
+    fn package_name_from_repository() {
+        assert_eq!(package_name_from_plan_path("plan.sh", VCS_URL),
+                   Some("repo".to_string()));
+        assert_eq!(package_name_from_plan_path("habitat/plan.sh", VCS_URL),
+                   Some("repo".to_string()));
+    }
+
+    #[test]
+    fn package_name_from_invalid_plan_path() {
+        assert_eq!(package_name_from_plan_path("", VCS_URL), None);
+        assert_eq!(package_name_from_plan_path("foo/README.md", VCS_URL), None);
+        assert_eq!(package_name_from_plan_path("/foo/plan.sh", VCS_URL), None);
+        assert_eq!(package_name_from_plan_path("../foo/plan.sh", VCS_URL), None);
+    }
+
+    #[test]
+    fn package_name_from_ssh_repository() {
+        assert_eq!(package_name_from_plan_path("plan.sh", "git@git.internal:repo.git"),
+                   Some("repo".to_string()));
+        assert_eq!(package_name_from_plan_path("plan.sh", "git@git.internal:org/repo.git"),
+                   Some("repo".to_string()));
+    }
+
+    #[test]
+    fn supported_vcs_urls() {
+        for url in &[VCS_URL, "git@git.internal:org/repo.git", "git@git.internal:repo.git"] {
+            assert!(is_supported_vcs_url(url), "{}", url);
+        }
+    }
+
+    #[test]
+    fn unsupported_vcs_urls() {
+        for url in &["http://git.internal/org/repo.git",
+                     "git://git.internal/org/repo.git",
+                     "file:///srv/git/repo.git",
+                     "ssh://git@git.internal/org/repo.git",
+                     "/srv/git/repo.git",
+                     "https://git.internal",
+                     "https:///org/repo.git",
+                     "git@git.internal",
+                     "git@:org/repo.git",
+                     "git@git.internal:",
+                     "https://git.internal/org/repo.git --upload-pack=touch"] {
+            assert!(!is_supported_vcs_url(url), "{}", url);
+        }
+    }
+
+    #[test]
+    fn allowed_orgs_allows_member() {
+        let allowed = AllowedOrgs(vec!["habitat-sh".to_string(), "chef".to_string()]);
+        assert!(allowed.allows(&["other".to_string(), "chef".to_string()]));
+    }
+
+    #[test]
+    fn allowed_orgs_rejects_non_member() {
+        let allowed = AllowedOrgs(vec!["habitat-sh".to_string()]);
+        assert!(!allowed.allows(&["other".to_string()]));
+        assert!(!allowed.allows(&[]));
+    }
+
+    #[test]
+    fn allowed_orgs_empty_allows_all() {
+        let allowed = AllowedOrgs(vec![]);
+        assert!(allowed.allows(&["other".to_string()]));
+        assert!(allowed.allows(&[]));
+    }
+
+    #[test]
+    fn clone_url_matches_same_repository() {
+        let mut project = OriginProject::new();
+        project.set_vcs_data("https://github.com/habitat-sh/core-plans.git".to_string());
+        assert!(clone_url_matches_project("https://github.com/habitat-sh/core-plans.git",
+                                          &project));
+        assert!(clone_url_matches_project("https://github.com/Habitat-sh/Core-Plans", &project));
+        assert!(clone_url_matches_project("git@github.com:habitat-sh/core-plans.git", &project));
+    }
+
+    #[test]
+    fn clone_url_mismatches_other_repository() {
+        let mut project = OriginProject::new();
+        project.set_vcs_data("https://github.com/habitat-sh/core-plans.git".to_string());
+        assert!(!clone_url_matches_project("https://github.com/attacker/core-plans.git",
+                                           &project));
+        assert!(!clone_url_matches_project("https://github.com/habitat-sh/habitat.git",
+                                           &project));
+        assert!(!clone_url_matches_project("", &project));
+    }
+
+    #[test]
+    fn clone_url_mismatches_other_host() {
+        let mut project = OriginProject::new();
+        project.set_vcs_data("https://github.com/habitat-sh/core-plans.git".to_string());
+        assert!(!clone_url_matches_project("https://gitlab.com/habitat-sh/core-plans.git",
+                                           &project));
+        assert!(!clone_url_matches_project("git@example.com:habitat-sh/core-plans.git",
+                                           &project));
+        assert!(!clone_url_matches_project("https://example.com/github.com/habitat-sh/core-plans",
+                                           &project));
+    }
+
+    #[test]
+    fn project_show_found() {
+        let mut broker: TestableBroker = Default::default();
+        let mut project = OriginProject::new();
+        project.set_name("core/nginx".to_string());
+        broker.setup::<OriginProjectGet, OriginProject>(&project);
+
+        let response = project_show_request(broker, Headers::new()).unwrap();
+        assert_eq!(response.status, Some(status::Ok));
+        let body = response::extract_body_to_string(response);
+        assert!(body.contains("\"name\":\"core/nginx\""));
+    }
+
+    #[test]
+    fn project_show_not_found() {
+        let mut broker: TestableBroker = Default::default();
+        let err = net::err(ErrCode::ENTITY_NOT_FOUND, "vt:origin-project-get:0");
+        broker.setup_error::<OriginProjectGet>(err);
+
+        let response = project_show_request(broker, Headers::new()).unwrap();
+        assert_eq!(response.status, Some(status::NotFound));
+        let body = response::extract_body_to_string(response);
+        assert_eq!(body, r#"{"error":"project core/nginx not found"}"#);
+    }
+
+    #[test]
+    fn project_show_not_found_protobuf() {
+        let mut broker: TestableBroker = Default::default();
+        let err = net::err(ErrCode::ENTITY_NOT_FOUND, "vt:origin-project-get:0");
+        broker.setup_error::<OriginProjectGet>(err);
+        let mut headers = Headers::new();
+        headers.set_raw("Accept", vec![b"application/x-protobuf".to_vec()]);
+
+        let response = project_show_request(broker, headers).unwrap();
+        assert_eq!(response.status, Some(status::NotFound));
+        let body = response::extract_body_to_bytes(response);
+        let err: NetError = protobuf::parse_from_bytes(&body).unwrap();
+        assert_eq!(err.get_code(), ErrCode::ENTITY_NOT_FOUND);
+        assert_eq!(err.get_msg(), "vt:origin-project-get:0");
+    }
+
+    #[test]
+    fn job_create_body_under_limit() {
+        let mut broker: TestableBroker = Default::default();
+        let mut project = OriginProject::new();
+        project.set_name("core/nginx".to_string());
+        broker.setup::<OriginProjectGet, OriginProject>(&project);
+        let mut job = Job::new();
+        job.set_id(1);
+        broker.setup::<JobSpec, Job>(&job);
+
+        let body = r#"{"project_id":"core/nginx"}"#;
+        let response = job_create_request(broker, json_headers(body), body, 64).unwrap();
+        assert_eq!(response.status, Some(status::Created));
+    }
+
+    #[test]
+    fn job_create_unknown_project_protobuf() {
+        let mut broker: TestableBroker = Default::default();
+        let err = net::err(ErrCode::ENTITY_NOT_FOUND, "vt:origin-project-get:0");
+        broker.setup_error::<OriginProjectGet>(err);
+        let body = r#"{"project_id":"core/nginx"}"#;
+        let mut headers = json_headers(body);
+        headers.set_raw("Accept", vec![b"application/x-protobuf".to_vec()]);
+
+        let response = job_create_request(broker, headers, body, 64).unwrap();
+        assert_eq!(response.status, Some(status::NotFound));
+        let body = response::extract_body_to_bytes(response);
+        let err: NetError = protobuf::parse_from_bytes(&body).unwrap();
+        assert_eq!(err.get_code(), ErrCode::ENTITY_NOT_FOUND);
+    }
+
+    #[test]
+    fn job_create_invalid_body() {
+        let response =
+            job_create_request(Default::default(), json_headers("not json"), "not json", 64)
+                .unwrap();
+        assert_eq!(response.status, Some(status::UnprocessableEntity));
+    }
+
+    #[test]
+    fn job_create_truncated_body() {
+        let body = r#"{"project_id":"core/nginx"}"#;
+        let mut headers = json_headers(body);
+        headers.set(ContentLength(body.len() as u64 + 10));
+        let response = job_create_request(Default::default(), headers, body, 64).unwrap();
+        assert_eq!(response.status, Some(status::BadRequest));
+    }
+
+    #[test]
+    fn job_create_body_over_limit() {
+        let body = r#"{"project_id":"core/nginx-long"}"#;
+        let err = job_create_request(Default::default(), json_headers(body), body, 16)
+            .unwrap_err();
+        assert_eq!(err.response.status, Some(status::PayloadTooLarge));
+    }
+
+    #[test]
+    fn job_create_body_over_limit_without_length() {
+        let body = r#"{"project_id":"core/nginx-long"}"#;
+        let mut headers = Headers::new();
+        headers.set(ContentType::json());
+        let response = job_create_request(Default::default(), headers, body, 16).unwrap();
+        assert_eq!(response.status, Some(status::PayloadTooLarge));
     }
 }
@@ -0,0 +1,158 @@
+// Copyright (c) 2016-2017 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Helpers shared by the HTTP handlers.
+
+use depot::server::TestableBroker;
+use hab_net::routing::{Broker, RouteResult};
+use iron::prelude::*;
+use protocol::net::{self, ErrCode};
+use protocol::originsrv::{Origin, OriginCreate, OriginGet};
+use protocol::Routable;
+use protobuf;
+
+/// Routes a message through the test broker if the request carries one, and through a new
+/// connection to the routers otherwise.
+pub fn route_message<M: Routable, R: protobuf::MessageStatic>(req: &mut Request,
+                                                              msg: &M)
+                                                              -> RouteResult<R> {
+    if let Some(broker) = req.extensions.get_mut::<TestableBroker>() {
+        return broker.route::<M, R>(msg);
+    }
+
+    match Broker::try_connect() {
+        Some(mut conn) => conn.route::<M, R>(msg),
+        None => Err(net::err(ErrCode::ZMQ, "rg:route:0")),
+    }
+}
+
+/// Returns the named origin, creating it with the given owner if it doesn't exist yet. An origin
+/// created concurrently by another request is returned as if it had already existed.
+pub fn get_or_create_origin(req: &mut Request,
+                            name: &str,
+                            owner_id: u64,
+                            owner_name: &str)
+                            -> RouteResult<Origin> {
+    let mut origin_get = OriginGet::new();
+    origin_get.set_name(name.to_string());
+    match route_message::<OriginGet, Origin>(req, &origin_get) {
+        Ok(origin) => return Ok(origin),
+        Err(ref err) if err.get_code() == ErrCode::ENTITY_NOT_FOUND => (),
+        Err(err) => return Err(err),
+    }
+    let mut origin_create = OriginCreate::new();
+    origin_create.set_name(name.to_string());
+    origin_create.set_owner_id(owner_id);
+    origin_create.set_owner_name(owner_name.to_string());
+    match route_message::<OriginCreate, Origin>(req, &origin_create) {
+        Ok(origin) => Ok(origin),
+        Err(ref err) if err.get_code() == ErrCode::ENTITY_CONFLICT => {
+            route_message::<OriginGet, Origin>(req, &origin_get)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Returns the value of a query string parameter. Values are not percent-decoded, so this is
+/// only suitable for flags and other plain values.
+pub fn query_param(req: &Request, name: &str) -> Option<String> {
+    let query = match req.url.query() {
+        Some(query) => query,
+        None => return None,
+    };
+    query
+        .split('&')
+        .filter_map(|pair| {
+                        let mut parts = pair.splitn(2, '=');
+                        match (parts.next(), parts.next()) {
+                            (Some(key), Some(value)) if key == name => Some(value.to_string()),
+                            (Some(key), None) if key == name => Some("".to_string()),
+                            _ => None,
+                        }
+                    })
+        .next()
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Mutex;
+
+    use depot::server::{RoutedMessages, TestableBroker};
+    use iron::Headers;
+    use iron::prelude::*;
+    use iron::status;
+    use iron_test::request;
+    use protocol::net::{self, ErrCode};
+    use protocol::originsrv::{Origin, OriginCreate, OriginGet};
+
+    use super::*;
+
+    // Calls get_or_create_origin for `core` within a request routed through the given broker,
+    // returning its result and the messages routed.
+    fn get_or_create(broker: TestableBroker) -> (RouteResult<Origin>, RoutedMessages) {
+        let broker = Mutex::new(Some(broker));
+        let result = Mutex::new(None);
+        {
+            let handler = |req: &mut Request| {
+                req.extensions.insert::<TestableBroker>(broker.lock().unwrap().take().unwrap());
+                let origin = get_or_create_origin(req, "core", 42, "bobo");
+                let msgs = req.extensions.get::<TestableBroker>().unwrap().routed_messages();
+                *result.lock().unwrap() = Some((origin, msgs));
+                Ok(Response::with(status::Ok))
+            };
+            request::get("http://localhost:9636/projects", Headers::new(), &handler).unwrap();
+        }
+        result.into_inner().unwrap().unwrap()
+    }
+
+    #[test]
+    fn get_or_create_origin_creates_missing_origin() {
+        let mut broker: TestableBroker = Default::default();
+        let mut created = Origin::new();
+        created.set_name("core".to_string());
+        created.set_owner_id(42);
+        broker.setup_error::<OriginGet>(net::err(ErrCode::ENTITY_NOT_FOUND, "vt:origin-get:0"));
+        broker.setup::<OriginCreate, Origin>(&created);
+
+        let (origin, msgs) = get_or_create(broker);
+        assert_eq!(origin.unwrap().get_name(), "core");
+        let create = msgs.get::<OriginCreate>().unwrap();
+        assert_eq!(create.get_name(), "core");
+        assert_eq!(create.get_owner_id(), 42);
+        assert_eq!(create.get_owner_name(), "bobo");
+    }
+
+    #[test]
+    fn get_or_create_origin_returns_existing_origin() {
+        let mut broker: TestableBroker = Default::default();
+        let mut existing = Origin::new();
+        existing.set_name("core".to_string());
+        existing.set_owner_id(7);
+        broker.setup::<OriginGet, Origin>(&existing);
+
+        let (origin, msgs) = get_or_create(broker);
+        assert_eq!(origin.unwrap().get_owner_id(), 7);
+        assert!(msgs.get::<OriginCreate>().is_err());
+    }
+
+    #[test]
+    fn get_or_create_origin_surfaces_other_errors() {
+        let mut broker: TestableBroker = Default::default();
+        broker.setup_error::<OriginGet>(net::err(ErrCode::TIMEOUT, "rt:route:1"));
+
+        let (origin, msgs) = get_or_create(broker);
+        assert_eq!(origin.unwrap_err().get_code(), ErrCode::TIMEOUT);
+        assert!(msgs.get::<OriginCreate>().is_err());
+    }
+}
@@ -14,7 +14,10 @@
 
 //! A module containing the HTTP server and handlers for servicing client requests
 
+pub mod body_limit;
 pub mod handlers;
+pub mod helpers;
+pub mod throttle;
 
 use std::sync::{mpsc, Arc};
 use std::thread::{self, JoinHandle};
@@ -31,7 +34,9 @@ use staticfile::Static;
 
 use config::Config;
 use error::Result;
+use self::body_limit::BodyLimit;
 use self::handlers::*;
+use self::throttle::JobThrottle;
 
 // Iron defaults to a threadpool of size `8 * num_cpus`.
 // See: http://172.16.2.131:9633/iron/prelude/struct.Iron.html#method.http
@@ -53,7 +58,14 @@ pub fn router(config: Arc<Config>) -> Result<Chain> {
         },
         user_origins: get "/user/origins" => XHandler::new(list_user_origins).before(basic.clone()),
 
+        github_repos: get "/github/repos/:org" => {
+            XHandler::new(github_repos).before(basic.clone())
+        },
+
         projects: post "/projects" => XHandler::new(project_create).before(bldr.clone()),
+        projects_external: post "/projects/:origin" => {
+            XHandler::new(project_create_external).before(bldr.clone())
+        },
         project: get "/projects/:origin/:name" => XHandler::new(project_show).before(bldr.clone()),
         edit_project: put "/projects/:origin/:name" => {
             XHandler::new(project_update).before(bldr.clone())
@@ -64,8 +76,16 @@ pub fn router(config: Arc<Config>) -> Result<Chain> {
     );
     let mut chain = Chain::new(router);
     chain.link(persistent::Read::<GitHubCli>::both(GitHubClient::new(&*config)));
+    let allowed_orgs = AllowedOrgs(config.allowed_github_orgs.clone());
+    chain.link(persistent::Read::<AllowedOrgs>::both(allowed_orgs));
     chain.link(Read::<EventLog>::both(EventLogger::new(&config.log_dir, config.events_enabled)));
+    let throttle = JobThrottle::new(config.jobs.max_per_origin);
+    chain.link(persistent::Read::<JobThrottle>::both(throttle));
+    let body_limit = BodyLimit::new(config.max_request_body);
+    chain.link_before(body_limit);
+    chain.link_before(body_limit.max_body_length());
     chain.link_before(RouteBroker);
+    chain.link_before(AcceptHeader);
     chain.link_after(Cors);
     Ok(chain)
 }
@@ -85,6 +105,8 @@ pub fn run(config: Arc<Config>) -> Result<JoinHandle<()>> {
     let (tx, rx) = mpsc::sync_channel(1);
 
     let depot = depot::DepotUtil::new(config.depot.clone());
+    try!(depot.check_layout_version());
+    depot::server::remove_orphaned_uploads(&depot);
     let depot_chain = try!(depot::server::router(depot));
 
     let mut mount = Mount::new();
@@ -94,11 +116,13 @@ pub fn run(config: Arc<Config>) -> Result<JoinHandle<()>> {
     }
     let chain = try!(router(config.clone()));
     mount.mount("/v1", chain).mount("/v1/depot", depot_chain);
+    let mut server_chain = Chain::new(mount);
+    server_chain.link_before(ClientAddr::new(config.trusted_proxy_ranges.clone()));
 
     let handle = thread::Builder::new()
         .name("http-srv".to_string())
         .spawn(move || {
-                   let mut server = Iron::new(mount);
+                   let mut server = Iron::new(server_chain);
                    server.threads = HTTP_THREAD_COUNT;
                    server.http(&config.http).unwrap();
                    tx.send(()).unwrap();
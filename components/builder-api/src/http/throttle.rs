@@ -0,0 +1,147 @@
+// Copyright (c) 2016-2017 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Per-origin limits on the number of in-flight build jobs.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use iron::typemap::Key;
+
+/// Tracks the jobs enqueued for each origin so that a single origin cannot monopolize the
+/// worker pool.
+///
+/// Each origin's jobs sit behind their own lock, so checking one origin's jobs with the job
+/// server never holds up requests for another.
+pub struct JobThrottle {
+    max_per_origin: usize,
+    origins: Mutex<HashMap<String, Arc<Mutex<OriginJobs>>>>,
+}
+
+impl JobThrottle {
+    /// Create a new throttle. A `max_per_origin` of `0` disables throttling.
+    pub fn new(max_per_origin: usize) -> Self {
+        JobThrottle {
+            max_per_origin: max_per_origin,
+            origins: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the jobs recorded for the given origin, or `None` if throttling is disabled.
+    ///
+    /// Hold the returned lock from checking `OriginJobs::admit` until the new job has been
+    /// recorded, so concurrent requests for the origin can't both take its last slot.
+    pub fn origin_jobs(&self, origin: &str) -> Option<Arc<Mutex<OriginJobs>>> {
+        if self.max_per_origin == 0 {
+            return None;
+        }
+        let mut origins = self.origins.lock().expect("job throttle lock is poisoned");
+        let jobs = origins
+            .entry(origin.to_string())
+            .or_insert_with(|| Arc::new(Mutex::new(OriginJobs::new(self.max_per_origin))));
+        Some(jobs.clone())
+    }
+}
+
+impl Key for JobThrottle {
+    type Value = Self;
+}
+
+/// The jobs enqueued for one origin.
+pub struct OriginJobs {
+    max: usize,
+    jobs: Vec<u64>,
+}
+
+impl OriginJobs {
+    fn new(max: usize) -> Self {
+        OriginJobs {
+            max: max,
+            jobs: vec![],
+        }
+    }
+
+    /// Returns true if another job may be enqueued for the origin.
+    ///
+    /// The `in_flight` callback is consulted for each job previously recorded for the origin and
+    /// should return false once that job has finished, freeing its slot.
+    pub fn admit<F>(&mut self, mut in_flight: F) -> bool
+        where F: FnMut(u64) -> bool
+    {
+        self.jobs.retain(|id| in_flight(*id));
+        self.jobs.len() < self.max
+    }
+
+    /// Record a newly enqueued job for the origin.
+    pub fn record(&mut self, job_id: u64) {
+        self.jobs.push(job_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn admit_under_cap() {
+        let throttle = JobThrottle::new(2);
+        let jobs = throttle.origin_jobs("core").unwrap();
+        let mut jobs = jobs.lock().unwrap();
+        assert!(jobs.admit(|_| true));
+        jobs.record(1);
+        assert!(jobs.admit(|_| true));
+    }
+
+    #[test]
+    fn throttle_over_cap() {
+        let throttle = JobThrottle::new(2);
+        {
+            let jobs = throttle.origin_jobs("core").unwrap();
+            let mut jobs = jobs.lock().unwrap();
+            jobs.record(1);
+            jobs.record(2);
+        }
+        let jobs = throttle.origin_jobs("core").unwrap();
+        assert!(!jobs.lock().unwrap().admit(|_| true));
+        let jobs = throttle.origin_jobs("acme").unwrap();
+        assert!(jobs.lock().unwrap().admit(|_| true));
+    }
+
+    #[test]
+    fn finished_jobs_free_slots() {
+        let throttle = JobThrottle::new(2);
+        let jobs = throttle.origin_jobs("core").unwrap();
+        let mut jobs = jobs.lock().unwrap();
+        jobs.record(1);
+        jobs.record(2);
+        assert!(jobs.admit(|id| id != 1));
+        jobs.record(3);
+        assert!(!jobs.admit(|_| true));
+    }
+
+    #[test]
+    fn origins_lock_separately() {
+        let throttle = JobThrottle::new(2);
+        let core = throttle.origin_jobs("core").unwrap();
+        let _held = core.lock().unwrap();
+        assert!(throttle.origin_jobs("core").unwrap().try_lock().is_err());
+        assert!(throttle.origin_jobs("acme").unwrap().try_lock().is_ok());
+    }
+
+    #[test]
+    fn zero_cap_is_unlimited() {
+        let throttle = JobThrottle::new(0);
+        assert!(throttle.origin_jobs("core").is_none());
+    }
+}
@@ -12,7 +12,6 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-extern crate base64;
 extern crate bodyparser;
 extern crate habitat_builder_protocol as protocol;
 #[macro_use]
@@ -21,6 +20,8 @@ extern crate habitat_depot as depot;
 extern crate habitat_net as hab_net;
 extern crate hyper;
 extern crate iron;
+#[cfg(test)]
+extern crate iron_test;
 #[macro_use]
 extern crate log;
 extern crate mount;
@@ -0,0 +1,227 @@
+// Copyright (c) 2016-2017 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate habitat_builder_api as api;
+extern crate habitat_builder_protocol as protocol;
+extern crate habitat_http_test as http_test;
+extern crate habitat_net as hab_net;
+extern crate hyper;
+extern crate iron;
+#[macro_use]
+extern crate lazy_static;
+extern crate protobuf;
+extern crate zmq;
+
+mod support;
+
+use hyper::status::StatusCode;
+use protocol::jobsrv::{Job, JobSpec};
+use protocol::net::{self, ErrCode};
+use protocol::originsrv::{Origin, OriginCreate, OriginGet, OriginProject,
+                          OriginProjectCreate, OriginProjectGet};
+use protocol::sessionsrv::{Session, SessionCreate, SessionGet};
+
+const PROJECT_BODY: &'static str = r#"{
+    "origin": "bobo",
+    "plan_path": "plan.sh",
+    "github": { "organization": "bobo", "repo": "nginx" }
+}"#;
+
+fn session() -> Session {
+    let mut session = Session::new();
+    session.set_id(1);
+    session.set_name("bobo".to_string());
+    session.set_email("bobo@example.com".to_string());
+    session.set_token(support::TOKEN.to_string());
+    session
+}
+
+fn origin() -> Origin {
+    let mut origin = Origin::new();
+    origin.set_id(3);
+    origin.set_name("bobo".to_string());
+    origin.set_owner_id(1);
+    origin
+}
+
+fn project() -> OriginProject {
+    let mut project = OriginProject::new();
+    project.set_id(2);
+    project.set_origin_id(3);
+    project.set_origin_name("bobo".to_string());
+    project.set_package_name("nginx".to_string());
+    project.set_name("bobo/nginx".to_string());
+    project.set_plan_path("plan.sh".to_string());
+    project.set_owner_id(1);
+    project.set_vcs_type("git".to_string());
+    project.set_vcs_data("https://github.com/bobo/nginx.git".to_string());
+    project
+}
+
+#[test]
+fn status() {
+    let api = support::setup();
+    let (status, _) = api.get("/status");
+    assert_eq!(status, StatusCode::Ok);
+}
+
+#[test]
+fn github_authenticate() {
+    let api = support::setup();
+    api.router.setup::<SessionCreate, Session>(&session());
+    let (status, body) = api.get("/authenticate/bobo");
+    assert_eq!(status, StatusCode::Ok);
+    assert!(body.contains("bobo@example.com"));
+    let request = api.router.routed::<SessionCreate>().unwrap();
+    assert_eq!(request.get_name(), "bobo");
+}
+
+#[test]
+fn github_authenticate_denied() {
+    let api = support::setup();
+    api.router
+        .setup_error::<SessionCreate>(net::err(ErrCode::ACCESS_DENIED, "ss:auth:0"));
+    let (status, _) = api.get("/authenticate/bobo");
+    assert_eq!(status, StatusCode::Unauthorized);
+}
+
+#[test]
+fn job_create() {
+    let api = support::setup();
+    api.router.setup::<SessionGet, Session>(&session());
+    api.router.setup::<OriginProjectGet, OriginProject>(&project());
+    let mut job = Job::new();
+    job.set_id(4);
+    job.set_owner_id(1);
+    job.set_project(project());
+    api.router.setup::<JobSpec, Job>(&job);
+    let (status, _) = api.post("/jobs", r#"{"project_id": "bobo/nginx"}"#);
+    assert_eq!(status, StatusCode::Created);
+    let spec = api.router.routed::<JobSpec>().unwrap();
+    assert_eq!(spec.get_owner_id(), 1);
+    assert_eq!(spec.get_project().get_name(), "bobo/nginx");
+}
+
+#[test]
+fn job_create_unknown_project() {
+    let api = support::setup();
+    api.router.setup::<SessionGet, Session>(&session());
+    let err = net::err(ErrCode::ENTITY_NOT_FOUND, "vt:origin-project-get:1");
+    api.router.setup_error::<OriginProjectGet>(err);
+    let (status, _) = api.post("/jobs", r#"{"project_id": "bobo/nope"}"#);
+    assert_eq!(status, StatusCode::NotFound);
+    assert!(api.router.routed::<JobSpec>().is_none());
+}
+
+#[test]
+fn project_create() {
+    let api = support::setup();
+    api.router.setup::<SessionGet, Session>(&session());
+    api.router.setup::<OriginGet, Origin>(&origin());
+    api.router.setup::<OriginProjectCreate, OriginProject>(&project());
+    api.github
+        .respond("/repos/bobo/nginx",
+                 200,
+                 include_str!("fixtures/github/repo.json"));
+    api.github
+        .respond("/repos/bobo/nginx/contents/plan.sh",
+                 200,
+                 include_str!("fixtures/github/plan_contents.json"));
+    let (status, _) = api.post("/projects", PROJECT_BODY);
+    assert_eq!(status, StatusCode::Created);
+    let request = api.router.routed::<OriginProjectCreate>().unwrap();
+    assert_eq!(request.get_project().get_origin_id(), 3);
+    assert_eq!(request.get_project().get_package_name(), "nginx");
+    assert_eq!(request.get_project().get_vcs_data(),
+               "https://github.com/bobo/nginx.git");
+}
+
+#[test]
+fn project_create_missing_origin() {
+    let api = support::setup();
+    api.router.setup::<SessionGet, Session>(&session());
+    let body = PROJECT_BODY.replace(r#""origin": "bobo""#, r#""origin": """#);
+    let (status, body) = api.post("/projects", &body);
+    assert_eq!(status, StatusCode::UnprocessableEntity);
+    assert_eq!(body, "Missing value for field: `origin`");
+}
+
+#[test]
+fn project_create_unknown_repo() {
+    let api = support::setup();
+    api.router.setup::<SessionGet, Session>(&session());
+    let (status, body) = api.post("/projects", PROJECT_BODY);
+    assert_eq!(status, StatusCode::UnprocessableEntity);
+    assert_eq!(body, "rg:pc:1");
+    assert!(api.router.routed::<OriginProjectCreate>().is_none());
+}
+
+#[test]
+fn project_create_with_new_origin() {
+    let api = support::setup();
+    api.router.setup::<SessionGet, Session>(&session());
+    let err = net::err(ErrCode::ENTITY_NOT_FOUND, "vt:origin-get:1");
+    api.router.setup_error::<OriginGet>(err);
+    api.router.setup::<OriginCreate, Origin>(&origin());
+    api.router.setup::<OriginProjectCreate, OriginProject>(&project());
+    api.github
+        .respond("/repos/bobo/nginx",
+                 200,
+                 include_str!("fixtures/github/repo.json"));
+    api.github
+        .respond("/repos/bobo/nginx/contents/plan.sh",
+                 200,
+                 include_str!("fixtures/github/plan_contents.json"));
+    let (status, _) = api.post("/projects?create_origin=true", PROJECT_BODY);
+    assert_eq!(status, StatusCode::Created);
+    let create = api.router.routed::<OriginCreate>().unwrap();
+    assert_eq!(create.get_name(), "bobo");
+    assert_eq!(create.get_owner_id(), 1);
+    let request = api.router.routed::<OriginProjectCreate>().unwrap();
+    assert_eq!(request.get_project().get_origin_id(), 3);
+}
+
+#[test]
+fn project_create_with_invalid_new_origin() {
+    let api = support::setup();
+    api.router.setup::<SessionGet, Session>(&session());
+    let body = PROJECT_BODY.replace(r#""origin": "bobo""#, r#""origin": "-bobo""#);
+    let (status, body) = api.post("/projects?create_origin=true", &body);
+    assert_eq!(status, StatusCode::UnprocessableEntity);
+    assert_eq!(body, "Invalid value for field: `origin`");
+    assert!(api.router.routed::<OriginGet>().is_none());
+    assert!(api.router.routed::<OriginCreate>().is_none());
+}
+
+#[test]
+fn project_create_with_invalid_plan_creates_no_origin() {
+    let api = support::setup();
+    api.router.setup::<SessionGet, Session>(&session());
+    let err = net::err(ErrCode::ENTITY_NOT_FOUND, "vt:origin-get:1");
+    api.router.setup_error::<OriginGet>(err);
+    api.router.setup::<OriginCreate, Origin>(&origin());
+    api.github
+        .respond("/repos/bobo/nginx",
+                 200,
+                 include_str!("fixtures/github/repo.json"));
+    api.github
+        .respond("/repos/bobo/nginx/contents/plan.sh",
+                 200,
+                 include_str!("fixtures/github/invalid_plan_contents.json"));
+    let (status, body) = api.post("/projects?create_origin=true", PROJECT_BODY);
+    assert_eq!(status, StatusCode::UnprocessableEntity);
+    assert!(body.contains("pkg_name"));
+    assert!(api.router.routed::<OriginGet>().is_none());
+    assert!(api.router.routed::<OriginCreate>().is_none());
+}
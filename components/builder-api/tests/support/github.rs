@@ -0,0 +1,63 @@
+// Copyright (c) 2016-2017 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A stand-in for the GitHub API which answers requests from a table of canned responses keyed
+//! by path. Unknown paths are answered with a `404 Not Found`, like GitHub does for repositories
+//! and files which don't exist.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use http_test::{MockServer, Response};
+
+/// Path prefix of the API of a GitHub Enterprise server, which the mock is configured as.
+pub const API_PREFIX: &'static str = "/api/v3";
+
+pub struct MockGitHub {
+    responses: Arc<Mutex<HashMap<String, (u16, String)>>>,
+    server: MockServer,
+}
+
+impl MockGitHub {
+    /// Listen on a free local port and serve requests in a separate thread.
+    pub fn start() -> Self {
+        let responses: Arc<Mutex<HashMap<String, (u16, String)>>> =
+            Arc::new(Mutex::new(HashMap::new()));
+        let table = responses.clone();
+        let server = MockServer::start(move |req| {
+            match table.lock().unwrap().get(req.path()) {
+                Some(&(status, ref body)) => Response::json(&format!("{} Mock", status), body),
+                None => Response::json("404 Not Found", r#"{"message":"Not Found"}"#),
+            }
+        });
+        MockGitHub {
+            responses: responses,
+            server: server,
+        }
+    }
+
+    /// Root URL of the mock server, to be used as the GitHub URL of the API's configuration.
+    pub fn url(&self) -> &str {
+        self.server.url()
+    }
+
+    /// Answer requests for the given API path, such as `/repos/org/repo`, with the given status
+    /// and JSON body.
+    pub fn respond(&self, path: &str, status: u16, body: &str) {
+        self.responses
+            .lock()
+            .unwrap()
+            .insert(format!("{}{}", API_PREFIX, path), (status, body.to_string()));
+    }
+}
@@ -0,0 +1,115 @@
+// Copyright (c) 2016-2017 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Harness running the API's router over HTTP against a mock `RouteSrv` and a mock GitHub.
+//!
+//! The application's `Broker` is a process-wide singleton, so a single mock router serves every
+//! test and tests hold a lock for their duration to keep their canned replies apart.
+
+pub mod github;
+pub mod router;
+
+use std::env;
+use std::io::Read;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use api::Config;
+use hab_net::routing::Broker;
+use hyper;
+use hyper::header::{Authorization, Bearer, ContentType};
+use hyper::status::StatusCode;
+use hyper::server::Listening;
+use iron::Iron;
+
+use self::github::MockGitHub;
+use self::router::MockRouter;
+
+/// Token sent as the bearer token of authenticated requests.
+pub const TOKEN: &'static str = "bobo";
+
+lazy_static! {
+    static ref ROUTER: MockRouter = {
+        // Lets `github_authenticate` create sessions for the stub users without a GitHub round
+        // trip. Set once, before any test's API starts, as tests run on several threads.
+        env::set_var("HAB_FUNC_TEST", "1");
+        let router = MockRouter::start();
+        Broker::run("builder-api-test".to_string(), &vec![router.addr()]);
+        router
+    };
+    static ref LOCK: Mutex<()> = Mutex::new(());
+}
+
+pub struct TestApi {
+    pub router: MockRouter,
+    pub github: MockGitHub,
+    url: String,
+    listening: Listening,
+    _lock: MutexGuard<'static, ()>,
+}
+
+/// Start the API with a fresh mock GitHub. Any replies set up on the mock router by earlier tests
+/// are forgotten.
+pub fn setup() -> TestApi {
+    let lock = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    ROUTER.reset();
+    let github = MockGitHub::start();
+    let mut config = Config::default();
+    config.github.url = github.url().parse().unwrap();
+    config.log_dir = env::temp_dir().to_string_lossy().into_owned();
+    let chain = api::http::router(Arc::new(config)).unwrap();
+    let listening = Iron::new(chain).http("127.0.0.1:0").unwrap();
+    TestApi {
+        router: ROUTER.clone(),
+        github: github,
+        url: format!("http://{}", listening.socket),
+        listening: listening,
+        _lock: lock,
+    }
+}
+
+impl TestApi {
+    /// Send an unauthenticated `GET` and return the status and body of the response.
+    pub fn get(&self, path: &str) -> (StatusCode, String) {
+        let client = hyper::Client::new();
+        let res = client.get(&format!("{}{}", self.url, path)).send().unwrap();
+        read_response(res)
+    }
+
+    /// Send an authenticated `POST` of a JSON body and return the status and body of the
+    /// response.
+    pub fn post(&self, path: &str, body: &str) -> (StatusCode, String) {
+        let client = hyper::Client::new();
+        let res = client
+            .post(&format!("{}{}", self.url, path))
+            .header(Authorization(Bearer { token: TOKEN.to_string() }))
+            .header(ContentType::json())
+            .body(body)
+            .send()
+            .unwrap();
+        read_response(res)
+    }
+}
+
+impl Drop for TestApi {
+    fn drop(&mut self) {
+        // Stop serving before the lock is released to the next test.
+        self.listening.close().unwrap();
+    }
+}
+
+fn read_response(mut res: hyper::client::Response) -> (StatusCode, String) {
+    let mut body = String::new();
+    res.read_to_string(&mut body).unwrap();
+    (res.status, body)
+}
@@ -0,0 +1,133 @@
+// Copyright (c) 2016-2017 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A stand-in for `RouteSrv` which answers every routed message from a table of canned replies,
+//! so that the API's `Broker` can be exercised without any backend services.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use hab_net::config::RouterAddr;
+use http_test::free_port;
+use protobuf::{self, parse_from_bytes, Message};
+use protocol;
+use protocol::net::{self, ErrCode, NetError};
+use zmq;
+
+#[derive(Clone)]
+pub struct MockRouter {
+    replies: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+    routed: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+    addr: RouterAddr,
+}
+
+impl MockRouter {
+    /// Bind the router to a free local port and answer messages in a separate thread.
+    pub fn start() -> Self {
+        let port = free_port();
+        let ctx = zmq::Context::new();
+        let sock = ctx.socket(zmq::ROUTER).unwrap();
+        sock.bind(&format!("tcp://127.0.0.1:{}", port)).unwrap();
+        let router = MockRouter {
+            replies: Arc::new(Mutex::new(HashMap::new())),
+            routed: Arc::new(Mutex::new(HashMap::new())),
+            addr: RouterAddr {
+                host: IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+                port: port,
+                heartbeat: port,
+            },
+        };
+        let replies = router.replies.clone();
+        let routed = router.routed.clone();
+        thread::Builder::new()
+            .name("mock-router".to_string())
+            .spawn(move || {
+                // Keep the context alive for as long as the socket is in use.
+                let _ctx = ctx;
+                loop {
+                    let frames = sock.recv_multipart(0).unwrap();
+                    let hops = frames.iter().position(|f| f.is_empty()).unwrap();
+                    let msg: net::Msg = parse_from_bytes(frames.last().unwrap()).unwrap();
+                    let id = msg.get_message_id().to_string();
+                    routed
+                        .lock()
+                        .unwrap()
+                        .insert(id.clone(), msg.get_body().to_vec());
+                    let reply = match replies.lock().unwrap().get(&id) {
+                        Some(reply) => reply.clone(),
+                        None => {
+                            let err = net::err(ErrCode::BUG, format!("mock-router:{}", id));
+                            encode(&err)
+                        }
+                    };
+                    for hop in &frames[..hops] {
+                        sock.send(&hop[..], zmq::SNDMORE).unwrap();
+                    }
+                    sock.send(&[], zmq::SNDMORE).unwrap();
+                    sock.send(&reply[..], 0).unwrap();
+                }
+            })
+            .unwrap();
+        router
+    }
+
+    pub fn addr(&self) -> RouterAddr {
+        self.addr.clone()
+    }
+
+    /// Answer every `M` routed from now on with the given reply.
+    pub fn setup<M: protobuf::MessageStatic, R: Message>(&self, reply: &R) {
+        self.replies
+            .lock()
+            .unwrap()
+            .insert(message_id::<M>(), encode(reply));
+    }
+
+    /// Answer every `M` routed from now on with the given error.
+    pub fn setup_error<M: protobuf::MessageStatic>(&self, err: NetError) {
+        self.replies
+            .lock()
+            .unwrap()
+            .insert(message_id::<M>(), encode(&err));
+    }
+
+    /// Returns the last `M` routed, if any.
+    pub fn routed<M: protobuf::MessageStatic>(&self) -> Option<M> {
+        self.routed
+            .lock()
+            .unwrap()
+            .get(&message_id::<M>())
+            .map(|body| parse_from_bytes(body).unwrap())
+    }
+
+    /// Forget all replies and routed messages.
+    pub fn reset(&self) {
+        self.replies.lock().unwrap().clear();
+        self.routed.lock().unwrap().clear();
+    }
+}
+
+fn message_id<M: protobuf::MessageStatic>() -> String {
+    M::descriptor_static(None).name().to_string()
+}
+
+fn encode<M: Message>(msg: &M) -> Vec<u8> {
+    protocol::Message::new(msg)
+        .build()
+        .write_to_bytes()
+        .unwrap()
+}
+
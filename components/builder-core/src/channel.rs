@@ -0,0 +1,152 @@
+// Copyright (c) 2016-2017 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Release channel names, validated the same way wherever Builder accepts one.
+
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+/// Channel every package is placed in when it is uploaded by Builder.
+pub const UNSTABLE_CHANNEL: &'static str = "unstable";
+/// Channel for packages which have been promoted for general consumption.
+pub const STABLE_CHANNEL: &'static str = "stable";
+/// Maximum number of characters in a channel name.
+pub const MAX_CHANNEL_NAME_LEN: usize = 255;
+
+/// Returns true if the given string is a valid channel name.
+///
+/// Channel names follow the same rules as origin names. They are made of lowercase letters,
+/// digits, `_` and `-`, must start with a letter or digit, and may be at most
+/// `MAX_CHANNEL_NAME_LEN` characters long.
+pub fn is_valid_channel_name(name: &str) -> bool {
+    if name.chars().count() > MAX_CHANNEL_NAME_LEN {
+        return false;
+    }
+    let mut chars = name.chars();
+    match chars.next() {
+        Some('a'...'z') | Some('0'...'9') => (),
+        _ => return false,
+    }
+    chars.all(|c| match c {
+                  'a'...'z' | '0'...'9' | '_' | '-' => true,
+                  _ => false,
+              })
+}
+
+/// A validated release channel name.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct ChannelName(String);
+
+impl ChannelName {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for ChannelName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for ChannelName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for ChannelName {
+    type Err = InvalidChannelName;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if is_valid_channel_name(value) {
+            Ok(ChannelName(value.to_string()))
+        } else {
+            Err(InvalidChannelName(value.to_string()))
+        }
+    }
+}
+
+/// Returned when parsing a string which is not a valid channel name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidChannelName(pub String);
+
+impl fmt::Display for InvalidChannelName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+               "Invalid channel name: {:?}. Channel names may only contain lowercase letters, \
+                digits, '_' and '-', and must start with a letter or digit.",
+               self.0)
+    }
+}
+
+impl error::Error for InvalidChannelName {
+    fn description(&self) -> &str {
+        "Invalid channel name"
+    }
+}
+
+/// Channel names exercised by the tests of every component which validates channels, so that
+/// they stay in agreement about which names are valid. All of them can be used in a URL path
+/// without escaping.
+#[doc(hidden)]
+pub const VALID_CHANNEL_NAMES: &'static [&'static str] =
+    &["stable", "unstable", "bldr-812305617041510400", "my_channel", "2017"];
+#[doc(hidden)]
+pub const INVALID_CHANNEL_NAMES: &'static [&'static str] =
+    &["Stable", "-unstable", "_unstable", "my.channel", "my+channel"];
+
+#[cfg(test)]
+mod test {
+    use std::iter;
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn valid_channel_names() {
+        for name in VALID_CHANNEL_NAMES {
+            assert!(is_valid_channel_name(name), "expected {:?} to be valid", name);
+            assert_eq!(ChannelName::from_str(name).unwrap().as_str(), *name);
+        }
+    }
+
+    #[test]
+    fn invalid_channel_names() {
+        for name in INVALID_CHANNEL_NAMES {
+            assert!(!is_valid_channel_name(name),
+                    "expected {:?} to be invalid",
+                    name);
+            assert_eq!(ChannelName::from_str(name),
+                       Err(InvalidChannelName(name.to_string())));
+        }
+    }
+
+    #[test]
+    fn invalid_unescaped_channel_names() {
+        for name in &["", "my channel", "my/channel", "chän"] {
+            assert!(!is_valid_channel_name(name),
+                    "expected {:?} to be invalid",
+                    name);
+        }
+    }
+
+    #[test]
+    fn channel_name_length_limit() {
+        let longest: String = iter::repeat('a').take(MAX_CHANNEL_NAME_LEN).collect();
+        assert!(is_valid_channel_name(&longest));
+        assert!(!is_valid_channel_name(&format!("{}a", longest)));
+    }
+}
@@ -21,6 +21,7 @@ extern crate time;
 extern crate petgraph;
 extern crate walkdir;
 
+pub mod channel;
 pub mod metrics;
 pub mod rdeps;
 pub mod package_graph;
@@ -12,8 +12,9 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::cell::RefCell;
 use std::sync::{Once, ONCE_INIT};
-use std::sync::mpsc::{channel, sync_channel, Sender, Receiver, SyncSender};
+use std::sync::mpsc::{channel, sync_channel, Sender, SendError, Receiver, SyncSender};
 use std::thread;
 use statsd::Client;
 use hab_core::env;
@@ -28,6 +29,9 @@ pub const STATS_ENV: &'static str = "HAB_STATS_ADDR";
 #[derive(Debug, Clone)]
 pub enum Counter {
     SearchPackages,
+    // Downloads of any release of the named package, tagged with its origin and name. Versions
+    // and releases are left out to keep the number of distinct metrics bounded.
+    PackageDownload(String, String),
 }
 
 // Supported metrics
@@ -50,12 +54,12 @@ enum MetricOperation {
     SetValue,
 }
 
-type MetricId = &'static str;
+type MetricId = String;
 type MetricValue = f64;
 type MetricTuple = (MetricType, MetricOperation, MetricId, Option<MetricValue>);
 
 trait Metric {
-    fn id(&self) -> &'static str;
+    fn id(&self) -> MetricId;
 }
 
 // One-time initialization
@@ -63,6 +67,54 @@ static mut SENDER: *const Sender<MetricTuple> = 0 as *const Sender<MetricTuple>;
 
 static INIT: Once = ONCE_INIT;
 
+thread_local!(static RECORDED: RefCell<Option<Vec<MetricTuple>>> = RefCell::new(None));
+
+/// Records the metrics sent from the current thread in memory, instead of sending them to statsd,
+/// until the returned `Recorder` is dropped. Intended for tests.
+pub fn record() -> Recorder {
+    RECORDED.with(|r| *r.borrow_mut() = Some(vec![]));
+    Recorder
+}
+
+pub struct Recorder;
+
+impl Recorder {
+    /// Returns how many times the given counter has been incremented while recording.
+    pub fn increments(&self, counter: &Counter) -> usize {
+        let id = counter.id();
+        RECORDED.with(|r| {
+            r.borrow()
+                .iter()
+                .flat_map(|metrics| metrics.iter())
+                .filter(|&&(_, op, ref mid, _)| {
+                            *mid == id &&
+                            match op {
+                                MetricOperation::Increment => true,
+                                _ => false,
+                            }
+                        })
+                .count()
+        })
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        RECORDED.with(|r| *r.borrow_mut() = None);
+    }
+}
+
+fn send(metric: MetricTuple) -> Result<(), SendError<MetricTuple>> {
+    let mut metric = Some(metric);
+    RECORDED.with(|r| if let Some(ref mut metrics) = *r.borrow_mut() {
+                      metrics.push(metric.take().unwrap());
+                  });
+    match metric {
+        Some(metric) => sender().send(metric),
+        None => Ok(()),
+    }
+}
+
 fn sender() -> Sender<MetricTuple> {
     unsafe {
         INIT.call_once(|| { SENDER = Box::into_raw(Box::new(init())); });
@@ -101,14 +153,14 @@ fn receive(rz: SyncSender<()>, rx: Receiver<MetricTuple>) {
                 match mtyp {
                     MetricType::Counter => {
                         match mop {
-                            MetricOperation::Increment => cli.incr(mid),
-                            MetricOperation::Decrement => cli.decr(mid),
+                            MetricOperation::Increment => cli.incr(&mid),
+                            MetricOperation::Decrement => cli.decr(&mid),
                             _ => error!("Unexpected metric operation: {:?}", mop),
                         }
                     }
                     MetricType::Gauge => {
                         match mop {
-                            MetricOperation::SetValue => cli.gauge(mid, mval.unwrap()),
+                            MetricOperation::SetValue => cli.gauge(&mid, mval.unwrap()),
                             _ => error!("Unexpected metric operation: {:?}", mop),
                         }
                     }
@@ -136,14 +188,14 @@ fn statsd_client() -> Option<Client> {
 
 impl Counter {
     pub fn increment(&self) {
-        match sender().send((MetricType::Counter, MetricOperation::Increment, &self.id(), None)) {
+        match send((MetricType::Counter, MetricOperation::Increment, self.id(), None)) {
             Ok(_) => (),
             Err(e) => error!("Failed to increment counter, error: {:?}", e),
         }
     }
 
     pub fn decrement(&self) {
-        match sender().send((MetricType::Counter, MetricOperation::Decrement, &self.id(), None)) {
+        match send((MetricType::Counter, MetricOperation::Decrement, self.id(), None)) {
             Ok(_) => (),
             Err(e) => error!("Failed to decrement counter, error: {:?}", e),
         }
@@ -152,7 +204,7 @@ impl Counter {
 
 impl Gauge {
     pub fn set(&self, val: f64) {
-        match sender().send((MetricType::Gauge, MetricOperation::SetValue, &self.id(), Some(val))) {
+        match send((MetricType::Gauge, MetricOperation::SetValue, self.id(), Some(val))) {
             Ok(_) => (),
             Err(e) => error!("Failed to set gauge, error: {:?}", e),
         }
@@ -160,24 +212,27 @@ impl Gauge {
 }
 
 impl Metric for Counter {
-    fn id(&self) -> &'static str {
+    fn id(&self) -> MetricId {
         match *self {
-            Counter::SearchPackages => "search-packages",
+            Counter::SearchPackages => "search-packages".to_string(),
+            Counter::PackageDownload(ref origin, ref name) => {
+                format!("package-download.{}.{}", origin, name)
+            }
         }
     }
 }
 
 impl Metric for Gauge {
-    fn id(&self) -> &'static str {
+    fn id(&self) -> MetricId {
         match *self {
-            Gauge::PackageCount => "package-count",
+            Gauge::PackageCount => "package-count".to_string(),
         }
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::{Counter, Gauge};
+    use super::{record, Counter, Gauge};
     use metrics::Metric;
     use std::time::Duration;
     use std::thread;
@@ -189,6 +244,24 @@ mod test {
         assert!(disp == expected);
     }
 
+    #[test]
+    fn package_download_counter_id() {
+        let counter = Counter::PackageDownload("core".to_string(), "nginx".to_string());
+        assert_eq!(counter.id(), "package-download.core.nginx");
+    }
+
+    #[test]
+    fn recorder_counts_increments() {
+        let core_nginx = Counter::PackageDownload("core".to_string(), "nginx".to_string());
+        let core_redis = Counter::PackageDownload("core".to_string(), "redis".to_string());
+        let recorder = record();
+        core_nginx.increment();
+        core_nginx.increment();
+        core_nginx.decrement();
+        assert_eq!(recorder.increments(&core_nginx), 2);
+        assert_eq!(recorder.increments(&core_redis), 0);
+    }
+
     #[test]
     fn guage_id() {
         let expected = r#"package-count"#;
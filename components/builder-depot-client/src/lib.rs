@@ -15,6 +15,8 @@
 extern crate habitat_builder_protocol as protocol;
 extern crate habitat_core as hab_core;
 extern crate habitat_http_client as hab_http;
+#[cfg(test)]
+extern crate habitat_http_test as http_test;
 extern crate broadcast;
 #[macro_use]
 extern crate hyper;
@@ -38,6 +40,7 @@ use std::fs::{self, File};
 use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
 use std::string::ToString;
+use std::time::Duration;
 
 use broadcast::BroadcastWriter;
 use hab_core::package::{Identifiable, PackageArchive};
@@ -194,6 +197,21 @@ impl Client {
         Ok(Client { inner: try!(ApiClient::new(&url, product, version, fs_root_path)) })
     }
 
+    /// Returns the URL of the Depot the client talks to.
+    pub fn endpoint(&self) -> &Url {
+        self.inner.endpoint()
+    }
+
+    /// Returns the read and write timeout of requests to the Depot.
+    pub fn timeout(&self) -> Duration {
+        self.inner.timeout()
+    }
+
+    /// Sets the read and write timeout of requests to the Depot.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.inner.set_timeout(timeout)
+    }
+
     /// Download a public key from a remote Depot to the given filepath.
     ///
     /// # Failures
@@ -405,6 +423,39 @@ impl Client {
         Ok(package)
     }
 
+    /// Returns whether the given fully qualified package exists on the remote Depot. If a channel
+    /// is given, the package must also be a member of that channel.
+    ///
+    /// # Failures
+    ///
+    /// * Remote Depot is not available
+    ///
+    /// # Panics
+    ///
+    /// * If package ident does not have a version/release
+    pub fn package_exists<I: Identifiable>(&self,
+                                           ident: &I,
+                                           channel: Option<&str>)
+                                           -> Result<bool> {
+        let path = match channel {
+            Some(channel) => {
+                format!("channels/{}/{}/pkgs/{}/{}/{}",
+                        ident.origin(),
+                        channel,
+                        ident.name(),
+                        ident.version().unwrap(),
+                        ident.release().unwrap())
+            }
+            None => format!("pkgs/{}", ident),
+        };
+        let res = try!(self.inner.head(&path).send());
+        match res.status {
+            StatusCode::Ok => Ok(true),
+            StatusCode::NotFound => Ok(false),
+            _ => Err(err_from_response(res)),
+        }
+    }
+
     /// Upload a package to a remote Depot.
     ///
     /// # Failures
@@ -448,7 +499,20 @@ impl Client {
         }
     }
 
-    pub fn x_put_package(&self, pa: &mut PackageArchive, token: &str) -> Result<()> {
+    /// Upload a package to a remote Depot on behalf of a Builder worker. Dependent packages will
+    /// not be scheduled for a rebuild.
+    ///
+    /// # Failures
+    ///
+    /// * Remote Depot is not available
+    /// * File cannot be read
+    pub fn x_put_package<D>(&self,
+                            pa: &mut PackageArchive,
+                            token: &str,
+                            progress: Option<D>)
+                            -> Result<()>
+        where D: DisplayProgress + Sized
+    {
         let checksum = try!(pa.checksum());
         let ident = try!(pa.ident());
         let mut file = try!(File::open(&pa.path));
@@ -461,9 +525,17 @@ impl Client {
         };
         debug!("Reading from {}", &pa.path.display());
 
-        let result = self.add_authz(self.inner.post_with_custom_url(&path, custom), token)
-            .body(Body::SizedBody(&mut file, file_size))
-            .send();
+        let result = if let Some(mut progress) = progress {
+            progress.size(file_size);
+            let mut reader = TeeReader::new(file, progress);
+            self.add_authz(self.inner.post_with_custom_url(&path, custom), token)
+                .body(Body::SizedBody(&mut reader, file_size))
+                .send()
+        } else {
+            self.add_authz(self.inner.post_with_custom_url(&path, custom), token)
+                .body(Body::SizedBody(&mut file, file_size))
+                .send()
+        };
         match result {
             Ok(Response { status: StatusCode::Created, .. }) => Ok(()),
             Ok(response) => Err(err_from_response(response)),
@@ -603,8 +675,126 @@ fn err_from_response(mut response: hyper::client::Response) -> Error {
 
 #[cfg(test)]
 mod tests {
+    use std::str::FromStr;
+    use std::sync::{Arc, Mutex};
+
+    use hab_core::package::PackageIdent;
+    use http_test::{MockServer, Response};
+
     use super::*;
 
+    struct RecordingProgress {
+        size: Arc<Mutex<u64>>,
+        sent: Arc<Mutex<Vec<u64>>>,
+    }
+
+    impl DisplayProgress for RecordingProgress {
+        fn size(&mut self, size: u64) {
+            *self.size.lock().unwrap() = size;
+        }
+
+        fn finish(&mut self) {}
+    }
+
+    impl Write for RecordingProgress {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let mut sent = self.sent.lock().unwrap();
+            let total = sent.last().cloned().unwrap_or(0) + buf.len() as u64;
+            sent.push(total);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn hart_file(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("..")
+            .join("builder-depot")
+            .join("tests")
+            .join("fixtures")
+            .join(name)
+    }
+
+    #[test]
+    fn x_put_package_reports_progress() {
+        let depot = MockServer::respond_with(vec![Response::new("201 Created")]);
+        let url = format!("{}/v1/depot", depot.url());
+        let client = Client::new(url.as_str(), "test", "0.0.0", None).unwrap();
+        let hart = hart_file("core-cacerts-2017.01.17-20170209064044-x86_64-windows.hart");
+        let mut archive = PackageArchive::new(hart);
+        let file_size = fs::metadata(&archive.path).unwrap().len();
+        let size = Arc::new(Mutex::new(0));
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let progress = RecordingProgress {
+            size: size.clone(),
+            sent: sent.clone(),
+        };
+
+        client
+            .x_put_package(&mut archive, "token", Some(progress))
+            .unwrap();
+
+        assert_eq!(depot.requests()[0].body.len() as u64, file_size);
+        let sent = sent.lock().unwrap();
+        assert_eq!(*size.lock().unwrap(), file_size);
+        assert!(!sent.is_empty());
+        assert!(sent.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(*sent.last().unwrap(), file_size);
+    }
+
+    fn test_client(depot: &MockServer) -> Client {
+        let url = format!("{}/v1/depot", depot.url());
+        Client::new(url.as_str(), "test", "0.0.0", None).unwrap()
+    }
+
+    fn ident() -> PackageIdent {
+        PackageIdent::from_str("core/redis/3.2.4/20170514150022").unwrap()
+    }
+
+    #[test]
+    fn package_exists_found() {
+        let depot = MockServer::respond_with(vec![Response::new("200 OK")]);
+        assert!(test_client(&depot).package_exists(&ident(), None).unwrap());
+        assert_eq!(depot.request_lines(),
+                   vec!["HEAD /v1/depot/pkgs/core/redis/3.2.4/20170514150022 HTTP/1.1"]);
+    }
+
+    #[test]
+    fn package_exists_in_channel() {
+        let depot = MockServer::respond_with(vec![Response::new("200 OK")]);
+        assert!(test_client(&depot)
+                    .package_exists(&ident(), Some("stable"))
+                    .unwrap());
+        assert_eq!(depot.request_lines(),
+                   vec!["HEAD /v1/depot/channels/core/stable/pkgs/redis/3.2.4/20170514150022 \
+                         HTTP/1.1"]);
+    }
+
+    #[test]
+    fn package_exists_not_found() {
+        let depot = MockServer::respond_with(vec![Response::new("404 Not Found")]);
+        assert!(!test_client(&depot).package_exists(&ident(), None).unwrap());
+    }
+
+    #[test]
+    fn package_exists_error_status() {
+        let depot = MockServer::respond_with(vec![Response::new("503 Service Unavailable")]);
+        match test_client(&depot).package_exists(&ident(), None) {
+            Err(Error::APIError(StatusCode::ServiceUnavailable, _)) => (),
+            e => panic!("Unexpected result: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn package_exists_unreachable() {
+        let url = format!("{}/v1/depot", http_test::unreachable_url());
+        let client = Client::new(url.as_str(), "test", "0.0.0", None).unwrap();
+        assert!(client.package_exists(&ident(), None).is_err());
+    }
+
     #[test]
     fn json_round_trip_u64_fields() {
         let pre = OriginSecretKey {
@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::HashSet;
 use std::env;
 use std::io;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr, ToSocketAddrs};
@@ -20,7 +21,8 @@ use std::option::IntoIter;
 use hab_core::config::ConfigFile;
 use hab_core::os::system::{Architecture, Platform};
 use hab_core::package::PackageTarget;
-use hab_net::config::{GitHubCfg, GitHubOAuth, RouterAddr, RouterCfg};
+use hab_core::package::target::deserialize_targets;
+use hab_net::config::{GitHubCfg, GitHubEndpoint, GitHubOAuth, RouterAddr, RouterCfg};
 
 use error::Error;
 
@@ -33,6 +35,9 @@ pub struct Config {
     pub github: GitHubCfg,
     /// Disable authenticated uploads for all entities
     pub insecure: bool,
+    /// Reject uploaded packages signed by an origin key the Depot doesn't have. Signatures made
+    /// with a key the Depot does have are always verified.
+    pub require_signed_uploads: bool,
     /// Filepath to location on disk to store entities
     pub path: String,
     /// Whether to log events for funnel metrics
@@ -41,12 +46,19 @@ pub struct Config {
     pub builds_enabled: bool,
     /// Filepath to where log events for funnel metrics will be recorded
     pub log_dir: String,
-    /// A list of package platform and architecture combinations which can be uploaded and hosted
-    pub targets: Vec<PackageTarget>,
+    /// The package platform and architecture combinations which can be uploaded and hosted,
+    /// accepting the same spellings as the worker's `supported_targets`
+    #[serde(deserialize_with = "deserialize_targets")]
+    pub targets: HashSet<PackageTarget>,
 }
 
 impl ConfigFile for Config {
     type Error = Error;
+    const ENV_PREFIX: &'static str = "HAB_DEPOT";
+
+    fn service_name() -> &'static str {
+        "hab-depot"
+    }
 }
 
 impl Default for Config {
@@ -57,17 +69,20 @@ impl Default for Config {
             github: GitHubCfg::default(),
             path: "/hab/svc/hab-depot/data".to_string(),
             insecure: false,
+            require_signed_uploads: false,
             events_enabled: false, // TODO: change to default to true later
             builds_enabled: false,
             log_dir: env::temp_dir().to_string_lossy().into_owned(),
             targets: vec![PackageTarget::new(Platform::Linux, Architecture::X86_64),
-                          PackageTarget::new(Platform::Windows, Architecture::X86_64)],
+                          PackageTarget::new(Platform::Windows, Architecture::X86_64)]
+                    .into_iter()
+                    .collect(),
         }
     }
 }
 
 impl GitHubOAuth for Config {
-    fn github_url(&self) -> &str {
+    fn github_url(&self) -> &GitHubEndpoint {
         &self.github.url
     }
 
@@ -78,6 +93,10 @@ impl GitHubOAuth for Config {
     fn github_client_secret(&self) -> &str {
         &self.github.client_secret
     }
+
+    fn github_idle_timeout(&self) -> u64 {
+        self.github.idle_timeout
+    }
 }
 
 impl RouterCfg for Config {
@@ -122,6 +141,7 @@ mod tests {
         let content = r#"
         path = "/hab/svc/hab-depot/data"
         insecure = true
+        require_signed_uploads = true
         builds_enabled = true
         events_enabled = true
         log_dir = "/hab/svc/hab-depot/var/log"
@@ -152,21 +172,41 @@ mod tests {
         let config = Config::from_raw(&content).unwrap();
         assert_eq!(config.path, "/hab/svc/hab-depot/data");
         assert_eq!(config.insecure, true);
+        assert_eq!(config.require_signed_uploads, true);
         assert_eq!(config.builds_enabled, true);
         assert_eq!(config.events_enabled, true);
         assert_eq!(config.log_dir, "/hab/svc/hab-depot/var/log");
         assert_eq!(&format!("{}", config.http.listen), "127.0.0.1");
         assert_eq!(config.http.port, 9000);
         assert_eq!(&format!("{}", config.routers[0]), "172.18.0.2:9001");
-        assert_eq!(config.github.url, "https://api.github.com");
+        assert_eq!(config.github.url.api_base(), "https://api.github.com");
         assert_eq!(config.github.client_id, "0c2f738a7d0bd300de10");
         assert_eq!(config.github.client_secret,
                    "438223113eeb6e7edf2d2f91a232b72de72b9bdf");
         assert_eq!(config.targets.len(), 2);
-        assert_eq!(config.targets[0].platform, Platform::Linux);
-        assert_eq!(config.targets[0].architecture, Architecture::X86_64);
-        assert_eq!(config.targets[1].platform, Platform::Windows);
-        assert_eq!(config.targets[1].architecture, Architecture::X86_64);
+        assert!(config
+                    .targets
+                    .contains(&PackageTarget::new(Platform::Linux, Architecture::X86_64)));
+        assert!(config
+                    .targets
+                    .contains(&PackageTarget::new(Platform::Windows, Architecture::X86_64)));
+    }
+
+    #[test]
+    fn config_from_file_canonicalizes_targets() {
+        let content = r#"
+        targets = ["amd64-linux", "x86_64-pc-windows-msvc"]
+        "#;
+
+        let config = Config::from_raw(&content).unwrap();
+        assert_eq!(config.targets.len(), 2);
+        assert!(config
+                    .targets
+                    .contains(&PackageTarget::new(Platform::Linux, Architecture::X86_64)));
+        assert!(config
+                    .targets
+                    .contains(&PackageTarget::new(Platform::Windows, Architecture::X86_64)));
+        assert!(Config::from_raw("targets = [\"x86_64-plan9\"]").is_err());
     }
 
     #[test]
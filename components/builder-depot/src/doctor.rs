@@ -19,12 +19,13 @@ use std::path::PathBuf;
 use hab_core;
 use hab_core::package::{FromArchive, PackageArchive};
 use hab_net::routing::Broker;
+use protocol::net::{self, ErrCode};
 use protocol::originsrv;
 use time;
 use walkdir::WalkDir;
 
 use super::DepotUtil;
-use error::Result;
+use error::{Error, Result};
 
 #[derive(Debug)]
 /// A struct containing the details of a repair run by `Doctor`.
@@ -180,6 +181,10 @@ impl<'a> Doctor<'a> {
     }
 
     fn rebuild_metadata(&mut self) -> Result<()> {
+        let mut conn = match Broker::try_connect() {
+            Some(conn) => conn,
+            None => return Err(Error::from(net::err(ErrCode::ZMQ, "dp:doctor:0"))),
+        };
         let mut directories = vec![];
         for entry in WalkDir::new(&self.packages_path).follow_links(false) {
             let entry = entry.unwrap();
@@ -192,7 +197,6 @@ impl<'a> Doctor<'a> {
                 Ok(ident) => {
                     match originsrv::OriginPackageCreate::from_archive(&mut archive) {
                         Ok(package) => {
-                            let mut conn = Broker::connect().unwrap();
                             conn.route::<originsrv::OriginPackageCreate, originsrv::OriginPackage>(&package)?;
                             let path = self.depot.archive_path(&ident, &try!(archive.target()));
                             if let Some(e) = fs::create_dir_all(path.parent().unwrap()).err() {
@@ -34,6 +34,8 @@ pub enum Error {
     HTTP(hyper::status::StatusCode),
     InvalidPackageIdent(String),
     IO(io::Error),
+    LayoutVersionInvalid(String),
+    LayoutVersionMismatch(u32, u32),
     MessageTypeNotFound,
     NoXFilename,
     NoFilePart,
@@ -61,6 +63,15 @@ impl fmt::Display for Error {
                         e)
             }
             Error::IO(ref e) => format!("{}", e),
+            Error::LayoutVersionInvalid(ref e) => {
+                format!("Invalid storage layout version marker, {:?}", e)
+            }
+            Error::LayoutVersionMismatch(found, expected) => {
+                format!("Depot storage layout is version {} but version {} is required. Migrate \
+                         the package store before starting the Depot.",
+                        found,
+                        expected)
+            }
             Error::MessageTypeNotFound => format!("Unable to find message for given type"),
             Error::NoXFilename => format!("Invalid download from a Depot - missing X-Filename header"),
             Error::NoFilePart => {
@@ -96,6 +107,8 @@ impl error::Error for Error {
             Error::HTTP(_) => "Received an HTTP error",
             Error::InvalidPackageIdent(_) => "Package identifiers must be in origin/name format (example: acme/redis)",
             Error::IO(ref err) => err.description(),
+            Error::LayoutVersionInvalid(_) => "Invalid storage layout version marker",
+            Error::LayoutVersionMismatch(_, _) => "Depot storage layout version does not match",
             Error::NulError(_) => "An attempt was made to build a CString with a null byte inside it",
             Error::PackageIsAlreadyInChannel(_, _) => "Package is already in channel",
             Error::ProtocolNetError(ref err) => err.description(),
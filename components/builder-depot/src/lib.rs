@@ -54,14 +54,26 @@ pub mod server;
 pub use self::config::Config;
 pub use self::error::{Error, Result};
 
-use std::fs;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use crypto::sha2::Sha256;
 use crypto::digest::Digest;
 use hab_core::package::{Identifiable, PackageArchive, PackageTarget};
 use hab_net::server::NetIdent;
 use iron::typemap;
+use walkdir::WalkDir;
+
+/// Version of the on-disk storage layout under `Config.path` expected by this Depot. Bump this
+/// whenever the layout changes in a way which requires existing data to be migrated.
+pub const LAYOUT_VERSION: u32 = 1;
+const LAYOUT_VERSION_FILE: &'static str = "layout_version";
+/// File extension of archives staged by an upload before they are verified and moved into place.
+pub const UPLOAD_TEMP_EXT: &'static str = "hart.tmp";
+/// Age, in seconds, after which a staged upload is considered orphaned.
+pub const ORPHANED_UPLOAD_MAX_AGE_SECS: u64 = 3_600;
 
 pub struct DepotUtil {
     pub config: Config,
@@ -72,6 +84,85 @@ impl DepotUtil {
         DepotUtil { config: config }
     }
 
+    /// Verify the on-disk storage layout matches the `LAYOUT_VERSION` of this Depot. A store
+    /// without a layout version marker, including a fresh one, is stamped with the current
+    /// version.
+    ///
+    /// # Errors
+    ///
+    /// * The layout version marker does not match `LAYOUT_VERSION` and the store must be migrated
+    /// * The layout version marker could not be read or written
+    pub fn check_layout_version(&self) -> Result<()> {
+        let path = self.layout_version_path();
+        match File::open(&path) {
+            Ok(mut file) => {
+                let mut raw = String::new();
+                try!(file.read_to_string(&mut raw));
+                match raw.trim().parse::<u32>() {
+                    Ok(version) if version == LAYOUT_VERSION => Ok(()),
+                    Ok(version) => Err(Error::LayoutVersionMismatch(version, LAYOUT_VERSION)),
+                    Err(_) => Err(Error::LayoutVersionInvalid(raw.trim().to_string())),
+                }
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+                try!(fs::create_dir_all(&self.config.path));
+                let mut file = try!(File::create(&path));
+                try!(write!(file, "{}\n", LAYOUT_VERSION));
+                Ok(())
+            }
+            Err(e) => Err(Error::IO(e)),
+        }
+    }
+
+    /// Remove staged uploads older than `max_age` which were left behind by an interrupted
+    /// upload, returning the number of files removed. A file which can't be inspected or removed
+    /// is logged and skipped so it doesn't stop the rest from being cleaned up.
+    pub fn remove_orphaned_uploads(&self, max_age: Duration) -> usize {
+        let suffix = format!(".{}", UPLOAD_TEMP_EXT);
+        let mut removed = 0;
+        if !self.packages_path().is_dir() {
+            return removed;
+        }
+        for entry in WalkDir::new(self.packages_path()).follow_links(false) {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    warn!("Unable to read entry in package store, err={:?}", e);
+                    continue;
+                }
+            };
+            if !entry.file_name().to_string_lossy().ends_with(&suffix) {
+                continue;
+            }
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    warn!("Unable to read metadata of {:?}, err={:?}", entry.path(), e);
+                    continue;
+                }
+            };
+            let modified = match metadata.modified() {
+                Ok(modified) => modified,
+                Err(e) => {
+                    warn!("Unable to read modification time of {:?}, err={:?}",
+                          entry.path(),
+                          e);
+                    continue;
+                }
+            };
+            match modified.elapsed() {
+                Ok(age) if age >= max_age => {
+                    match fs::remove_file(entry.path()) {
+                        Ok(_) => removed += 1,
+                        Err(e) => warn!("Unable to remove {:?}, err={:?}", entry.path(), e),
+                    }
+                }
+                _ => (),
+            }
+        }
+        removed
+    }
+
     // Return a PackageArchive representing the given package. None is returned if the Depot
     // doesn't have an archive for the given package.
     fn archive<T: Identifiable>(&self,
@@ -104,6 +195,11 @@ impl DepotUtil {
                           target.platform))
     }
 
+    // Return the location of the build log stored alongside the archive for the given package.
+    fn log_path<T: Identifiable>(&self, ident: &T, target: &PackageTarget) -> PathBuf {
+        self.archive_path(ident, target).with_extension("log")
+    }
+
     // Return a formatted string representing the folder location for an archive.
     fn archive_parent<T: Identifiable>(&self, ident: &T) -> PathBuf {
         let mut digest = Sha256::new();
@@ -118,6 +214,10 @@ impl DepotUtil {
     fn packages_path(&self) -> PathBuf {
         Path::new(&self.config.path).join("pkgs")
     }
+
+    fn layout_version_path(&self) -> PathBuf {
+        Path::new(&self.config.path).join(LAYOUT_VERSION_FILE)
+    }
 }
 
 impl typemap::Key for DepotUtil {
@@ -125,3 +225,86 @@ impl typemap::Key for DepotUtil {
 }
 
 impl NetIdent for DepotUtil {}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::{Read, Write};
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn depot() -> (DepotUtil, PathBuf) {
+        let path = env::temp_dir().join(format!("depot-layout-{}", Uuid::new_v4()));
+        let mut config = Config::default();
+        config.path = path.to_string_lossy().into_owned();
+        (DepotUtil::new(config), path)
+    }
+
+    #[test]
+    fn layout_version_fresh_store() {
+        let (depot, path) = depot();
+        depot.check_layout_version().unwrap();
+        let mut raw = String::new();
+        File::open(path.join("layout_version"))
+            .unwrap()
+            .read_to_string(&mut raw)
+            .unwrap();
+        assert_eq!(raw.trim(), LAYOUT_VERSION.to_string());
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn layout_version_matching() {
+        let (depot, path) = depot();
+        fs::create_dir_all(&path).unwrap();
+        write!(File::create(path.join("layout_version")).unwrap(),
+               "{}",
+               LAYOUT_VERSION)
+                .unwrap();
+        assert!(depot.check_layout_version().is_ok());
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn remove_orphaned_uploads() {
+        let (depot, path) = depot();
+        let parent = path.join("pkgs").join("1f").join("2e");
+        fs::create_dir_all(&parent).unwrap();
+        File::create(parent.join(format!("orphan.{}", UPLOAD_TEMP_EXT))).unwrap();
+        File::create(parent.join("core-cacerts-2017.01.17-20170209064044-x86_64-linux.hart"))
+            .unwrap();
+
+        assert_eq!(depot.remove_orphaned_uploads(Duration::from_secs(3_600)), 0);
+        assert_eq!(depot.remove_orphaned_uploads(Duration::from_secs(0)), 1);
+        assert!(!parent
+                     .join(format!("orphan.{}", UPLOAD_TEMP_EXT))
+                     .exists());
+        assert!(parent
+                    .join("core-cacerts-2017.01.17-20170209064044-x86_64-linux.hart")
+                    .exists());
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn layout_version_mismatching() {
+        let (depot, path) = depot();
+        fs::create_dir_all(&path).unwrap();
+        write!(File::create(path.join("layout_version")).unwrap(),
+               "{}",
+               LAYOUT_VERSION + 1)
+                .unwrap();
+        match depot.check_layout_version() {
+            Err(Error::LayoutVersionMismatch(found, expected)) => {
+                assert_eq!(found, LAYOUT_VERSION + 1);
+                assert_eq!(expected, LAYOUT_VERSION);
+            }
+            e => panic!("Unexpected result: {:?}", e),
+        }
+        fs::remove_dir_all(path).unwrap();
+    }
+}
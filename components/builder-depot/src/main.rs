@@ -27,11 +27,11 @@ use std::process;
 use std::str::FromStr;
 
 use hab_core::config::ConfigFile;
+use hab_net::config::RouterCfg;
 
 use depot::{server, Config, Error, Result};
 
 const VERSION: &'static str = include_str!(concat!(env!("OUT_DIR"), "/VERSION"));
-const CFG_DEFAULT_PATH: &'static str = "/hab/svc/hab-depot/config.toml";
 
 fn main() {
     env_logger::init().unwrap();
@@ -73,7 +73,12 @@ fn config_from_args(matches: &clap::ArgMatches) -> Result<Config> {
     let args = matches.subcommand_matches(cmd).unwrap();
     let mut config = match args.value_of("config") {
         Some(cfg_path) => try!(Config::from_file(cfg_path)),
-        None => Config::from_file(CFG_DEFAULT_PATH).unwrap_or(Config::default()),
+        None => {
+            match Config::default_url() {
+                Some(url) => try!(Config::from_url(&url)),
+                None => Config::from_file(Config::default_path()).unwrap_or(Config::default()),
+            }
+        }
     };
     if let Some(port) = args.value_of("port") {
         if let Some(port) = u16::from_str(port).ok() {
@@ -91,6 +96,7 @@ fn config_from_args(matches: &clap::ArgMatches) -> Result<Config> {
     if let Some(path) = args.value_of("path") {
         config.path = path.to_string();
     }
+    try!(config.validate_routers());
     Ok(config)
 }
 
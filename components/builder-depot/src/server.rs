@@ -13,31 +13,41 @@
 // limitations under the License.
 
 use std::any::TypeId;
-use std::collections::HashMap;
+use std::cmp;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
 use std::fs::{self, File};
 use std::path::PathBuf;
-use std::io::{Read, Write, BufWriter};
+use std::io::{Read, Seek, SeekFrom, Write, BufWriter};
 use std::result;
-use std::str::FromStr;
+use std::str::{self, FromStr};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use uuid::Uuid;
+use bld_core::channel::{is_valid_channel_name, STABLE_CHANNEL, UNSTABLE_CHANNEL};
+use bld_core::metrics::Counter;
 use bodyparser;
-use hab_core::package::{Identifiable, FromArchive, PackageArchive, PackageTarget};
+use hab_core::package::{Identifiable, FromArchive, PackageArchive, PackageIdent, PackageTarget,
+                        SbomFormat};
+use hab_core::crypto::artifact;
 use hab_core::crypto::keys::{self, PairType};
 use hab_core::crypto::SigKeyPair;
 use hab_core::event::*;
 use hab_net::config::RouterCfg;
 use hab_net::http::controller::*;
 use hab_net::privilege;
-use hab_net::routing::{Broker, RouteResult};
+use hab_net::routing::{Broker, RouteResult, PING_TIMEOUT_MS};
 use hab_net::server::NetIdent;
-use hyper::header::{Charset, ContentDisposition, DispositionType, DispositionParam};
+use hyper::header::{AcceptRanges, ByteRangeSpec, Charset, ContentDisposition, ContentLength,
+                    ContentRange, ContentRangeSpec, DispositionType, DispositionParam, HttpDate,
+                    IfModifiedSince, LastModified, Range, RangeUnit};
 use hyper::mime::{Mime, TopLevel, SubLevel, Attr, Value};
 use iron::{status, headers, typemap};
 use iron::headers::{ContentType, UserAgent};
 use iron::middleware::BeforeMiddleware;
 use iron::prelude::*;
 use iron::request::Body;
+use iron::response::BodyReader;
 use iron::typemap::Key;
 use mount::Mount;
 use persistent;
@@ -49,12 +59,11 @@ use protocol::scheduler::{Group, GroupCreate, GroupGet, PackageStatsGet, Package
 use protocol::sessionsrv::{Account, AccountGet};
 use regex::Regex;
 use router::{Params, Router};
-use serde::Serialize;
 use serde_json;
 use url;
 use urlencoded::UrlEncodedQuery;
 
-use super::DepotUtil;
+use super::{DepotUtil, ORPHANED_UPLOAD_MAX_AGE_SECS, UPLOAD_TEMP_EXT};
 use config::Config;
 use error::{Error, Result};
 
@@ -64,6 +73,7 @@ define_event_log!();
 pub struct TestableBroker {
     message_map: HashMap<TypeId, Vec<u8>>,
     error_map: HashMap<TypeId, NetError>,
+    message_error_map: HashMap<(TypeId, Vec<u8>), NetError>,
     cached_messages: HashMap<TypeId, Vec<u8>>,
 }
 
@@ -77,12 +87,23 @@ impl TestableBroker {
         self.error_map.insert(TypeId::of::<M>(), error);
     }
 
+    /// Like `setup_error` but only answers messages equal to the given one with the error.
+    pub fn setup_error_for<M: Routable>(&mut self, msg: &M, error: NetError) {
+        let bytes = msg.write_to_bytes().unwrap();
+        self.message_error_map
+            .insert((TypeId::of::<M>(), bytes), error);
+    }
+
     pub fn routed_messages(&self) -> RoutedMessages {
         RoutedMessages(self.cached_messages.clone())
     }
 
     pub fn route<M: Routable, R: protobuf::MessageStatic>(&mut self, msg: &M) -> RouteResult<R> {
         let bytes = msg.write_to_bytes().unwrap();
+        if let Some(error) = self.message_error_map
+               .get(&(TypeId::of::<M>(), bytes.clone())) {
+            return Err(error.clone());
+        }
         self.cached_messages.insert(TypeId::of::<M>(), bytes);
         let msg_type = &TypeId::of::<M>();
         match self.message_map.get(msg_type) {
@@ -116,19 +137,19 @@ impl RoutedMessages {
     }
 }
 
-#[derive(Clone, Serialize, Deserialize)]
-struct OriginCreateReq {
-    name: String,
+#[derive(Default, Serialize)]
+struct PackagesExistence {
+    packages: BTreeMap<String, bool>,
+    errors: BTreeMap<String, String>,
 }
 
-#[derive(Serialize)]
-struct PackageResults<'a, T: 'a> {
-    range_start: isize,
-    range_end: isize,
-    total_count: isize,
-    package_list: &'a Vec<T>,
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct OriginCreateReq {
+    name: String,
 }
 
+// Most idents a client may ask about in a single existence check.
+const PACKAGES_EXIST_MAX: usize = 100;
 const PAGINATION_RANGE_DEFAULT: isize = 0;
 const PAGINATION_RANGE_MAX: isize = 50;
 const ONE_YEAR_IN_SECS: usize = 31536000;
@@ -140,22 +161,10 @@ fn route_message<M: Routable, R: protobuf::MessageStatic>(req: &mut Request,
         return broker.route::<M, R>(msg);
     }
 
-    Broker::connect().unwrap().route::<M, R>(msg)
-}
-
-fn package_results_json<T: Serialize>(packages: &Vec<T>,
-                                      count: isize,
-                                      start: isize,
-                                      end: isize)
-                                      -> String {
-    let results = PackageResults {
-        range_start: start,
-        range_end: end,
-        total_count: count,
-        package_list: packages,
-    };
-
-    serde_json::to_string(&results).unwrap()
+    match Broker::try_connect() {
+        Some(mut conn) => conn.route::<M, R>(msg),
+        None => Err(protocol::net::err(ErrCode::ZMQ, "dp:route:0")),
+    }
 }
 
 pub fn origin_create(req: &mut Request) -> IronResult<Response> {
@@ -174,7 +183,10 @@ pub fn origin_create(req: &mut Request) -> IronResult<Response> {
         return Ok(Response::with(status::UnprocessableEntity));
     }
 
-    let mut conn = Broker::connect().unwrap();
+    let mut conn = match Broker::try_connect() {
+        Some(conn) => conn,
+        None => return Ok(Response::with(status::ServiceUnavailable)),
+    };
     match conn.route::<OriginCreate, Origin>(&request) {
         Ok(origin) => Ok(render_json(status::Created, &origin)),
         Err(err) => Ok(render_net_error(&err)),
@@ -188,7 +200,10 @@ pub fn origin_show(req: &mut Request) -> IronResult<Response> {
         _ => return Ok(Response::with(status::BadRequest)),
     };
 
-    let mut conn = Broker::connect().unwrap();
+    let mut conn = match Broker::try_connect() {
+        Some(conn) => conn,
+        None => return Ok(Response::with(status::ServiceUnavailable)),
+    };
     let mut request = OriginGet::new();
     request.set_name(origin);
     match conn.route::<OriginGet, Origin>(&request) {
@@ -218,6 +233,15 @@ pub fn get_origin<T: ToString>(req: &mut Request, origin: T) -> IronResult<Optio
     }
 }
 
+// Returns the response for a request whose message could not be routed, logging the error
+// unless the entity simply wasn't found.
+fn net_error_response(tag: &str, err: &NetError) -> Response {
+    if err.get_code() != ErrCode::ENTITY_NOT_FOUND {
+        error!("{}, err={:?}", tag, err);
+    }
+    Response::with(net_err_to_http(err.get_code()))
+}
+
 pub fn check_origin_access<T: ToString>(req: &mut Request,
                                         account_id: u64,
                                         origin: T)
@@ -255,7 +279,10 @@ pub fn accept_invitation(req: &mut Request) -> IronResult<Response> {
         }
     };
 
-    let mut conn = Broker::connect().unwrap();
+    let mut conn = match Broker::try_connect() {
+        Some(conn) => conn,
+        None => return Ok(Response::with(status::ServiceUnavailable)),
+    };
     debug!("Accepting invitation for user {} origin {}",
            &session.get_id(),
            &origin);
@@ -294,7 +321,10 @@ pub fn invite_to_origin(req: &mut Request) -> IronResult<Response> {
         Some(username) => username,
         None => return Ok(Response::with(status::BadRequest)),
     };
-    let mut conn = Broker::connect().unwrap();
+    let mut conn = match Broker::try_connect() {
+        Some(conn) => conn,
+        None => return Ok(Response::with(status::ServiceUnavailable)),
+    };
     debug!("Creating invitation for user {} origin {}",
            &user_to_invite,
            &origin);
@@ -350,7 +380,10 @@ pub fn list_origin_invitations(req: &mut Request) -> IronResult<Response> {
         };
     }
 
-    let mut conn = Broker::connect().unwrap();
+    let mut conn = match Broker::try_connect() {
+        Some(conn) => conn,
+        None => return Ok(Response::with(status::ServiceUnavailable)),
+    };
     if !try!(check_origin_access(req, session_id, &origin_name)) {
         return Ok(Response::with(status::Forbidden));
     }
@@ -384,7 +417,10 @@ pub fn list_origin_members(req: &mut Request) -> IronResult<Response> {
         };
     }
 
-    let mut conn = Broker::connect().unwrap();
+    let mut conn = match Broker::try_connect() {
+        Some(conn) => conn,
+        None => return Ok(Response::with(status::ServiceUnavailable)),
+    };
 
     if !try!(check_origin_access(req, session_id, &origin_name)) {
         return Ok(Response::with(status::Forbidden));
@@ -405,6 +441,49 @@ pub fn list_origin_members(req: &mut Request) -> IronResult<Response> {
     }
 }
 
+// Verifies the signature of an uploaded archive with the public key named by its signer. The key
+// is fetched from the origin server into a scratch key cache for the duration of the check.
+fn verify_signature(req: &mut Request,
+                    archive: &PackageArchive,
+                    signer: &str,
+                    owner_id: u64,
+                    require_key: bool)
+                    -> result::Result<(), String> {
+    let (origin, revision) = match keys::parse_name_with_rev(signer) {
+        Ok(name_with_rev) => name_with_rev,
+        Err(e) => return Err(e.to_string()),
+    };
+    let mut request = OriginPublicKeyGet::new();
+    request.set_owner_id(owner_id);
+    request.set_origin(origin);
+    request.set_revision(revision);
+    let key = match route_message::<OriginPublicKeyGet, OriginPublicKey>(req, &request) {
+        Ok(key) => key,
+        Err(ref err) if err.get_code() == ErrCode::ENTITY_NOT_FOUND && !require_key => {
+            info!("Public key {} is unknown, accepting the package unverified", signer);
+            return Ok(());
+        }
+        Err(err) => return Err(format!("public key {} not found, {}", signer, err)),
+    };
+    let body = match str::from_utf8(key.get_body()) {
+        Ok(body) => body,
+        Err(e) => return Err(format!("public key {} is not valid UTF-8, {}", signer, e)),
+    };
+
+    let cache_path = env::temp_dir().join(format!("depot-keys-{}", Uuid::new_v4()));
+    let result = fs::create_dir_all(&cache_path)
+        .map_err(|e| e.to_string())
+        .and_then(|_| {
+                      SigKeyPair::write_file_from_str(body, &cache_path)
+                          .map_err(|e| e.to_string())
+                  })
+        .and_then(|_| archive.verify(&cache_path).map_err(|e| e.to_string()));
+    if let Err(e) = fs::remove_dir_all(&cache_path) {
+        warn!("Unable to remove key cache {:?}, err={:?}", cache_path, e);
+    }
+    result.map(|_| ())
+}
+
 fn write_archive(filename: &PathBuf, body: &mut Body) -> Result<PackageArchive> {
     let file = try!(File::create(&filename));
     let mut writer = BufWriter::new(file);
@@ -427,36 +506,34 @@ fn write_archive(filename: &PathBuf, body: &mut Body) -> Result<PackageArchive>
             }
         };
     }
+    try!(writer.flush());
     Ok(PackageArchive::new(filename))
 }
 
+// Remove an uploaded archive which failed verification or could not be recorded.
+fn discard_archive(path: &PathBuf) {
+    if let Err(e) = fs::remove_file(path) {
+        warn!("Unable to remove uploaded archive {:?}, err={:?}", path, e);
+    }
+}
+
 fn upload_origin_key(req: &mut Request) -> IronResult<Response> {
     debug!("Upload Origin Public Key {:?}", req);
     // TODO: SA - Eliminate need to clone the session and params
     let session = req.extensions.get::<Authenticated>().unwrap().clone();
     let params = req.extensions.get::<Router>().unwrap().clone();
-    let mut conn = Broker::connect().unwrap();
-    let mut request = OriginPublicKeyCreate::new();
-    request.set_owner_id(session.get_id());
 
     let origin = match params.find("origin") {
         Some(origin) => {
             if !try!(check_origin_access(req, session.get_id(), origin)) {
                 return Ok(Response::with(status::Forbidden));
             }
-            match get_origin(req, origin)? {
-                Some(mut origin) => {
-                    request.set_name(origin.take_name());
-                    request.set_origin_id(origin.get_id());
-                }
-                None => return Ok(Response::with(status::NotFound)),
-            }
             origin
         }
         None => return Ok(Response::with(status::BadRequest)),
     };
-    match params.find("revision") {
-        Some(revision) => request.set_revision(revision.to_string()),
+    let revision = match params.find("revision") {
+        Some(revision) => revision.to_string(),
         None => return Ok(Response::with(status::BadRequest)),
     };
 
@@ -488,22 +565,42 @@ fn upload_origin_key(req: &mut Request) -> IronResult<Response> {
         }
     }
 
+    create_origin_key(req, session.get_id(), origin, revision, key_content)
+}
+
+// Stores a validated public key as the given revision of the origin's key and answers with the
+// location of the new key.
+fn create_origin_key(req: &mut Request,
+                     account_id: u64,
+                     origin: &str,
+                     revision: String,
+                     key_content: Vec<u8>)
+                     -> IronResult<Response> {
+    let mut request = OriginPublicKeyCreate::new();
+    match try!(get_origin(req, origin)) {
+        Some(mut origin) => {
+            request.set_name(origin.take_name());
+            request.set_origin_id(origin.get_id());
+        }
+        None => return Ok(Response::with(status::NotFound)),
+    }
+    request.set_revision(revision);
     request.set_body(key_content);
     request.set_owner_id(0);
 
-    match conn.route::<OriginPublicKeyCreate, OriginPublicKey>(&request) {
+    match route_message::<OriginPublicKeyCreate, OriginPublicKey>(req, &request) {
         Ok(_) => {
             log_event!(req,
                        Event::OriginKeyUpload {
                            origin: origin.to_string(),
                            version: request.get_revision().to_string(),
-                           account: session.get_id().to_string(),
+                           account: account_id.to_string(),
                        });
             let mut response =
                 Response::with((status::Created,
-                                format!("/origins/{}/keys/{}", &origin, &request.get_revision())));
+                                format!("/origins/{}/keys/{}", origin, request.get_revision())));
             let mut base_url: url::Url = req.url.clone().into();
-            base_url.set_path(&format!("key/{}-{}", &origin, &request.get_revision()));
+            base_url.set_path(&format!("key/{}-{}", origin, request.get_revision()));
             response
                 .headers
                 .set(headers::Location(format!("{}", base_url)));
@@ -513,12 +610,77 @@ fn upload_origin_key(req: &mut Request) -> IronResult<Response> {
     }
 }
 
+/// Upload a new revision of an origin's public key, taking the revision from the key itself.
+///
+/// The uploaded key becomes the origin's latest key. Earlier revisions are kept so packages
+/// signed with them can still be verified.
+fn upload_origin_key_revision(req: &mut Request) -> IronResult<Response> {
+    let insecure = {
+        let lock = req.get::<persistent::State<DepotUtil>>()
+            .expect("depot not found");
+        let depot = lock.read().expect("depot read lock is poisoned");
+        depot.config.insecure
+    };
+    let session = req.extensions.get::<Authenticated>().unwrap().clone();
+    let origin = {
+        let params = req.extensions.get::<Router>().unwrap();
+        match params.find("origin") {
+            Some(origin) => origin.to_string(),
+            None => return Ok(Response::with(status::BadRequest)),
+        }
+    };
+    if !insecure && !try!(check_origin_access(req, session.get_id(), &origin)) {
+        return Ok(Response::with(status::Forbidden));
+    }
+
+    let mut key_content = Vec::new();
+    if let Err(e) = req.body.read_to_end(&mut key_content) {
+        debug!("Can't read key content {}", e);
+        return Ok(Response::with(status::BadRequest));
+    }
+    let parsed = match String::from_utf8(key_content.clone()) {
+        Ok(content) => SigKeyPair::parse_key_str(&content),
+        Err(e) => {
+            debug!("Can't parse public key upload content: {}", e);
+            return Ok(Response::with((status::UnprocessableEntity, "Malformed key")));
+        }
+    };
+    let revision = match parsed {
+        Ok((PairType::Public, name_with_rev, _)) => {
+            match keys::parse_name_with_rev(&name_with_rev) {
+                Ok((ref name, ref revision)) if *name == origin => revision.to_string(),
+                Ok(_) => {
+                    return Ok(Response::with((status::UnprocessableEntity,
+                                              "Key does not belong to this origin")))
+                }
+                Err(e) => {
+                    debug!("Invalid public key name: {}", e);
+                    return Ok(Response::with((status::UnprocessableEntity, "Malformed key")));
+                }
+            }
+        }
+        Ok(_) => {
+            return Ok(Response::with((status::UnprocessableEntity,
+                                      "Expected a public key but received a secret key")))
+        }
+        Err(e) => {
+            debug!("Invalid public key content: {}", e);
+            return Ok(Response::with((status::UnprocessableEntity, "Malformed key")));
+        }
+    };
+
+    create_origin_key(req, session.get_id(), &origin, revision, key_content)
+}
+
 fn download_latest_origin_secret_key(req: &mut Request) -> IronResult<Response> {
     let origin = {
         let params = req.extensions.get::<Router>().unwrap();
         params.find("origin").unwrap().to_owned()
     };
-    let mut conn = Broker::connect().unwrap();
+    let mut conn = match Broker::try_connect() {
+        Some(conn) => conn,
+        None => return Ok(Response::with(status::ServiceUnavailable)),
+    };
     let mut request = OriginSecretKeyGet::new();
     match try!(get_origin(req, origin)) {
         Some(mut origin) => {
@@ -538,7 +700,10 @@ fn upload_origin_secret_key(req: &mut Request) -> IronResult<Response> {
     // TODO: SA - Eliminate need to clone the session and params
     let session = req.extensions.get::<Authenticated>().unwrap().clone();
     let params = req.extensions.get::<Router>().unwrap().clone();
-    let mut conn = Broker::connect().unwrap();
+    let mut conn = match Broker::try_connect() {
+        Some(conn) => conn,
+        None => return Ok(Response::with(status::ServiceUnavailable)),
+    };
     let mut request = OriginSecretKeyCreate::new();
     request.set_owner_id(session.get_id());
 
@@ -653,17 +818,26 @@ fn upload_package(req: &mut Request) -> IronResult<Response> {
         }
     };
 
-    // Create a temp file at the archive location
-    let temp_name = format!("{}.tmp", Uuid::new_v4());
+    // Stage the upload in a temp file next to its final location. The archive is only renamed
+    // into place once it has been verified so that a failed upload never leaves a partial
+    // archive behind.
+    let temp_name = format!("{}.{}", Uuid::new_v4(), UPLOAD_TEMP_EXT);
     let temp_path = parent_path.join(temp_name);
 
-    let mut archive = try!(write_archive(&temp_path, &mut req.body));
+    let mut archive = match write_archive(&temp_path, &mut req.body) {
+        Ok(archive) => archive,
+        Err(e) => {
+            discard_archive(&temp_path);
+            return Err(IronError::from(e));
+        }
+    };
     debug!("Package Archive: {:#?}", archive);
 
     let target_from_artifact = match archive.target() {
         Ok(target) => target,
         Err(e) => {
             info!("Could not read the target for {:#?}: {:#?}", archive, e);
+            discard_archive(&temp_path);
             return Ok(Response::with(status::UnprocessableEntity));
         }
     };
@@ -671,24 +845,60 @@ fn upload_package(req: &mut Request) -> IronResult<Response> {
     if !depot.config.targets.contains(&target_from_artifact) {
         debug!("Unsupported package platform or architecture {}.",
                target_from_artifact);
+        discard_archive(&temp_path);
         return Ok(Response::with(status::NotImplemented));
     };
 
+    // The signing key's name tells us which origin public key verifies the package, so it must
+    // belong to the package's own origin.
+    let signer = match archive.signing_key_name() {
+        Ok(signer) => signer,
+        Err(e) => {
+            info!("Could not read the signing key name of {:#?}: {}", archive, e);
+            discard_archive(&temp_path);
+            return Ok(Response::with(status::UnprocessableEntity));
+        }
+    };
+    match keys::parse_name_with_rev(&signer) {
+        Ok((ref origin, _)) if origin == ident.get_origin() => {}
+        _ => {
+            info!("Package {} is signed by key {} of another origin", ident, signer);
+            discard_archive(&temp_path);
+            return Ok(Response::with(status::UnprocessableEntity));
+        }
+    }
+    // The signature is checked whenever the origin's public key is known. Depots requiring
+    // signed uploads also reject packages signed by a key they don't have.
+    if let Err(e) = verify_signature(req,
+                                     &archive,
+                                     &signer,
+                                     session.get_id(),
+                                     depot.config.require_signed_uploads) {
+        info!("Could not verify the signature of {:#?}: {}", archive, e);
+        discard_archive(&temp_path);
+        return Ok(Response::with(status::UnprocessableEntity));
+    }
+
     let mut ident_req = OriginPackageGet::new();
     ident_req.set_ident(ident.clone());
 
     match route_message::<OriginPackageGet, OriginPackage>(req, &ident_req) {
-        Ok(_) => return Ok(Response::with((status::Conflict))),
+        Ok(_) => {
+            discard_archive(&temp_path);
+            return Ok(Response::with((status::Conflict)));
+        }
         Err(err) => {
             match err.get_code() {
                 ErrCode::ENTITY_NOT_FOUND => {
                     if depot.archive(&ident, &target_from_artifact).is_some() {
+                        discard_archive(&temp_path);
                         return Ok(Response::with((status::Conflict)));
                     }
                 }
                 _ => {
                     error!("upload_package:1, err={:?}", err);
-                    return Ok(Response::with(status::InternalServerError));
+                    discard_archive(&temp_path);
+                    return Ok(Response::with(net_err_to_http(err.get_code())));
                 }
             }
         }
@@ -698,6 +908,7 @@ fn upload_package(req: &mut Request) -> IronResult<Response> {
         Ok(cksum) => cksum,
         Err(e) => {
             info!("Could not compute a checksum for {:#?}: {:#?}", archive, e);
+            discard_archive(&temp_path);
             return Ok(Response::with(status::UnprocessableEntity));
         }
     };
@@ -705,9 +916,46 @@ fn upload_package(req: &mut Request) -> IronResult<Response> {
         info!("Checksums did not match: from_param={:?}, from_artifact={:?}",
               checksum_from_param,
               checksum_from_artifact);
+        discard_archive(&temp_path);
+        return Ok(Response::with(status::UnprocessableEntity));
+    }
+
+    if let Err(e) = artifact::get_artifact_header(&temp_path) {
+        info!("Could not read the signed header of {:#?}: {:#?}", archive, e);
+        discard_archive(&temp_path);
+        return Ok(Response::with(status::UnprocessableEntity));
+    }
+
+    let mut package = match OriginPackageCreate::from_archive(&mut archive) {
+        Ok(package) => package,
+        Err(e) => {
+            info!("Error building package from archive: {:#?}", e);
+            discard_archive(&temp_path);
+            return Ok(Response::with(status::UnprocessableEntity));
+        }
+    };
+    if !ident.satisfies(package.get_ident()) {
+        info!("Ident mismatch, expected={:?}, got={:?}",
+              ident,
+              package.get_ident());
+        discard_archive(&temp_path);
         return Ok(Response::with(status::UnprocessableEntity));
     }
 
+    // let's make sure this origin actually exists before storing the archive
+    match get_origin(req, &ident.get_origin()) {
+        Ok(Some(origin)) => package.set_origin_id(origin.get_id()),
+        Ok(None) => {
+            discard_archive(&temp_path);
+            return Ok(Response::with(status::NotFound));
+        }
+        Err(err) => {
+            discard_archive(&temp_path);
+            return Err(err);
+        }
+    }
+    package.set_owner_id(session.get_id());
+
     let filename = depot.archive_path(&ident, &target_from_artifact);
 
     match fs::rename(&temp_path, &filename) {
@@ -717,80 +965,66 @@ fn upload_package(req: &mut Request) -> IronResult<Response> {
                    temp_path,
                    filename,
                    e);
+            discard_archive(&temp_path);
             return Ok(Response::with(status::InternalServerError));
         }
     }
 
     info!("File added to Depot at {}", filename.to_string_lossy());
-    let mut archive = PackageArchive::new(filename);
-    let mut package = match OriginPackageCreate::from_archive(&mut archive) {
-        Ok(package) => package,
-        Err(e) => {
-            info!("Error building package from archive: {:#?}", e);
-            return Ok(Response::with(status::UnprocessableEntity));
-        }
-    };
-    if ident.satisfies(package.get_ident()) {
-        package.set_owner_id(session.get_id());
 
-        // let's make sure this origin actually exists
-        match try!(get_origin(req, &ident.get_origin())) {
-            Some(origin) => {
-                package.set_origin_id(origin.get_id());
-            }
-            None => return Ok(Response::with(status::NotFound)),
-        };
+    if let Err(err) = route_message::<OriginPackageCreate, OriginPackage>(req, &package) {
+        discard_archive(&filename);
+        return Ok(net_error_response("upload_package:2", &err));
+    }
 
-        route_message::<OriginPackageCreate, OriginPackage>(req, &package).unwrap();
-
-        log_event!(req,
-                   Event::PackageUpload {
-                       origin: ident.get_origin().to_string(),
-                       package: ident.get_name().to_string(),
-                       version: ident.get_version().to_string(),
-                       release: ident.get_release().to_string(),
-                       target: target_from_artifact.to_string(),
-                       account: session.get_id().to_string(),
-                   });
-
-        // Schedule re-build of dependent packages (if requested)
-        // Don't schedule builds if the upload is being done by the builder
-        // Currently, we only do dep builds of 'core' packages
-        if depot.config.builds_enabled && ident.get_origin() == "core" &&
-           !match extract_query_value("builder", req) {
-                Some(_) => true,
-                None => false,
-            } {
-            let mut conn = Broker::connect().unwrap();
-
-            let mut request = GroupCreate::new();
-            request.set_origin(ident.get_origin().to_string());
-            request.set_package(ident.get_name().to_string());
-            request.set_deps_only(true);
-
-            match conn.route::<GroupCreate, Group>(&request) {
-                Ok(group) => {
-                    debug!("Scheduled reverse dependecy build, group id: {}",
-                           group.get_id())
+    log_event!(req,
+               Event::PackageUpload {
+                   origin: ident.get_origin().to_string(),
+                   package: ident.get_name().to_string(),
+                   version: ident.get_version().to_string(),
+                   release: ident.get_release().to_string(),
+                   target: target_from_artifact.to_string(),
+                   account: session.get_id().to_string(),
+               });
+
+    // Schedule re-build of dependent packages (if requested)
+    // Don't schedule builds if the upload is being done by the builder
+    // Currently, we only do dep builds of 'core' packages
+    if depot.config.builds_enabled && ident.get_origin() == "core" &&
+       !match extract_query_value("builder", req) {
+            Some(_) => true,
+            None => false,
+        } {
+        let mut request = GroupCreate::new();
+        request.set_origin(ident.get_origin().to_string());
+        request.set_package(ident.get_name().to_string());
+        request.set_deps_only(true);
+
+        match Broker::try_connect() {
+            Some(mut conn) => {
+                match conn.route::<GroupCreate, Group>(&request) {
+                    Ok(group) => {
+                        debug!("Scheduled reverse dependecy build, group id: {}",
+                               group.get_id())
+                    }
+                    Err(err) => error!("Unable to schedule build, err: {:?}", err),
                 }
-                Err(err) => error!("Unable to schedule build, err: {:?}", err),
+            }
+            None => {
+                warn!("Unable to schedule build of {} dependents, no router available",
+                      ident)
             }
         }
-
-        let mut response = Response::with((status::Created,
-                                           format!("/pkgs/{}/download", package.get_ident())));
-        let mut base_url: url::Url = req.url.clone().into();
-        base_url.set_path(&format!("pkgs/{}/download", package.get_ident()));
-        response
-            .headers
-            .set(headers::Location(format!("{}", base_url)));
-        Ok(response)
-    } else {
-        info!("Ident mismatch, expected={:?}, got={:?}",
-              ident,
-              package.get_ident());
-        Ok(Response::with(status::UnprocessableEntity))
     }
+
+    let mut response = Response::with((status::Created,
+                                       format!("/pkgs/{}/download", package.get_ident())));
+    let mut base_url: url::Url = req.url.clone().into();
+    base_url.set_path(&format!("pkgs/{}/download", package.get_ident()));
+    response
+        .headers
+        .set(headers::Location(format!("{}", base_url)));
+    Ok(response)
 }
 
 fn package_stats(req: &mut Request) -> IronResult<Response> {
@@ -802,7 +1036,10 @@ fn package_stats(req: &mut Request) -> IronResult<Response> {
         }
     };
 
-    let mut conn = Broker::connect().unwrap();
+    let mut conn = match Broker::try_connect() {
+        Some(conn) => conn,
+        None => return Ok(Response::with(status::ServiceUnavailable)),
+    };
 
     let mut request = PackageStatsGet::new();
     request.set_origin(String::from(origin));
@@ -828,7 +1065,10 @@ fn schedule(req: &mut Request) -> IronResult<Response> {
         None => return Ok(Response::with(status::BadRequest)),
     };
 
-    let mut conn = Broker::connect().unwrap();
+    let mut conn = match Broker::try_connect() {
+        Some(conn) => conn,
+        None => return Ok(Response::with(status::ServiceUnavailable)),
+    };
 
     let mut request = GroupCreate::new();
     request.set_origin(String::from(origin));
@@ -858,7 +1098,10 @@ fn get_schedule(req: &mut Request) -> IronResult<Response> {
         }
     };
 
-    let mut conn = Broker::connect().unwrap();
+    let mut conn = match Broker::try_connect() {
+        Some(conn) => conn,
+        None => return Ok(Response::with(status::ServiceUnavailable)),
+    };
 
     let mut request = GroupGet::new();
     request.set_group_id(group_id);
@@ -877,7 +1120,10 @@ fn download_origin_key(req: &mut Request) -> IronResult<Response> {
     let params = req.extensions.get::<Router>().unwrap();
     // TODO: SA - Eliminate need to clone the session and params
     let session = req.extensions.get::<Authenticated>().unwrap().clone();
-    let mut conn = Broker::connect().unwrap();
+    let mut conn = match Broker::try_connect() {
+        Some(conn) => conn,
+        None => return Ok(Response::with(status::ServiceUnavailable)),
+    };
     let mut request = OriginPublicKeyGet::new();
     request.set_owner_id(session.get_id());
     match params.find("origin") {
@@ -911,7 +1157,10 @@ fn download_latest_origin_key(req: &mut Request) -> IronResult<Response> {
     let params = req.extensions.get::<Router>().unwrap();
     // TODO: SA - Eliminate need to clone the session and params
     let session = req.extensions.get::<Authenticated>().unwrap().clone();
-    let mut conn = Broker::connect().unwrap();
+    let mut conn = match Broker::try_connect() {
+        Some(conn) => conn,
+        None => return Ok(Response::with(status::ServiceUnavailable)),
+    };
     let mut request = OriginPublicKeyLatestGet::new();
     request.set_owner_id(session.get_id());
     match params.find("origin") {
@@ -957,9 +1206,22 @@ fn download_package(req: &mut Request) -> IronResult<Response> {
         Ok(package) => {
             if let Some(archive) = depot.archive(package.get_ident(), &agent_target) {
                 match fs::metadata(&archive.path) {
-                    Ok(_) => {
+                    Ok(metadata) => {
+                        let etag = if package.get_checksum().is_empty() {
+                            None
+                        } else {
+                            Some(package.get_checksum())
+                        };
+                        let modified = metadata.modified().ok();
+                        if is_not_modified(&req.headers, etag, modified) {
+                            let mut response = Response::with(status::NotModified);
+                            do_cache_response(&mut response);
+                            set_validator_headers(&mut response, etag, modified);
+                            return Ok(response);
+                        }
                         let mut response = Response::with((status::Ok, archive.path.clone()));
                         do_cache_response(&mut response);
+                        set_validator_headers(&mut response, etag, modified);
                         let disp = ContentDisposition {
                             disposition: DispositionType::Attachment,
                             parameters: vec![DispositionParam::Filename(Charset::Iso_8859_1,
@@ -971,6 +1233,10 @@ fn download_package(req: &mut Request) -> IronResult<Response> {
                         };
                         response.headers.set(disp);
                         response.headers.set(XFileName(archive.file_name()));
+                        let ident = package.get_ident();
+                        Counter::PackageDownload(ident.get_origin().to_string(),
+                                                 ident.get_name().to_string())
+                                .increment();
                         Ok(response)
                     }
                     Err(_) => Ok(Response::with(status::NotFound)),
@@ -983,15 +1249,158 @@ fn download_package(req: &mut Request) -> IronResult<Response> {
                         data integrity.");
             }
         }
-        Err(err) => {
-            match err.get_code() {
-                ErrCode::ENTITY_NOT_FOUND => Ok(Response::with((status::NotFound))),
-                _ => {
-                    error!("download_package:1, err={:?}", err);
-                    Ok(Response::with(status::InternalServerError))
+        Err(err) => Ok(net_error_response("download_package:1", &err)),
+    }
+}
+
+fn download_package_log(req: &mut Request) -> IronResult<Response> {
+    let target = match extract_query_value("target", req) {
+        Some(target) => {
+            match PackageTarget::from_str(&target) {
+                Ok(target) => target,
+                Err(_) => return Ok(Response::with(status::BadRequest)),
+            }
+        }
+        None => PackageTarget::default(),
+    };
+    // Only the path is needed from the depot, so its lock isn't held while the log is sent.
+    let path = {
+        let lock = req.get::<persistent::State<DepotUtil>>()
+            .expect("depot not found");
+        let depot = lock.read().expect("depot read lock is poisoned");
+        let params = req.extensions.get::<Router>().unwrap();
+        depot.log_path(&ident_from_params(params), &target)
+    };
+    let mut file = match File::open(&path) {
+        Ok(file) => file,
+        Err(_) => return Ok(Response::with(status::NotFound)),
+    };
+    let length = match file.metadata() {
+        Ok(metadata) => metadata.len(),
+        Err(e) => {
+            error!("download_package_log:1, err={:?}", e);
+            return Ok(Response::with(status::InternalServerError));
+        }
+    };
+    let range = match req.headers.get::<Range>() {
+        Some(&Range::Bytes(ref specs)) if specs.len() == 1 => {
+            match satisfiable_range(&specs[0], length) {
+                Some(range) => Some(range),
+                None => {
+                    let mut response = Response::with(status::RangeNotSatisfiable);
+                    response
+                        .headers
+                        .set(ContentRange(ContentRangeSpec::Bytes {
+                                              range: None,
+                                              instance_length: Some(length),
+                                          }));
+                    return Ok(response);
+                }
+            }
+        }
+        // Multiple ranges and other units aren't supported, so the whole log is sent instead.
+        _ => None,
+    };
+    let mut response = match range {
+        Some((start, end)) => {
+            if let Err(e) = file.seek(SeekFrom::Start(start)) {
+                error!("download_package_log:2, err={:?}", e);
+                return Ok(Response::with(status::InternalServerError));
+            }
+            let mut response = Response::with((status::PartialContent,
+                                               BodyReader(file.take(end - start + 1))));
+            response.headers.set(ContentLength(end - start + 1));
+            response
+                .headers
+                .set(ContentRange(ContentRangeSpec::Bytes {
+                                      range: Some((start, end)),
+                                      instance_length: Some(length),
+                                  }));
+            response
+        }
+        None => Response::with((status::Ok, file)),
+    };
+    response
+        .headers
+        .set(ContentType(Mime(TopLevel::Text,
+                              SubLevel::Plain,
+                              vec![(Attr::Charset, Value::Utf8)])));
+    response.headers.set(AcceptRanges(vec![RangeUnit::Bytes]));
+    Ok(response)
+}
+
+// Returns the first and last byte, inclusive, of a file of the given length which a range
+// request asks for, or `None` if none of the range lies within the file.
+fn satisfiable_range(spec: &ByteRangeSpec, length: u64) -> Option<(u64, u64)> {
+    if length == 0 {
+        return None;
+    }
+    match *spec {
+        ByteRangeSpec::FromTo(from, to) if from < length && from <= to => {
+            Some((from, cmp::min(to, length - 1)))
+        }
+        ByteRangeSpec::AllFrom(from) if from < length => Some((from, length - 1)),
+        ByteRangeSpec::Last(last) if last > 0 => {
+            Some((length - cmp::min(last, length), length - 1))
+        }
+        _ => None,
+    }
+}
+
+fn package_sbom(req: &mut Request) -> IronResult<Response> {
+    let format = match extract_query_value("format", req) {
+        Some(format) => {
+            match SbomFormat::from_str(&format) {
+                Ok(format) => format,
+                Err(_) => return Ok(Response::with(status::BadRequest)),
+            }
+        }
+        None => SbomFormat::default(),
+    };
+    let target = match extract_query_value("target", req) {
+        Some(target) => {
+            match PackageTarget::from_str(&target) {
+                Ok(target) => target,
+                Err(_) => return Ok(Response::with(status::BadRequest)),
+            }
+        }
+        None => PackageTarget::default(),
+    };
+    let lock = req.get::<persistent::State<DepotUtil>>()
+        .expect("depot not found");
+    let depot = lock.read().expect("depot read lock is poisoned");
+    let mut ident_req = OriginPackageGet::new();
+    {
+        let params = req.extensions.get::<Router>().unwrap();
+        ident_req.set_ident(ident_from_params(params));
+    };
+
+    match route_message::<OriginPackageGet, OriginPackage>(req, &ident_req) {
+        Ok(package) => {
+            match depot.archive(package.get_ident(), &target) {
+                Some(mut archive) => {
+                    match archive.generate_sbom() {
+                        Ok(mut sbom) => {
+                            sbom.format = format;
+                            let mut response = Response::with((status::Ok, sbom.to_json()));
+                            response
+                                .headers
+                                .set(ContentType(Mime(TopLevel::Application,
+                                                      SubLevel::Json,
+                                                      vec![])));
+                            do_cache_response(&mut response);
+                            Ok(response)
+                        }
+                        Err(e) => {
+                            error!("package_sbom:1, err={:?}", e);
+                            Ok(Response::with(status::InternalServerError))
+                        }
+                    }
                 }
+                None => Ok(Response::with(status::NotFound)),
             }
         }
+        Err(err) => Ok(net_error_response("package_sbom:2", &err)),
     }
 }
 
@@ -1056,35 +1465,13 @@ fn list_unique_packages(req: &mut Request) -> IronResult<Response> {
                    packages.get_start(),
                    packages.get_stop(),
                    packages.get_count());
-            let body = package_results_json(&packages.get_idents().to_vec(),
-                                            packages.get_count() as isize,
-                                            packages.get_start() as isize,
-                                            packages.get_stop() as isize);
-
-            let mut response = if packages.get_count() as isize >
-                                  (packages.get_stop() as isize + 1) {
-                Response::with((status::PartialContent, body))
-            } else {
-                Response::with((status::Ok, body))
-            };
-
-            response
-                .headers
-                .set(ContentType(Mime(TopLevel::Application,
-                                      SubLevel::Json,
-                                      vec![(Attr::Charset, Value::Utf8)])));
-            dont_cache_response(&mut response);
-            Ok(response)
-        }
-        Err(err) => {
-            match err.get_code() {
-                ErrCode::ENTITY_NOT_FOUND => Ok(Response::with((status::NotFound))),
-                _ => {
-                    error!("list_unique_packages:2, err={:?}", err);
-                    Ok(Response::with(status::InternalServerError))
-                }
-            }
+            render_package_list(req,
+                                &packages.get_idents().to_vec(),
+                                packages.get_start(),
+                                packages.get_stop(),
+                                packages.get_count())
         }
+        Err(err) => Ok(net_error_response("list_unique_packages:2", &err)),
     }
 }
 
@@ -1143,35 +1530,13 @@ fn list_packages(req: &mut Request) -> IronResult<Response> {
                    packages.get_start(),
                    packages.get_stop(),
                    packages.get_count());
-            let body = package_results_json(&packages.get_idents().to_vec(),
-                                            packages.get_count() as isize,
-                                            packages.get_start() as isize,
-                                            packages.get_stop() as isize);
-
-            let mut response = if packages.get_count() as isize >
-                                  (packages.get_stop() as isize + 1) {
-                Response::with((status::PartialContent, body))
-            } else {
-                Response::with((status::Ok, body))
-            };
-
-            response
-                .headers
-                .set(ContentType(Mime(TopLevel::Application,
-                                      SubLevel::Json,
-                                      vec![(Attr::Charset, Value::Utf8)])));
-            dont_cache_response(&mut response);
-            Ok(response)
-        }
-        Err(err) => {
-            match err.get_code() {
-                ErrCode::ENTITY_NOT_FOUND => Ok(Response::with((status::NotFound))),
-                _ => {
-                    error!("list_packages:2, err={:?}", err);
-                    Ok(Response::with(status::InternalServerError))
-                }
-            }
+            render_package_list(req,
+                                &packages.get_idents().to_vec(),
+                                packages.get_start(),
+                                packages.get_stop(),
+                                packages.get_count())
         }
+        Err(err) => Ok(net_error_response("list_packages:2", &err)),
     }
 }
 
@@ -1229,6 +1594,10 @@ fn create_channel(req: &mut Request) -> IronResult<Response> {
         };
     }
 
+    if !is_valid_channel_name(&channel) {
+        return Ok(Response::with((status::UnprocessableEntity, "Invalid channel name")));
+    }
+
     let origin_id = match try!(get_origin(req, &origin)) {
         Some(origin) => {
             origin.get_id()
@@ -1281,7 +1650,7 @@ fn delete_channel(req: &mut Request) -> IronResult<Response> {
             }
 
             // stable and unstable can't be deleted
-            if channel == "stable" || channel == "unstable" {
+            if channel == STABLE_CHANNEL || channel == UNSTABLE_CHANNEL {
                 return Ok(Response::with(status::Forbidden));
             }
 
@@ -1296,15 +1665,7 @@ fn delete_channel(req: &mut Request) -> IronResult<Response> {
                 }
             }
         }
-        Err(err) => {
-            match err.get_code() {
-                ErrCode::ENTITY_NOT_FOUND => Ok(Response::with((status::NotFound))),
-                _ => {
-                    error!("delete_channel:1, err={:?}", err);
-                    Ok(Response::with(status::InternalServerError))
-                }
-            }
-        }
+        Err(err) => Ok(net_error_response("delete_channel:1", &err)),
     }
 }
 
@@ -1341,13 +1702,7 @@ fn show_package(req: &mut Request) -> IronResult<Response> {
                                                                                      &request) {
                 Ok(id) => ident = id.into(),
                 Err(err) => {
-                    match err.get_code() {
-                        ErrCode::ENTITY_NOT_FOUND => return Ok(Response::with((status::NotFound))),
-                        _ => {
-                            error!("show_package:2, err={:?}", err);
-                            return Ok(Response::with(status::InternalServerError));
-                        }
-                    }
+                    return Ok(net_error_response("show_package:2", &err));
                 }
             }
         }
@@ -1357,15 +1712,7 @@ fn show_package(req: &mut Request) -> IronResult<Response> {
         request.set_ident(ident);
         match route_message::<OriginChannelPackageGet, OriginPackage>(req, &request) {
             Ok(pkg) => render_package(&pkg, false),
-            Err(err) => {
-                match err.get_code() {
-                    ErrCode::ENTITY_NOT_FOUND => Ok(Response::with((status::NotFound))),
-                    _ => {
-                        error!("show_package:3, err={:?}", err);
-                        Ok(Response::with(status::InternalServerError))
-                    }
-                }
-            }
+            Err(err) => Ok(net_error_response("show_package:3", &err)),
         }
     } else {
         if !qualified {
@@ -1378,13 +1725,7 @@ fn show_package(req: &mut Request) -> IronResult<Response> {
             match route_message::<OriginPackageLatestGet, OriginPackageIdent>(req, &request) {
                 Ok(id) => ident = id.into(),
                 Err(err) => {
-                    match err.get_code() {
-                        ErrCode::ENTITY_NOT_FOUND => return Ok(Response::with((status::NotFound))),
-                        _ => {
-                            error!("show_package:5, err={:?}", err);
-                            return Ok(Response::with(status::InternalServerError));
-                        }
-                    }
+                    return Ok(net_error_response("show_package:5", &err));
                 }
             }
         }
@@ -1401,17 +1742,104 @@ fn show_package(req: &mut Request) -> IronResult<Response> {
                     render_package(&pkg, false)
                 }
             }
+            Err(err) => Ok(net_error_response("show_package:6", &err)),
+        }
+    }
+}
+
+// Answers whether a fully qualified package exists, optionally scoped to a channel, with an empty
+// `200` or `404` response. Clients use this to skip uploading packages the Depot already has.
+fn package_exists(req: &mut Request) -> IronResult<Response> {
+    let (ident, channel) = {
+        let params = req.extensions.get::<Router>().unwrap();
+        let ident = ident_from_params(params);
+        let channel = params.find("channel").map(|ch| ch.to_owned());
+        (ident, channel)
+    };
+
+    if !ident.fully_qualified() {
+        return Ok(Response::with(status::BadRequest));
+    }
+
+    let result = match channel {
+        Some(channel) => {
+            let mut request = OriginChannelPackageGet::new();
+            request.set_name(channel);
+            request.set_ident(ident);
+            route_message::<OriginChannelPackageGet, OriginPackage>(req, &request)
+        }
+        None => {
+            let mut request = OriginPackageGet::new();
+            request.set_ident(ident);
+            route_message::<OriginPackageGet, OriginPackage>(req, &request)
+        }
+    };
+
+    match result {
+        Ok(_) => Ok(Response::with(status::Ok)),
+        Err(err) => Ok(net_error_response("package_exists:1", &err)),
+    }
+}
+
+// Answers which of a JSON array of fully qualified idents exist, optionally scoped to the channel
+// given by the `channel` query parameter. Entries which aren't fully qualified idents, or whose
+// lookup failed, are reported under `errors` rather than failing the whole request.
+fn packages_exist(req: &mut Request) -> IronResult<Response> {
+    let idents = match req.get::<bodyparser::Struct<Vec<String>>>() {
+        Ok(Some(idents)) => idents,
+        _ => return Ok(Response::with(status::UnprocessableEntity)),
+    };
+    if idents.len() > PACKAGES_EXIST_MAX {
+        return Ok(Response::with((status::UnprocessableEntity,
+                                  format!("At most {} idents may be checked at once",
+                                          PACKAGES_EXIST_MAX))));
+    }
+    let channel = extract_query_value("channel", req);
+    let mut existence = PackagesExistence::default();
+
+    for value in idents {
+        let ident = match PackageIdent::from_str(&value) {
+            Ok(ident) => OriginPackageIdent::from(ident),
+            Err(_) => OriginPackageIdent::new(),
+        };
+        if !ident.fully_qualified() || !ident.valid() {
+            existence
+                .errors
+                .insert(value, "not a fully qualified package identifier".to_string());
+            continue;
+        }
+        let result = match channel {
+            Some(ref channel) => {
+                let mut request = OriginChannelPackageGet::new();
+                request.set_name(channel.clone());
+                request.set_ident(ident);
+                route_message::<OriginChannelPackageGet, OriginPackage>(req, &request)
+            }
+            None => {
+                let mut request = OriginPackageGet::new();
+                request.set_ident(ident);
+                route_message::<OriginPackageGet, OriginPackage>(req, &request)
+            }
+        };
+        match result {
+            Ok(_) => {
+                existence.packages.insert(value, true);
+            }
+            Err(ref err) if err.get_code() == ErrCode::ENTITY_NOT_FOUND => {
+                existence.packages.insert(value, false);
+            }
             Err(err) => {
-                match err.get_code() {
-                    ErrCode::ENTITY_NOT_FOUND => Ok(Response::with((status::NotFound))),
-                    _ => {
-                        error!("show_package:6, err={:?}", err);
-                        Ok(Response::with(status::InternalServerError))
-                    }
-                }
+                error!("packages_exist:1, err={:?}", err);
+                existence
+                    .errors
+                    .insert(value, format!("unable to check package, {}", err));
             }
         }
     }
+
+    let mut response = render_json(status::Ok, &existence);
+    dont_cache_response(&mut response);
+    Ok(response)
 }
 
 fn search_packages(req: &mut Request) -> IronResult<Response> {
@@ -1442,29 +1870,15 @@ fn search_packages(req: &mut Request) -> IronResult<Response> {
                    packages.get_start(),
                    packages.get_stop(),
                    packages.get_count());
-            let body = package_results_json(&packages.get_idents().to_vec(),
-                                            packages.get_count() as isize,
-                                            packages.get_start() as isize,
-                                            packages.get_stop() as isize);
-
-            let mut response = if packages.get_count() as isize >
-                                  (packages.get_stop() as isize + 1) {
-                Response::with((status::PartialContent, body))
-            } else {
-                Response::with((status::Ok, body))
-            };
-
-            response
-                .headers
-                .set(ContentType(Mime(TopLevel::Application,
-                                      SubLevel::Json,
-                                      vec![(Attr::Charset, Value::Utf8)])));
-            dont_cache_response(&mut response);
-            Ok(response)
+            render_package_list(req,
+                                &packages.get_idents().to_vec(),
+                                packages.get_start(),
+                                packages.get_stop(),
+                                packages.get_count())
         }
         Err(err) => {
             error!("search_packages:2, err={:?}", err);
-            Ok(Response::with(status::InternalServerError))
+            Ok(Response::with(net_err_to_http(err.get_code())))
         }
     }
 }
@@ -1504,6 +1918,10 @@ fn promote_package(req: &mut Request) -> IronResult<Response> {
             _ => return Ok(Response::with(status::BadRequest)),
         };
 
+        if !is_valid_channel_name(&channel) {
+            return Ok(Response::with((status::UnprocessableEntity, "Invalid channel name")));
+        }
+
         let pkg = match params.find("pkg") {
             Some(p) => p.to_string(),
             _ => return Ok(Response::with(status::BadRequest)),
@@ -1553,26 +1971,10 @@ fn promote_package(req: &mut Request) -> IronResult<Response> {
                         }
                     }
                 }
-                Err(err) => {
-                    match err.get_code() {
-                        ErrCode::ENTITY_NOT_FOUND => Ok(Response::with((status::NotFound))),
-                        _ => {
-                            error!("promote:2, err={:?}", err);
-                            Ok(Response::with(status::InternalServerError))
-                        }
-                    }
-                }
-            }
-        }
-        Err(err) => {
-            match err.get_code() {
-                ErrCode::ENTITY_NOT_FOUND => Ok(Response::with((status::NotFound))),
-                _ => {
-                    error!("promote_package:1, err={:?}", err);
-                    Ok(Response::with(status::InternalServerError))
-                }
+                Err(err) => Ok(net_error_response("promote:2", &err)),
             }
         }
+        Err(err) => Ok(net_error_response("promote_package:1", &err)),
     }
 }
 
@@ -1607,24 +2009,50 @@ fn target_from_headers(user_agent_header: &UserAgent) -> result::Result<PackageT
 // Returns a tuple representing the from and to values representing a paginated set.
 // The range (start, stop) values are zero-based.
 //
+// A request may ask for a page with the `page` and `per_page` parameters. Otherwise the legacy
+// `range` parameter gives the offset of a window of `PAGINATION_RANGE_MAX` items. A page or
+// offset too large to address is answered with a `400 Bad Request`.
+//
 // These values can be passed to a sorted set in Redis to return a paginated list.
 fn extract_pagination(req: &mut Request) -> result::Result<(isize, isize), Response> {
+    if let Some((page, per_page)) = parse_pagination(req) {
+        return match page_range(page, per_page) {
+                   Some((start, end)) => Ok((start as isize, end as isize)),
+                   None => Err(Response::with(status::BadRequest)),
+               };
+    }
     let range_from_param = match extract_query_value("range", req) {
         Some(range) => range,
         None => PAGINATION_RANGE_DEFAULT.to_string(),
     };
 
-    let offset = {
-        match range_from_param.parse::<usize>() {
-            Ok(range) => range as isize,
-            Err(_) => return Err(Response::with(status::BadRequest)),
-        }
+    let offset = match range_from_param.parse::<isize>() {
+        Ok(range) if range >= 0 => range,
+        _ => return Err(Response::with(status::BadRequest)),
     };
+    let end = match offset.checked_add(PAGINATION_RANGE_MAX - 1) {
+        Some(end) => end,
+        None => return Err(Response::with(status::BadRequest)),
+    };
+
+    debug!("extract_pagination range: (start, end): ({}, {})", offset, end);
+    Ok((offset, end))
+}
 
-    debug!("extract_pagination range: (start, end): ({}, {})",
-           offset,
-           (offset + PAGINATION_RANGE_MAX - 1));
-    Ok((offset, offset + PAGINATION_RANGE_MAX - 1))
+// Renders the page of package identifiers the back end returned for a listing paginated by
+// `extract_pagination`. Listings change as packages are uploaded, so they aren't cached.
+fn render_package_list(req: &mut Request,
+                       idents: &Vec<OriginPackageIdent>,
+                       start: u64,
+                       stop: u64,
+                       count: u64)
+                       -> IronResult<Response> {
+    let mut response = match parse_pagination(req) {
+        Some((page, per_page)) => try!(render_paginated(idents, page, per_page, count as usize)),
+        None => render_paginated_range(idents, start as usize, stop as usize, count as usize),
+    };
+    dont_cache_response(&mut response);
+    Ok(response)
 }
 
 fn extract_query_value(key: &str, req: &mut Request) -> Option<String> {
@@ -1650,6 +2078,45 @@ fn do_cache_response(response: &mut Response) {
         .set(CacheControl(format!("public, max-age={}", ONE_YEAR_IN_SECS)));
 }
 
+// Returns true if the client's cached copy of a download, described by the conditional headers of
+// its request, is still current. `If-None-Match` takes precedence over `If-Modified-Since`.
+fn is_not_modified(headers: &headers::Headers,
+                   etag: Option<&str>,
+                   modified: Option<SystemTime>)
+                   -> bool {
+    // Our entity tags are sent unquoted, so the header is compared raw rather than parsed.
+    if let Some(values) = headers.get_raw("If-None-Match") {
+        return values
+                   .iter()
+                   .filter_map(|value| str::from_utf8(value).ok())
+                   .flat_map(|value| value.split(','))
+                   .map(|tag| tag.trim().trim_left_matches("W/").trim_matches('"'))
+                   .any(|tag| tag == "*" || Some(tag) == etag);
+    }
+    match (headers.get::<IfModifiedSince>(), modified) {
+        (Some(&IfModifiedSince(since)), Some(modified)) => {
+            // HTTP dates only have a resolution of one second
+            match (modified.duration_since(UNIX_EPOCH),
+                   SystemTime::from(since).duration_since(UNIX_EPOCH)) {
+                (Ok(modified), Ok(since)) => modified.as_secs() <= since.as_secs(),
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+fn set_validator_headers(response: &mut Response,
+                         etag: Option<&str>,
+                         modified: Option<SystemTime>) {
+    if let Some(etag) = etag {
+        response.headers.set(ETag(etag.to_string()));
+    }
+    if let Some(modified) = modified {
+        response.headers.set(LastModified(HttpDate::from(modified)));
+    }
+}
+
 fn dont_cache_response(response: &mut Response) {
     response
         .headers
@@ -1668,6 +2135,8 @@ pub fn routes<M: BeforeMiddleware + Clone>(insecure: bool, basic: M, worker: M)
             "/channels/:origin/:channel/pkgs/:pkg/:version/latest" => show_package,
         channel_package_release: get
             "/channels/:origin/:channel/pkgs/:pkg/:version/:release" => show_package,
+        channel_package_exists: head
+            "/channels/:origin/:channel/pkgs/:pkg/:version/:release" => package_exists,
         channel_package_promote: put
             "/channels/:origin/:channel/pkgs/:pkg/:version/:release/promote" => {
             XHandler::new(promote_package).before(basic.clone())
@@ -1686,10 +2155,14 @@ pub fn routes<M: BeforeMiddleware + Clone>(insecure: bool, basic: M, worker: M)
         packages_version: get "/pkgs/:origin/:pkg/:version" => list_packages,
         package_version_latest: get "/pkgs/:origin/:pkg/:version/latest" => show_package,
         package: get "/pkgs/:origin/:pkg/:version/:release" => show_package,
+        package_exists: head "/pkgs/:origin/:pkg/:version/:release" => package_exists,
+        packages_exist: post "/pkgs/exists" => packages_exist,
 
         package_download: get "/pkgs/:origin/:pkg/:version/:release/download" => {
             download_package
         },
+        package_sbom: get "/pkgs/:origin/:pkg/:version/:release/sbom" => package_sbom,
+        package_log: get "/pkgs/:origin/:pkg/:version/:release/log" => download_package_log,
         package_upload: post "/pkgs/:origin/:pkg/:version/:release" => {
             if insecure {
                 XHandler::new(upload_package)
@@ -1713,6 +2186,13 @@ pub fn routes<M: BeforeMiddleware + Clone>(insecure: bool, basic: M, worker: M)
         origin: get "/origins/:origin" => origin_show,
 
         origin_keys: get "/origins/:origin/keys" => list_origin_keys,
+        origin_key_revision_create: post "/origins/:origin/keys" => {
+            if insecure {
+                XHandler::new(upload_origin_key_revision)
+            } else {
+                XHandler::new(upload_origin_key_revision).before(basic.clone())
+            }
+        },
         origin_key_latest: get "/origins/:origin/keys/latest" => download_latest_origin_key,
         origin_key: get "/origins/:origin/keys/:revision" => download_origin_key,
         origin_key_create: post "/origins/:origin/keys/:revision" => {
@@ -1764,10 +2244,23 @@ pub fn router(depot: DepotUtil) -> Result<Chain> {
     Ok(chain)
 }
 
+/// Remove uploads left staged by a previous run of the Depot. Failures are logged rather than
+/// preventing startup.
+pub fn remove_orphaned_uploads(depot: &DepotUtil) {
+    match depot.remove_orphaned_uploads(Duration::from_secs(ORPHANED_UPLOAD_MAX_AGE_SECS)) {
+        0 => (),
+        count => info!("Removed {} orphaned package upload(s)", count),
+    }
+}
+
 pub fn run(config: Config) -> Result<()> {
     let depot = DepotUtil::new(config.clone());
+    try!(depot.check_layout_version());
+    remove_orphaned_uploads(&depot);
     let v1 = try!(router(depot));
     let broker = Broker::run(DepotUtil::net_ident(), &config.route_addrs().clone());
+    try!(Broker::connect_with_timeout(config.route_addrs(),
+                                      Duration::from_millis(PING_TIMEOUT_MS)));
 
     let mut mount = Mount::new();
     mount.mount("/v1", v1);
@@ -1796,17 +2289,21 @@ mod test {
     use hyper;
     use hyper::net::NetworkStream;
     use hyper::buffer::BufReader;
-    use hyper::header::{Charset, ContentDisposition, DispositionType, DispositionParam};
+    use hyper::header::{Charset, ContentDisposition, DispositionType, DispositionParam,
+                        IfModifiedSince, LastModified};
 
     use hab_core::crypto::hash;
     use protocol::net::{self, ErrCode};
     use protocol::sessionsrv::Session;
 
-    use std::env;
+    use std::{env, isize, usize};
     use std::fs::File;
     use std::io::Cursor;
     use std::path::PathBuf;
 
+    use bld_core::channel::{INVALID_CHANNEL_NAMES, VALID_CHANNEL_NAMES};
+    use bld_core::metrics;
+
     use super::*;
     use super::super::DepotUtil;
 
@@ -1826,12 +2323,31 @@ mod test {
             .join(name)
     }
 
+    fn test_config() -> Config {
+        let mut config = Config::default();
+        config.path = env::temp_dir()
+            .join("depot-tests")
+            .to_string_lossy()
+            .to_string();
+        config
+    }
+
     fn iron_request(method: method::Method,
                     path: &str,
                     body: &mut Vec<u8>,
                     headers: Headers,
                     broker: TestableBroker)
                     -> (IronResult<Response>, RoutedMessages) {
+        iron_request_with_config(method, path, body, headers, broker, test_config())
+    }
+
+    fn iron_request_with_config(method: method::Method,
+                                path: &str,
+                                body: &mut Vec<u8>,
+                                headers: Headers,
+                                broker: TestableBroker,
+                                config: Config)
+                                -> (IronResult<Response>, RoutedMessages) {
         let url = Url::parse(path).unwrap();
         let mut buffer = String::new();
         buffer.push_str(&format!("{} {} HTTP/1.1\r\n", &method, url));
@@ -1849,12 +2365,6 @@ mod test {
         let http_request = hyper::server::Request::new(&mut buf_reader, addr).unwrap();
         let mut req = Request::from_http(http_request, addr, &iron::Protocol::http()).unwrap();
 
-
-        let mut config = Config::default();
-        config.path = env::temp_dir()
-            .join("depot-tests")
-            .to_string_lossy()
-            .to_string();
         let depot = DepotUtil::new(config);
         req.extensions.insert::<Authenticated>(Session::new());
         req.extensions.insert::<TestableBroker>(broker);
@@ -1871,6 +2381,84 @@ mod test {
         (resp, msgs)
     }
 
+    const CORE_PUBLIC_KEY: &'static str = "SIG-PUB-1
+core-20170503150000
+
+YFt55lgI3l/vcTEp/7IFGIXgnrLr5bWMADbIvuHKkoY=
+";
+
+    fn key_upload_broker() -> TestableBroker {
+        let mut broker: TestableBroker = Default::default();
+        let mut access_res = CheckOriginAccessResponse::new();
+        access_res.set_has_access(true);
+        broker.setup::<CheckOriginAccessRequest, CheckOriginAccessResponse>(&access_res);
+        let mut origin_res = Origin::new();
+        origin_res.set_id(5000);
+        origin_res.set_name("core".to_string());
+        broker.setup::<OriginGet, Origin>(&origin_res);
+        broker.setup::<OriginPublicKeyCreate, OriginPublicKey>(&OriginPublicKey::new());
+        broker
+    }
+
+    #[test]
+    fn upload_origin_key_revision() {
+        let (response, msgs) = iron_request(method::Post,
+                                            "http://localhost/origins/core/keys",
+                                            &mut CORE_PUBLIC_KEY.as_bytes().to_vec(),
+                                            Headers::new(),
+                                            key_upload_broker());
+        let response = response.unwrap();
+        assert_eq!(response.status, Some(status::Created));
+        assert_eq!(response::extract_body_to_string(response),
+                   "/origins/core/keys/20170503150000");
+
+        let create = msgs.get::<OriginPublicKeyCreate>().unwrap();
+        assert_eq!(create.get_name(), "core");
+        assert_eq!(create.get_origin_id(), 5000);
+        assert_eq!(create.get_revision(), "20170503150000");
+        assert_eq!(create.get_body(), CORE_PUBLIC_KEY.as_bytes());
+    }
+
+    #[test]
+    fn upload_origin_key() {
+        let (response, msgs) = iron_request(method::Post,
+                                            "http://localhost/origins/core/keys/20170503150000",
+                                            &mut CORE_PUBLIC_KEY.as_bytes().to_vec(),
+                                            Headers::new(),
+                                            key_upload_broker());
+        let response = response.unwrap();
+        assert_eq!(response.status, Some(status::Created));
+        assert_eq!(response::extract_body_to_string(response),
+                   "/origins/core/keys/20170503150000");
+
+        let create = msgs.get::<OriginPublicKeyCreate>().unwrap();
+        assert_eq!(create.get_name(), "core");
+        assert_eq!(create.get_origin_id(), 5000);
+        assert_eq!(create.get_revision(), "20170503150000");
+        assert_eq!(create.get_body(), CORE_PUBLIC_KEY.as_bytes());
+    }
+
+    #[test]
+    fn upload_origin_key_revision_malformed() {
+        let keys = ["SIG-PUB-1\ncore-20170503150000\n\nnot base64!\n",
+                    "SIG-PUB-1\ncore\n\nYFt55lgI3l/vcTEp/7IFGIXgnrLr5bWMADbIvuHKkoY=\n",
+                    "SIG-SEC-1\ncore-20170503150000\n\nYFt55lgI3l/vcTEp/7IFGIXgnrLr5bWMADbIvuHKkoY=\n",
+                    "SIG-PUB-1\nother-20170503150000\n\nYFt55lgI3l/vcTEp/7IFGIXgnrLr5bWMADbIvuHKkoY=\n",
+                    ""];
+        for key in keys.iter() {
+            let (response, msgs) = iron_request(method::Post,
+                                                "http://localhost/origins/core/keys",
+                                                &mut key.as_bytes().to_vec(),
+                                                Headers::new(),
+                                                key_upload_broker());
+            assert_eq!(response.unwrap().status,
+                       Some(status::UnprocessableEntity),
+                       "key {:?}",
+                       key);
+            assert!(msgs.get::<OriginPublicKeyCreate>().is_err());
+        }
+    }
+
     #[test]
     fn list_public_keys() {
         let mut broker: TestableBroker = Default::default();
@@ -1945,6 +2533,7 @@ mod test {
         access_res.set_has_access(true);
         broker.setup::<CheckOriginAccessRequest, CheckOriginAccessResponse>(&access_res);
         broker.setup_error::<OriginPackageGet>(net::err(ErrCode::ENTITY_NOT_FOUND, ""));
+        broker.setup_error::<OriginPublicKeyGet>(net::err(ErrCode::ENTITY_NOT_FOUND, ""));
 
         let mut origin_res = Origin::new();
         origin_res.set_id(5000);
@@ -1987,6 +2576,238 @@ mod test {
         assert_eq!(package_req.get_target().to_string(), target.to_string());
     }
 
+    #[test]
+    fn upload_package_to_unknown_origin() {
+        let depot = DepotUtil::new(test_config());
+        let mut ident = OriginPackageIdent::new();
+        ident.set_origin("core".to_string());
+        ident.set_name("cacerts".to_string());
+        ident.set_version("2017.01.17".to_string());
+        ident.set_release("20170209064044".to_string());
+        let target = PackageTarget::from_str("x86_64-windows").unwrap();
+        let file_name = depot.archive_path(&ident, &target);
+        let _ = fs::remove_file(&file_name);
+
+        let mut broker: TestableBroker = Default::default();
+        let mut access_res = CheckOriginAccessResponse::new();
+        access_res.set_has_access(true);
+        broker.setup::<CheckOriginAccessRequest, CheckOriginAccessResponse>(&access_res);
+        broker.setup_error::<OriginPackageGet>(net::err(ErrCode::ENTITY_NOT_FOUND, ""));
+        broker.setup_error::<OriginPublicKeyGet>(net::err(ErrCode::ENTITY_NOT_FOUND, ""));
+        broker.setup_error::<OriginGet>(net::err(ErrCode::ENTITY_NOT_FOUND, ""));
+
+        let mut body: Vec<u8> = Vec::new();
+        let path = hart_file("core-cacerts-2017.01.17-20170209064044-x86_64-windows.hart");
+        File::open(&path)
+            .unwrap()
+            .read_to_end(&mut body)
+            .unwrap();
+        let checksum = hash::hash_file(&path).unwrap();
+
+        let (response, msgs) = iron_request(method::Post,
+                     format!("http://localhost/pkgs/core/cacerts/2017.01.17/20170209064044?checksum={}",
+                             checksum)
+                             .as_str(),
+                     &mut body,
+                     Headers::new(),
+                     broker);
+
+        assert_eq!(response.unwrap().status, Some(status::NotFound));
+        assert!(fs::metadata(&file_name).is_err());
+        assert!(msgs.get::<OriginPackageCreate>().is_err());
+    }
+
+    #[test]
+    fn upload_package_without_router() {
+        let depot = DepotUtil::new(test_config());
+        let mut ident = OriginPackageIdent::new();
+        ident.set_origin("core".to_string());
+        ident.set_name("cacerts".to_string());
+        ident.set_version("2017.01.17".to_string());
+        ident.set_release("20170209064044".to_string());
+        let target = PackageTarget::from_str("x86_64-windows").unwrap();
+        let file_name = depot.archive_path(&ident, &target);
+        let _ = fs::remove_file(&file_name);
+
+        let mut broker: TestableBroker = Default::default();
+        let mut access_res = CheckOriginAccessResponse::new();
+        access_res.set_has_access(true);
+        broker.setup::<CheckOriginAccessRequest, CheckOriginAccessResponse>(&access_res);
+        broker.setup_error::<OriginPackageGet>(net::err(ErrCode::ENTITY_NOT_FOUND, ""));
+        broker.setup_error::<OriginPublicKeyGet>(net::err(ErrCode::ENTITY_NOT_FOUND, ""));
+        broker.setup::<OriginGet, Origin>(&Origin::new());
+        broker.setup_error::<OriginPackageCreate>(net::err(ErrCode::ZMQ, "dp:route:0"));
+
+        let mut body: Vec<u8> = Vec::new();
+        let path = hart_file("core-cacerts-2017.01.17-20170209064044-x86_64-windows.hart");
+        File::open(&path)
+            .unwrap()
+            .read_to_end(&mut body)
+            .unwrap();
+        let checksum = hash::hash_file(&path).unwrap();
+
+        let (response, _) = iron_request(method::Post,
+                     format!("http://localhost/pkgs/core/cacerts/2017.01.17/20170209064044?checksum={}",
+                             checksum)
+                             .as_str(),
+                     &mut body,
+                     Headers::new(),
+                     broker);
+
+        assert_eq!(response.unwrap().status, Some(status::ServiceUnavailable));
+        assert!(fs::metadata(&file_name).is_err());
+    }
+
+    #[test]
+    fn upload_package_signed_by_another_origin() {
+        let mut broker: TestableBroker = Default::default();
+        let mut access_res = CheckOriginAccessResponse::new();
+        access_res.set_has_access(true);
+        broker.setup::<CheckOriginAccessRequest, CheckOriginAccessResponse>(&access_res);
+
+        //re-sign the fixture's header with a key from another origin
+        let mut body: Vec<u8> = Vec::new();
+        File::open(hart_file("core-cacerts-2017.01.17-20170209064044-x86_64-windows.hart"))
+            .unwrap()
+            .read_to_end(&mut body)
+            .unwrap();
+        let signer = b"\ncore-20160810182414\n";
+        let pos = body
+            .windows(signer.len())
+            .position(|window| window == signer)
+            .unwrap();
+        body[pos + 1..pos + 5].copy_from_slice(b"evil");
+        let checksum = hash::hash_bytes(&body).unwrap();
+
+        let (response, msgs) = iron_request(method::Post,
+                     format!("http://localhost/pkgs/core/cacerts/2017.01.17/20170209064044?checksum={}",
+                             checksum)
+                             .as_str(),
+                     &mut body,
+                     Headers::new(),
+                     broker);
+
+        assert_eq!(response.unwrap().status, Some(status::UnprocessableEntity));
+        assert!(msgs.get::<OriginPackageCreate>().is_err());
+    }
+
+    fn core_fixture(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../core/tests/fixtures")
+            .join(name)
+    }
+
+    // Uploads the happyhumans/possums fixture, signed by happyhumans-20160424223347, to a depot
+    // which may require signed uploads and may have the signing key.
+    fn upload_signed_package(body: &mut Vec<u8>,
+                             require_signed_uploads: bool,
+                             key_known: bool)
+                             -> (IronResult<Response>, RoutedMessages) {
+        let mut config = test_config();
+        config.require_signed_uploads = require_signed_uploads;
+        let depot = DepotUtil::new(config.clone());
+        let mut ident = OriginPackageIdent::new();
+        ident.set_origin("happyhumans".to_string());
+        ident.set_name("possums".to_string());
+        ident.set_version("8.1.4".to_string());
+        ident.set_release("20160427165340".to_string());
+        let target = PackageTarget::from_str("x86_64-linux").unwrap();
+        let _ = fs::remove_file(depot.archive_path(&ident, &target));
+
+        let mut key_body = String::new();
+        File::open(core_fixture("happyhumans-20160424223347.pub"))
+            .unwrap()
+            .read_to_string(&mut key_body)
+            .unwrap();
+        let mut key = OriginPublicKey::new();
+        key.set_name("happyhumans".to_string());
+        key.set_revision("20160424223347".to_string());
+        key.set_body(key_body.into_bytes());
+
+        let mut broker: TestableBroker = Default::default();
+        let mut access_res = CheckOriginAccessResponse::new();
+        access_res.set_has_access(true);
+        broker.setup::<CheckOriginAccessRequest, CheckOriginAccessResponse>(&access_res);
+        if key_known {
+            broker.setup::<OriginPublicKeyGet, OriginPublicKey>(&key);
+        } else {
+            broker.setup_error::<OriginPublicKeyGet>(net::err(ErrCode::ENTITY_NOT_FOUND, ""));
+        }
+        broker.setup_error::<OriginPackageGet>(net::err(ErrCode::ENTITY_NOT_FOUND, ""));
+        broker.setup::<OriginGet, Origin>(&Origin::new());
+        broker.setup::<OriginPackageCreate, OriginPackage>(&OriginPackage::new());
+
+        let checksum = hash::hash_bytes(body).unwrap();
+        iron_request_with_config(method::Post,
+                                 format!("http://localhost/pkgs/happyhumans/possums/8.1.4/20160427165340?checksum={}",
+                                         checksum)
+                                         .as_str(),
+                                 body,
+                                 Headers::new(),
+                                 broker,
+                                 config)
+    }
+
+    #[test]
+    fn upload_package_requiring_signature() {
+        let mut body: Vec<u8> = Vec::new();
+        File::open(core_fixture("happyhumans-possums-8.1.4-20160427165340-x86_64-linux.hart"))
+            .unwrap()
+            .read_to_end(&mut body)
+            .unwrap();
+
+        let (response, msgs) = upload_signed_package(&mut body, true, true);
+
+        assert_eq!(response.unwrap().status, Some(status::Created));
+        let key_req = msgs.get::<OriginPublicKeyGet>().unwrap();
+        assert_eq!(key_req.get_origin(), "happyhumans");
+        assert_eq!(key_req.get_revision(), "20160424223347");
+        assert!(msgs.get::<OriginPackageCreate>().is_ok());
+    }
+
+    #[test]
+    fn upload_package_requiring_signature_rejects_bad_signature() {
+        let mut body: Vec<u8> = Vec::new();
+        File::open(core_fixture("happyhumans-possums-8.1.4-20160427165340-x86_64-linux.hart"))
+            .unwrap()
+            .read_to_end(&mut body)
+            .unwrap();
+        //alter the first character of the signature, the fourth line of the header
+        let pos = body.iter()
+            .enumerate()
+            .filter(|&(_, byte)| *byte == b'\n')
+            .nth(2)
+            .map(|(pos, _)| pos + 1)
+            .unwrap();
+        body[pos] = if body[pos] == b'A' { b'B' } else { b'A' };
+
+        let (response, msgs) = upload_signed_package(&mut body.clone(), true, true);
+        assert_eq!(response.unwrap().status, Some(status::UnprocessableEntity));
+        assert!(msgs.get::<OriginPackageCreate>().is_err());
+
+        //the signature is checked against a known key even when signing isn't required
+        let (response, msgs) = upload_signed_package(&mut body, false, true);
+        assert_eq!(response.unwrap().status, Some(status::UnprocessableEntity));
+        assert!(msgs.get::<OriginPackageCreate>().is_err());
+    }
+
+    #[test]
+    fn upload_package_with_unknown_key() {
+        let mut body: Vec<u8> = Vec::new();
+        File::open(core_fixture("happyhumans-possums-8.1.4-20160427165340-x86_64-linux.hart"))
+            .unwrap()
+            .read_to_end(&mut body)
+            .unwrap();
+
+        let (response, msgs) = upload_signed_package(&mut body.clone(), true, false);
+        assert_eq!(response.unwrap().status, Some(status::UnprocessableEntity));
+        assert!(msgs.get::<OriginPackageCreate>().is_err());
+
+        let (response, msgs) = upload_signed_package(&mut body, false, false);
+        assert_eq!(response.unwrap().status, Some(status::Created));
+        assert!(msgs.get::<OriginPackageCreate>().is_ok());
+    }
+
     #[test]
     fn download_package() {
         //upload hart so it gets saved to disk
@@ -1995,6 +2816,9 @@ mod test {
         access_res.set_has_access(true);
         upload_broker.setup::<CheckOriginAccessRequest, CheckOriginAccessResponse>(&access_res);
         upload_broker.setup_error::<OriginPackageGet>(net::err(ErrCode::ENTITY_NOT_FOUND, ""));
+        upload_broker
+            .setup_error::<OriginPublicKeyGet>(net::err(ErrCode::ENTITY_NOT_FOUND, ""));
+        upload_broker.setup::<OriginGet, Origin>(&Origin::new());
         upload_broker.setup::<OriginPackageCreate, OriginPackage>(&OriginPackage::new());
 
         let mut body: Vec<u8> = Vec::new();
@@ -2028,6 +2852,7 @@ mod test {
         headers.set(UserAgent("hab/0.20.0-dev/20170326090935 (x86_64-windows; 10.0.14915)"
                                   .to_string()));
 
+        let recorder = metrics::record();
         let (response, _) = iron_request(method::Get,
                                          "http://localhost/pkgs/core/cacerts/2017.01.17/20170209064045/download",
                                          &mut Vec::new(),
@@ -2037,6 +2862,8 @@ mod test {
         //assert headers
         let response = response.unwrap();
         assert_eq!(response.status, Some(status::Ok));
+        let downloads = Counter::PackageDownload("core".to_string(), "cacerts".to_string());
+        assert_eq!(recorder.increments(&downloads), 1);
         let disp = ContentDisposition {
             disposition: DispositionType::Attachment,
             parameters: vec![DispositionParam::Filename(
@@ -2052,6 +2879,239 @@ mod test {
         assert_eq!(result_body, body);
     }
 
+    #[test]
+    fn download_package_not_found_is_not_counted() {
+        let mut broker: TestableBroker = Default::default();
+        broker.setup_error::<OriginPackageGet>(net::err(ErrCode::ENTITY_NOT_FOUND, ""));
+
+        let mut headers = Headers::new();
+        headers.set(UserAgent("hab/0.20.0-dev/20170326090935 (x86_64-windows; 10.0.14915)"
+                                  .to_string()));
+
+        let recorder = metrics::record();
+        let (response, _) = iron_request(method::Get,
+                                         "http://localhost/pkgs/core/cacerts/2017.01.17/20170209064045/download",
+                                         &mut Vec::new(),
+                                         headers,
+                                         broker);
+
+        assert_eq!(response.unwrap().status, Some(status::NotFound));
+        let downloads = Counter::PackageDownload("core".to_string(), "cacerts".to_string());
+        assert_eq!(recorder.increments(&downloads), 0);
+    }
+
+    #[test]
+    fn download_package_conditional_get() {
+        //upload hart so it gets saved to disk
+        let mut upload_broker: TestableBroker = Default::default();
+        let mut access_res = CheckOriginAccessResponse::new();
+        access_res.set_has_access(true);
+        upload_broker.setup::<CheckOriginAccessRequest, CheckOriginAccessResponse>(&access_res);
+        upload_broker.setup_error::<OriginPackageGet>(net::err(ErrCode::ENTITY_NOT_FOUND, ""));
+        upload_broker
+            .setup_error::<OriginPublicKeyGet>(net::err(ErrCode::ENTITY_NOT_FOUND, ""));
+        upload_broker.setup::<OriginGet, Origin>(&Origin::new());
+        upload_broker.setup::<OriginPackageCreate, OriginPackage>(&OriginPackage::new());
+
+        let mut body: Vec<u8> = Vec::new();
+        let path = hart_file("core-cacerts-2017.01.17-20170209064045-x86_64-windows.hart");
+        File::open(&path)
+            .unwrap()
+            .read_to_end(&mut body)
+            .unwrap();
+        let checksum = hash::hash_file(&path).unwrap();
+
+        iron_request(method::Post,
+                     format!("http://localhost/pkgs/core/cacerts/2017.01.17/20170209064045?checksum={}",
+                             checksum)
+                             .as_str(),
+                     &mut body,
+                     Headers::new(),
+                     upload_broker);
+
+        let mut package = OriginPackage::new();
+        let mut ident = OriginPackageIdent::new();
+        ident.set_origin("core".to_string());
+        ident.set_name("cacerts".to_string());
+        ident.set_version("2017.01.17".to_string());
+        ident.set_release("20170209064045".to_string());
+        package.set_ident(ident);
+        package.set_checksum(checksum.clone());
+
+        let url = "http://localhost/pkgs/core/cacerts/2017.01.17/20170209064045/download";
+        let user_agent = UserAgent("hab/0.20.0-dev/20170326090935 (x86_64-windows; 10.0.14915)"
+                                       .to_string());
+
+        //a fresh download carries the validators
+        let mut broker: TestableBroker = Default::default();
+        broker.setup::<OriginPackageGet, OriginPackage>(&package);
+        let mut headers = Headers::new();
+        headers.set(user_agent.clone());
+        let (response, _) = iron_request(method::Get, url, &mut Vec::new(), headers, broker);
+        let response = response.unwrap();
+        assert_eq!(response.status, Some(status::Ok));
+        assert_eq!(response.headers.get::<ETag>(), Some(&ETag(checksum.clone())));
+        let last_modified = response.headers.get::<LastModified>().unwrap().clone();
+
+        //a matching entity tag is not modified
+        let mut broker: TestableBroker = Default::default();
+        broker.setup::<OriginPackageGet, OriginPackage>(&package);
+        let mut headers = Headers::new();
+        headers.set(user_agent.clone());
+        headers.set_raw("If-None-Match", vec![checksum.clone().into_bytes()]);
+        let recorder = metrics::record();
+        let (response, _) = iron_request(method::Get, url, &mut Vec::new(), headers, broker);
+        let response = response.unwrap();
+        assert_eq!(response.status, Some(status::NotModified));
+        let downloads = Counter::PackageDownload("core".to_string(), "cacerts".to_string());
+        assert_eq!(recorder.increments(&downloads), 0);
+
+        //a stale entity tag is downloaded again
+        let mut broker: TestableBroker = Default::default();
+        broker.setup::<OriginPackageGet, OriginPackage>(&package);
+        let mut headers = Headers::new();
+        headers.set(user_agent.clone());
+        headers.set_raw("If-None-Match", vec![b"\"stale\"".to_vec()]);
+        let (response, _) = iron_request(method::Get, url, &mut Vec::new(), headers, broker);
+        assert_eq!(response.unwrap().status, Some(status::Ok));
+
+        //an unchanged modification date is not modified
+        let mut broker: TestableBroker = Default::default();
+        broker.setup::<OriginPackageGet, OriginPackage>(&package);
+        let mut headers = Headers::new();
+        headers.set(user_agent);
+        headers.set(IfModifiedSince(last_modified.0));
+        let (response, _) = iron_request(method::Get, url, &mut Vec::new(), headers, broker);
+        assert_eq!(response.unwrap().status, Some(status::NotModified));
+    }
+
+    #[test]
+    fn package_sbom() {
+        //upload hart so it gets saved to disk
+        let mut upload_broker: TestableBroker = Default::default();
+        let mut access_res = CheckOriginAccessResponse::new();
+        access_res.set_has_access(true);
+        upload_broker.setup::<CheckOriginAccessRequest, CheckOriginAccessResponse>(&access_res);
+        upload_broker.setup_error::<OriginPackageGet>(net::err(ErrCode::ENTITY_NOT_FOUND, ""));
+        upload_broker
+            .setup_error::<OriginPublicKeyGet>(net::err(ErrCode::ENTITY_NOT_FOUND, ""));
+        upload_broker.setup::<OriginGet, Origin>(&Origin::new());
+        upload_broker.setup::<OriginPackageCreate, OriginPackage>(&OriginPackage::new());
+
+        let mut body: Vec<u8> = Vec::new();
+        let path = hart_file("core-cacerts-2017.01.17-20170209064045-x86_64-windows.hart");
+        File::open(&path)
+            .unwrap()
+            .read_to_end(&mut body)
+            .unwrap();
+        let checksum = hash::hash_file(&path).unwrap();
+
+        iron_request(method::Post,
+                                    format!("http://localhost/pkgs/core/cacerts/2017.01.17/20170209064045?checksum={}", checksum).as_str(),
+                                    &mut body,
+                                    Headers::new(),
+                                    upload_broker);
+
+        let mut sbom_broker: TestableBroker = Default::default();
+        let mut package = OriginPackage::new();
+        let mut ident = OriginPackageIdent::new();
+        ident.set_origin("core".to_string());
+        ident.set_name("cacerts".to_string());
+        ident.set_version("2017.01.17".to_string());
+        ident.set_release("20170209064045".to_string());
+        package.set_ident(ident);
+        sbom_broker.setup::<OriginPackageGet, OriginPackage>(&package);
+
+        let (response, _) = iron_request(method::Get,
+                                         "http://localhost/pkgs/core/cacerts/2017.01.17/20170209064045/sbom?format=cyclonedx&target=x86_64-windows",
+                                         &mut Vec::new(),
+                                         Headers::new(),
+                                         sbom_broker);
+
+        let response = response.unwrap();
+        assert_eq!(response.status, Some(status::Ok));
+        let result_body = response::extract_body_to_string(response);
+        let sbom: serde_json::Value = serde_json::from_str(&result_body).unwrap();
+        assert_eq!(sbom["bomFormat"], "CycloneDX");
+        assert_eq!(sbom["specVersion"], "1.4");
+        assert_eq!(sbom["metadata"]["component"]["purl"],
+                   "pkg:habitat/core/cacerts@2017.01.17-20170209064045");
+    }
+
+    // Writes a build log for the given release of core/cacerts/2017.01.17 into the test depot.
+    fn write_package_log(release: &str, content: &[u8]) {
+        let depot = DepotUtil::new(test_config());
+        let mut ident = OriginPackageIdent::new();
+        ident.set_origin("core".to_string());
+        ident.set_name("cacerts".to_string());
+        ident.set_version("2017.01.17".to_string());
+        ident.set_release(release.to_string());
+        let path = depot.log_path(&ident, &PackageTarget::from_str("x86_64-linux").unwrap());
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        File::create(&path).unwrap().write_all(content).unwrap();
+    }
+
+    #[test]
+    fn download_package_log() {
+        let log = b"   cacerts: Loading /src/plans/cacerts/plan.sh\n   cacerts: Build complete\n";
+        write_package_log("20170209064045", log);
+
+        let (response, _) =
+            iron_request(method::Get,
+                         "http://localhost/pkgs/core/cacerts/2017.01.17/20170209064045/log?target=x86_64-linux",
+                         &mut Vec::new(),
+                         Headers::new(),
+                         Default::default());
+        let response = response.unwrap();
+        assert_eq!(response.status, Some(status::Ok));
+        assert_eq!(response.headers.get::<ContentType>().unwrap().to_string(),
+                   "text/plain; charset=utf-8");
+        assert_eq!(response::extract_body_to_bytes(response), log.to_vec());
+
+        let (response, _) =
+            iron_request(method::Get,
+                         "http://localhost/pkgs/core/cacerts/2017.01.17/20170209064046/log?target=x86_64-linux",
+                         &mut Vec::new(),
+                         Headers::new(),
+                         Default::default());
+        assert_eq!(response.unwrap().status, Some(status::NotFound));
+    }
+
+    #[test]
+    fn download_package_log_range() {
+        let log = b"0123456789";
+        write_package_log("20170209064047", log);
+        let url = "http://localhost/pkgs/core/cacerts/2017.01.17/20170209064047/log?target=x86_64-linux";
+
+        let mut headers = Headers::new();
+        headers.set(Range::bytes(2, 5));
+        let (response, _) =
+            iron_request(method::Get, url, &mut Vec::new(), headers, Default::default());
+        let response = response.unwrap();
+        assert_eq!(response.status, Some(status::PartialContent));
+        assert_eq!(response.headers.get::<ContentRange>().unwrap().to_string(),
+                   "bytes 2-5/10");
+        assert_eq!(response.headers.get::<ContentLength>(), Some(&ContentLength(4)));
+        assert_eq!(response::extract_body_to_bytes(response), b"2345".to_vec());
+
+        let mut headers = Headers::new();
+        headers.set(Range::Bytes(vec![ByteRangeSpec::Last(3)]));
+        let (response, _) =
+            iron_request(method::Get, url, &mut Vec::new(), headers, Default::default());
+        let response = response.unwrap();
+        assert_eq!(response.status, Some(status::PartialContent));
+        assert_eq!(response::extract_body_to_bytes(response), b"789".to_vec());
+
+        let mut headers = Headers::new();
+        headers.set(Range::Bytes(vec![ByteRangeSpec::AllFrom(10)]));
+        let (response, _) =
+            iron_request(method::Get, url, &mut Vec::new(), headers, Default::default());
+        let response = response.unwrap();
+        assert_eq!(response.status, Some(status::RangeNotSatisfiable));
+        assert_eq!(response.headers.get::<ContentRange>().unwrap().to_string(),
+                   "bytes */10");
+    }
+
     #[test]
     fn list_unique_packages() {
         let mut broker: TestableBroker = Default::default();
@@ -2175,6 +3235,80 @@ mod test {
         assert_eq!(package_req.get_ident().to_string(), "org/".to_string());
     }
 
+    #[test]
+    fn list_packages_by_page() {
+        let mut broker: TestableBroker = Default::default();
+
+        let mut pkg_res = OriginPackageListResponse::new();
+        pkg_res.set_start(20);
+        pkg_res.set_stop(29);
+        pkg_res.set_count(45);
+        let mut packages = protobuf::RepeatedField::new();
+
+        let mut ident = OriginPackageIdent::new();
+        ident.set_origin("org".to_string());
+        ident.set_name("name1".to_string());
+        packages.push(ident);
+
+        pkg_res.set_idents(packages);
+        broker.setup::<OriginPackageListRequest, OriginPackageListResponse>(&pkg_res);
+
+        let (response, msgs) = iron_request(method::Get,
+                                            "http://localhost/pkgs/org?page=3&per_page=10",
+                                            &mut Vec::new(),
+                                            Headers::new(),
+                                            broker);
+
+        let response = response.unwrap();
+        assert_eq!(response.status, Some(status::PartialContent));
+
+        let result_body = response::extract_body_to_string(response);
+
+        assert_eq!(result_body,
+                   "{\
+            \"range_start\":20,\
+            \"range_end\":29,\
+            \"total_count\":45,\
+            \"package_list\":[\
+                {\
+                    \"origin\":\"org\",\
+                    \"name\":\"name1\"\
+                }\
+            ]\
+        }");
+
+        let package_req = msgs.get::<OriginPackageListRequest>().unwrap();
+        assert_eq!(package_req.get_start(), 20);
+        assert_eq!(package_req.get_stop(), 29);
+
+        // Page sizes are clamped to the maximum
+        let mut broker: TestableBroker = Default::default();
+        broker.setup::<OriginPackageListRequest, OriginPackageListResponse>(&pkg_res);
+        let (_, msgs) = iron_request(method::Get,
+                                     "http://localhost/pkgs/org?per_page=500",
+                                     &mut Vec::new(),
+                                     Headers::new(),
+                                     broker);
+        let package_req = msgs.get::<OriginPackageListRequest>().unwrap();
+        assert_eq!(package_req.get_start(), 0);
+        assert_eq!(package_req.get_stop(), 99);
+    }
+
+    #[test]
+    fn list_packages_out_of_range() {
+        for url in &[format!("http://localhost/pkgs/org?page={}&per_page=100", usize::MAX),
+                     format!("http://localhost/pkgs/org?range={}", isize::MAX),
+                     "http://localhost/pkgs/org?range=-1".to_string()] {
+            let (response, msgs) = iron_request(method::Get,
+                                                url,
+                                                &mut Vec::new(),
+                                                Headers::new(),
+                                                Default::default());
+            assert_eq!(response.unwrap().status, Some(status::BadRequest), "{}", url);
+            assert!(msgs.get::<OriginPackageListRequest>().is_err());
+        }
+    }
+
     #[test]
     fn list_channel_packages() {
         let mut broker: TestableBroker = Default::default();
@@ -2593,6 +3727,168 @@ mod test {
         assert_eq!(package_req.get_name().to_string(), "channel".to_string());
     }
 
+    #[test]
+    fn package_exists_in_channel() {
+        let mut broker: TestableBroker = Default::default();
+
+        let mut ident = OriginPackageIdent::new();
+        ident.set_origin("org".to_string());
+        ident.set_name("name".to_string());
+        ident.set_version("1.1.1".to_string());
+        ident.set_release("20170101010101".to_string());
+        let mut package = OriginPackage::new();
+        package.set_ident(ident.clone());
+        broker.setup::<OriginChannelPackageGet, OriginPackage>(&package);
+
+        let (response, msgs) = iron_request(method::Head,
+                                            "http://localhost/channels/org/channel/pkgs/name/1.1.1/20170101010101",
+                                            &mut Vec::new(),
+                                            Headers::new(),
+                                            broker);
+
+        let response = response.unwrap();
+        assert_eq!(response.status, Some(status::Ok));
+        assert_eq!(response::extract_body_to_string(response), "");
+
+        let package_req = msgs.get::<OriginChannelPackageGet>().unwrap();
+        assert_eq!(package_req.get_ident().to_string(), ident.to_string());
+        assert_eq!(package_req.get_name().to_string(), "channel".to_string());
+    }
+
+    #[test]
+    fn package_exists_absent_in_channel() {
+        let mut broker: TestableBroker = Default::default();
+        broker.setup_error::<OriginChannelPackageGet>(net::err(ErrCode::ENTITY_NOT_FOUND, ""));
+
+        let (response, _) = iron_request(method::Head,
+                                         "http://localhost/channels/org/channel/pkgs/name/1.1.1/20170101010101",
+                                         &mut Vec::new(),
+                                         Headers::new(),
+                                         broker);
+
+        let response = response.unwrap();
+        assert_eq!(response.status, Some(status::NotFound));
+        assert_eq!(response::extract_body_to_string(response), "");
+    }
+
+    #[test]
+    fn package_exists_absent_entirely() {
+        let mut broker: TestableBroker = Default::default();
+        broker.setup_error::<OriginPackageGet>(net::err(ErrCode::ENTITY_NOT_FOUND, ""));
+
+        let (response, msgs) = iron_request(method::Head,
+                                            "http://localhost/pkgs/org/name/1.1.1/20170101010101",
+                                            &mut Vec::new(),
+                                            Headers::new(),
+                                            broker);
+
+        let response = response.unwrap();
+        assert_eq!(response.status, Some(status::NotFound));
+        assert_eq!(response::extract_body_to_string(response), "");
+        assert!(msgs.get::<OriginChannelPackageGet>().is_err());
+    }
+
+    fn exists_request(ident: &str) -> OriginPackageGet {
+        let mut request = OriginPackageGet::new();
+        request.set_ident(OriginPackageIdent::from_str(ident).unwrap());
+        request
+    }
+
+    #[test]
+    fn packages_exist() {
+        let mut broker: TestableBroker = Default::default();
+        broker.setup::<OriginPackageGet, OriginPackage>(&OriginPackage::new());
+        broker.setup_error_for(&exists_request("org/name/2.2.2/20170202020202"),
+                               net::err(ErrCode::ENTITY_NOT_FOUND, ""));
+
+        let mut body = br#"["org/name/1.1.1/20170101010101",
+                            "org/name/2.2.2/20170202020202",
+                            "org/name",
+                            "org/name/1.1.1/20170101010101/extra"]"#
+                .to_vec();
+        let mut headers = Headers::new();
+        headers.set(ContentType::json());
+        let (response, msgs) = iron_request(method::Post,
+                                            "http://localhost/pkgs/exists",
+                                            &mut body,
+                                            headers,
+                                            broker);
+
+        let response = response.unwrap();
+        assert_eq!(response.status, Some(status::Ok));
+        let body: serde_json::Value =
+            serde_json::from_str(&response::extract_body_to_string(response)).unwrap();
+        assert_eq!(body["packages"]["org/name/1.1.1/20170101010101"], true);
+        assert_eq!(body["packages"]["org/name/2.2.2/20170202020202"], false);
+        assert_eq!(body["packages"].as_object().unwrap().len(), 2);
+        assert!(body["errors"]["org/name"].is_string());
+        assert!(body["errors"]["org/name/1.1.1/20170101010101/extra"].is_string());
+        assert!(msgs.get::<OriginChannelPackageGet>().is_err());
+    }
+
+    #[test]
+    fn packages_exist_backend_error() {
+        let mut broker: TestableBroker = Default::default();
+        broker.setup::<OriginPackageGet, OriginPackage>(&OriginPackage::new());
+        broker.setup_error_for(&exists_request("org/name/2.2.2/20170202020202"),
+                               net::err(ErrCode::DATA_STORE, "vt:origin-package-get:1"));
+
+        let mut body = br#"["org/name/2.2.2/20170202020202",
+                            "org/name/1.1.1/20170101010101"]"#
+                .to_vec();
+        let (response, _) = iron_request(method::Post,
+                                         "http://localhost/pkgs/exists",
+                                         &mut body,
+                                         Headers::new(),
+                                         broker);
+
+        let response = response.unwrap();
+        assert_eq!(response.status, Some(status::Ok));
+        let body: serde_json::Value =
+            serde_json::from_str(&response::extract_body_to_string(response)).unwrap();
+        assert_eq!(body["packages"]["org/name/1.1.1/20170101010101"], true);
+        assert_eq!(body["packages"].as_object().unwrap().len(), 1);
+        assert!(body["errors"]["org/name/2.2.2/20170202020202"].is_string());
+    }
+
+    #[test]
+    fn packages_exist_in_channel() {
+        let mut broker: TestableBroker = Default::default();
+        broker.setup::<OriginChannelPackageGet, OriginPackage>(&OriginPackage::new());
+
+        let mut body = br#"["org/name/1.1.1/20170101010101"]"#.to_vec();
+        let (response, msgs) = iron_request(method::Post,
+                                            "http://localhost/pkgs/exists?channel=stable",
+                                            &mut body,
+                                            Headers::new(),
+                                            broker);
+
+        let response = response.unwrap();
+        assert_eq!(response.status, Some(status::Ok));
+        let package_req = msgs.get::<OriginChannelPackageGet>().unwrap();
+        assert_eq!(package_req.get_name(), "stable");
+        assert_eq!(package_req.get_ident().to_string(),
+                   "org/name/1.1.1/20170101010101");
+        assert!(msgs.get::<OriginPackageGet>().is_err());
+    }
+
+    #[test]
+    fn packages_exist_too_many() {
+        let broker: TestableBroker = Default::default();
+        let idents: Vec<String> = (0..PACKAGES_EXIST_MAX + 1)
+            .map(|i| format!("org/name/1.0.{}/20170101010101", i))
+            .collect();
+        let mut body = serde_json::to_vec(&idents).unwrap();
+        let (response, msgs) = iron_request(method::Post,
+                                            "http://localhost/pkgs/exists",
+                                            &mut body,
+                                            Headers::new(),
+                                            broker);
+
+        assert_eq!(response.unwrap().status, Some(status::UnprocessableEntity));
+        assert!(msgs.get::<OriginPackageGet>().is_err());
+    }
+
     #[test]
     fn search_packages() {
         let mut broker: TestableBroker = Default::default();
@@ -2736,6 +4032,36 @@ mod test {
         assert_eq!(channel_req.get_name(), "my_channel");
     }
 
+    #[test]
+    fn create_channel_validates_name() {
+        for name in VALID_CHANNEL_NAMES.iter().chain(INVALID_CHANNEL_NAMES) {
+            let mut broker: TestableBroker = Default::default();
+            let mut access_res = CheckOriginAccessResponse::new();
+            access_res.set_has_access(true);
+            broker.setup::<CheckOriginAccessRequest, CheckOriginAccessResponse>(&access_res);
+            let mut origin_res = Origin::new();
+            origin_res.set_name(String::from("neurosis"));
+            origin_res.set_id(5000);
+            broker.setup::<OriginGet, Origin>(&origin_res);
+            let mut channel_res = OriginChannel::new();
+            channel_res.set_origin_id(5000);
+            channel_res.set_name(name.to_string());
+            broker.setup::<OriginChannelCreate, OriginChannel>(&channel_res);
+
+            let (resp, _) = iron_request(method::Post,
+                                         &format!("http://localhost/channels/neurosis/{}", name),
+                                         &mut Vec::new(),
+                                         Headers::new(),
+                                         broker);
+            let expected = if VALID_CHANNEL_NAMES.contains(name) {
+                status::Created
+            } else {
+                status::UnprocessableEntity
+            };
+            assert_eq!(resp.unwrap().status, Some(expected), "channel {:?}", name);
+        }
+    }
+
     #[test]
     fn promote_package() {
         let mut broker: TestableBroker = Default::default();
@@ -52,6 +52,11 @@ impl Default for Config {
 
 impl ConfigFile for Config {
     type Error = Error;
+    const ENV_PREFIX: &'static str = "HAB_BUILDER_JOBSRV";
+
+    fn service_name() -> &'static str {
+        "builder-jobsrv"
+    }
 }
 
 impl DispatcherCfg for Config {
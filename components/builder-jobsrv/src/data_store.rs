@@ -26,6 +26,9 @@ use config::Config;
 use error::{Result, Error};
 use hab_net::routing::Broker;
 
+/// Times a job may be declined by workers before it's Rejected rather than queued again.
+pub const MAX_JOB_DECLINES: i32 = 10;
+
 /// DataStore inherints being Send + Sync by virtue of having only one member, the pool itself.
 #[derive(Debug, Clone)]
 pub struct DataStore {
@@ -207,6 +210,59 @@ impl DataStore {
         migrator.migrate("jobsrv",
                          r#"CREATE INDEX pending_jobs_index_v1 on jobs(created_at) WHERE job_state = 'Pending'"#)?;
 
+        // Hand a job a worker declined back to the queue. The job goes to the back, behind every
+        // other pending job, so a job that no available worker builds isn't offered again ahead
+        // of jobs that they do. `created_at` is only used to order the queue.
+        migrator.migrate("jobsrv",
+                         r#"CREATE OR REPLACE FUNCTION requeue_job_v1 (jid bigint) RETURNS void AS $$
+                            BEGIN
+                                UPDATE jobs SET job_state='Pending', created_at=now(), scheduler_sync=false, updated_at=now() WHERE id=jid;
+                            END
+                         $$ LANGUAGE plpgsql VOLATILE"#)?;
+
+        // Declined jobs are ordered by when they were last queued rather than created, so
+        // `created_at` keeps the time the job was created. Each decline holds the job back from
+        // dispatch a little longer, and a job declined `max_declines` times is rejected, as no
+        // worker is likely to build it.
+        migrator.migrate("jobsrv",
+                         r#"ALTER TABLE jobs ADD COLUMN queued_at timestamptz DEFAULT now(),
+                                             ADD COLUMN declines integer DEFAULT 0"#)?;
+        migrator.migrate("jobsrv", r#"UPDATE jobs SET queued_at = created_at"#)?;
+        migrator.migrate("jobsrv", r#"DROP INDEX IF EXISTS pending_jobs_index_v1;"#)?;
+        migrator.migrate("jobsrv",
+                         r#"CREATE INDEX pending_jobs_index_v2 on jobs(queued_at) WHERE job_state = 'Pending'"#)?;
+        migrator.migrate("jobsrv",
+                         r#"CREATE OR REPLACE FUNCTION pending_jobs_v1 (integer) RETURNS SETOF jobs AS
+                                $$
+                                DECLARE
+                                    r jobs % rowtype;
+                                BEGIN
+                                    FOR r IN
+                                        SELECT * FROM jobs
+                                        WHERE job_state = 'Pending' AND queued_at <= now()
+                                        ORDER BY queued_at ASC
+                                        FOR UPDATE SKIP LOCKED
+                                        LIMIT $1
+                                    LOOP
+                                        UPDATE jobs SET job_state='Dispatched', updated_at=now() WHERE id=r.id RETURNING * INTO r;
+                                        RETURN NEXT r;
+                                    END LOOP;
+                                  RETURN;
+                                END
+                                $$ LANGUAGE plpgsql VOLATILE"#)?;
+        migrator.migrate("jobsrv",
+                         r#"CREATE OR REPLACE FUNCTION requeue_job_v2 (jid bigint, max_declines integer) RETURNS void AS $$
+                            BEGIN
+                                UPDATE jobs SET
+                                    declines = declines + 1,
+                                    job_state = CASE WHEN declines + 1 >= max_declines THEN 'Rejected' ELSE 'Pending' END,
+                                    queued_at = now() + (declines + 1) * interval '30 seconds',
+                                    scheduler_sync = false,
+                                    updated_at = now()
+                                WHERE id=jid;
+                            END
+                         $$ LANGUAGE plpgsql VOLATILE"#)?;
+
         migrator.finish()?;
 
         self.async.register("sync_jobs".to_string(), sync_jobs);
@@ -324,6 +380,25 @@ impl DataStore {
 
         Ok(())
     }
+
+    /// Put a job a worker declined back in the Pending state, behind every other pending job.
+    /// The job isn't dispatched again for a while, longer each time it's declined, and is
+    /// Rejected once it has been declined `MAX_JOB_DECLINES` times.
+    ///
+    /// # Errors
+    ///
+    /// * If a connection cannot be gotten from the pool
+    /// * If the jobs state cannot be updated in the database
+    pub fn requeue_job(&self, job: &jobsrv::Job) -> Result<()> {
+        let conn = self.pool.get_shard(0)?;
+        conn.execute("SELECT requeue_job_v2($1, $2)",
+                     &[&(job.get_id() as i64), &MAX_JOB_DECLINES])
+            .map_err(Error::JobSetState)?;
+
+        self.async.schedule("sync_jobs")?;
+
+        Ok(())
+    }
 }
 
 /// Translate a database `jobs` row to a `jobsrv::Job`.
@@ -17,16 +17,17 @@ extern crate clap;
 extern crate env_logger;
 extern crate habitat_builder_jobsrv as jobsrv;
 extern crate habitat_core as hab_core;
+extern crate habitat_net as hab_net;
 #[macro_use]
 extern crate log;
 
 use std::process;
 
 use hab_core::config::ConfigFile;
+use hab_net::config::RouterCfg;
 use jobsrv::{Config, Error, Result};
 
 const VERSION: &'static str = include_str!(concat!(env!("OUT_DIR"), "/VERSION"));
-const CFG_DEFAULT_PATH: &'static str = "/hab/svc/builder-jobsrv/config.toml";
 
 fn main() {
     env_logger::init().unwrap();
@@ -61,8 +62,14 @@ fn config_from_args(matches: &clap::ArgMatches) -> Result<Config> {
     let args = matches.subcommand_matches(cmd).unwrap();
     let config = match args.value_of("config") {
         Some(cfg_path) => try!(Config::from_file(cfg_path)),
-        None => Config::from_file(CFG_DEFAULT_PATH).unwrap_or(Config::default()),
+        None => {
+            match Config::default_url() {
+                Some(url) => try!(Config::from_url(&url)),
+                None => Config::from_file(Config::default_path()).unwrap_or(Config::default()),
+            }
+        }
     };
+    try!(config.validate_routers());
     Ok(config)
 }
 
@@ -262,7 +262,14 @@ impl WorkerMgr {
         try!(self.rq_sock.recv(&mut self.msg, 0));
         let job: jobsrv::Job = try!(parse_from_bytes(&self.msg));
         debug!("job_status={:?}", job);
-        try!(self.datastore.set_job_state(&job));
+        // Workers only report a job as Pending when they decline it, which puts it at the back
+        // of the queue instead of being offered to the next worker ahead of every other job, and
+        // rejects it once it has been declined too often.
+        if job.get_state() == jobsrv::JobState::Pending {
+            try!(self.datastore.requeue_job(&job));
+        } else {
+            try!(self.datastore.set_job_state(&job));
+        }
 
         Ok(())
     }
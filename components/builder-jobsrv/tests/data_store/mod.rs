@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use jobsrv::data_store::DataStore;
+use jobsrv::data_store::{DataStore, MAX_JOB_DECLINES};
 use protocol::jobsrv;
 
 #[test]
@@ -146,3 +146,55 @@ fn set_job_state() {
         .expect("No job found");
     assert_eq!(failed_job.get_state(), jobsrv::JobState::Failed);
 }
+
+#[test]
+fn requeue_job() {
+    let mut job1 = test_job();
+    let mut job2 = test_job();
+    let ds = datastore_test!(DataStore);
+    ds.setup().expect("Failed to migrate data");
+    let rjob1 = ds.create_job(&mut job1).expect("Failed to create job");
+    let rjob2 = ds.create_job(&mut job2).expect("Failed to create job");
+
+    // A declined job goes behind the jobs that were queued after it
+    let mut declined = ds.pending_jobs(1).expect("Failed to get pending job").remove(0);
+    assert_eq!(declined.get_id(), rjob1.get_id());
+    declined.set_state(jobsrv::JobState::Pending);
+    ds.requeue_job(&declined).expect("Failed to requeue job");
+
+    // and isn't dispatched again right away
+    let pending_jobs = ds.pending_jobs(2).expect("Failed to get pending jobs");
+    assert_eq!(pending_jobs.len(), 1);
+    assert_eq!(pending_jobs[0].get_id(), rjob2.get_id());
+
+    let mut get_job = jobsrv::JobGet::new();
+    get_job.set_id(rjob1.get_id());
+    let requeued = ds.get_job(&get_job)
+        .expect("Failed to get job from database")
+        .expect("No job found");
+    assert_eq!(requeued.get_state(), jobsrv::JobState::Pending);
+}
+
+#[test]
+fn requeue_job_rejects_after_max_declines() {
+    let mut job1 = test_job();
+    let ds = datastore_test!(DataStore);
+    ds.setup().expect("Failed to migrate data");
+    let rjob1 = ds.create_job(&mut job1).expect("Failed to create job");
+    let mut get_job = jobsrv::JobGet::new();
+    get_job.set_id(rjob1.get_id());
+
+    for _ in 1..MAX_JOB_DECLINES {
+        ds.requeue_job(&rjob1).expect("Failed to requeue job");
+    }
+    let job = ds.get_job(&get_job)
+        .expect("Failed to get job from database")
+        .expect("No job found");
+    assert_eq!(job.get_state(), jobsrv::JobState::Pending);
+
+    ds.requeue_job(&rjob1).expect("Failed to requeue job");
+    let job = ds.get_job(&get_job)
+        .expect("Failed to get job from database")
+        .expect("No job found");
+    assert_eq!(job.get_state(), jobsrv::JobState::Rejected);
+}
@@ -48,6 +48,11 @@ impl Default for Config {
 
 impl ConfigFile for Config {
     type Error = Error;
+    const ENV_PREFIX: &'static str = "HAB_BUILDER_ORIGINSRV";
+
+    fn service_name() -> &'static str {
+        "builder-originsrv"
+    }
 }
 
 impl DispatcherCfg for Config {
@@ -16,12 +16,13 @@ pub mod handlers;
 
 use std::ops::Deref;
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
 use protocol::net;
 use zmq;
 
 use hab_net::config::RouterCfg;
-use hab_net::routing::Broker;
+use hab_net::routing::{Broker, PING_TIMEOUT_MS};
 use hab_net::{Application, Supervisor};
 use hab_net::dispatcher::prelude::*;
 use hab_net::server::{Envelope, NetIdent, RouteConn, Service, ZMQ_CONTEXT};
@@ -163,7 +164,10 @@ impl Application for Server {
         try!(self.be_sock.bind(BE_LISTEN_ADDR));
         let broker = {
             let cfg = self.config.read().unwrap();
-            Broker::run(Self::net_ident(), cfg.route_addrs())
+            let broker = Broker::run(Self::net_ident(), cfg.route_addrs());
+            try!(Broker::connect_with_timeout(cfg.route_addrs(),
+                                              Duration::from_millis(PING_TIMEOUT_MS)));
+            broker
         };
         let datastore = {
             let cfg = self.config.read().unwrap();
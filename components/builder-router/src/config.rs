@@ -56,6 +56,11 @@ impl Default for Config {
 
 impl ConfigFile for Config {
     type Error = Error;
+    const ENV_PREFIX: &'static str = "HAB_BUILDER_ROUTER";
+
+    fn service_name() -> &'static str {
+        "builder-router"
+    }
 }
 
 impl FromStr for Config {
@@ -147,6 +147,7 @@ impl Server {
                     }
                     match self.envelope.msg.get_route_info().get_protocol() {
                         Protocol::RouteSrv => try!(self.handle_message()),
+                        Protocol::Net => try!(self.handle_net_message()),
                         _ => try!(self.route_message()),
                     }
                     self.state = SocketState::Cleaning;
@@ -226,6 +227,23 @@ impl Server {
         Ok(())
     }
 
+    fn handle_net_message(&mut self) -> Result<()> {
+        debug!("handle-net-message, msg={:?}", &self.envelope.msg);
+        match self.envelope.message_id() {
+            "Ping" => {
+                let rep = protocol::Message::new(&protocol::net::Pong::new()).build();
+                let bytes = try!(rep.write_to_bytes());
+                for hop in self.envelope.hops() {
+                    try!(self.fe_sock.send(&*hop, zmq::SNDMORE));
+                }
+                try!(self.fe_sock.send(&[], zmq::SNDMORE));
+                try!(self.fe_sock.send(&bytes, 0));
+            }
+            id => warn!("Unknown net message, msg={}", id),
+        }
+        Ok(())
+    }
+
     fn route_message(&mut self) -> Result<()> {
         let shard = self.select_shard();
         match self.servers.get(&self.envelope.protocol()) {
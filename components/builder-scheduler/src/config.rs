@@ -51,6 +51,11 @@ impl Default for Config {
 
 impl ConfigFile for Config {
     type Error = Error;
+    const ENV_PREFIX: &'static str = "HAB_BUILDER_SCHEDULER";
+
+    fn service_name() -> &'static str {
+        "builder-scheduler"
+    }
 }
 
 impl DispatcherCfg for Config {
@@ -17,12 +17,13 @@ pub mod scheduler;
 
 use std::ops::Deref;
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
 use time::PreciseTime;
 
 use hab_net::config::RouterCfg;
 use hab_net::dispatcher::prelude::*;
 use hab_net::{Application, Supervisor};
-use hab_net::routing::Broker;
+use hab_net::routing::{Broker, PING_TIMEOUT_MS};
 use hab_net::server::{Envelope, NetIdent, RouteConn, Service, ZMQ_CONTEXT};
 use protocol::net;
 use zmq;
@@ -199,7 +200,10 @@ impl Application for Server {
         try!(self.connect());
         let broker = {
             let cfg = self.config.read().unwrap();
-            Broker::run(Self::net_ident(), cfg.route_addrs())
+            let broker = Broker::run(Self::net_ident(), cfg.route_addrs());
+            try!(Broker::connect_with_timeout(cfg.route_addrs(),
+                                              Duration::from_millis(PING_TIMEOUT_MS)));
+            broker
         };
         info!("builder-scheduler is ready to go.");
         try!(zmq::proxy(&mut self.router.socket, &mut self.be_sock));
@@ -21,6 +21,7 @@ use hab_net::routing::Broker;
 use zmq;
 
 use protocol::jobsrv::{self, Job, JobSpec};
+use protocol::net::{self, ErrCode};
 use protocol::originsrv::*;
 use protocol::scheduler as proto;
 use data_store::DataStore;
@@ -248,7 +249,13 @@ impl ScheduleMgr {
 
         project_get.set_name(String::from(project_name));
 
-        let mut conn = Broker::connect().unwrap();
+        let mut conn = match Broker::try_connect() {
+            Some(conn) => conn,
+            None => {
+                let err = net::err(ErrCode::ZMQ, "sc:schedule-job:0");
+                return Err(Error::ProtoNetError(err));
+            }
+        };
         let project = match conn.route::<OriginProjectGet, OriginProject>(&project_get) {
             Ok(project) => project,
             Err(err) => {
@@ -15,7 +15,8 @@
 
 use db::config::DataStoreCfg;
 use hab_core::config::ConfigFile;
-use hab_net::config::{DispatcherCfg, GitHubCfg, GitHubOAuth, RouterCfg, RouterAddr, Shards};
+use hab_net::config::{DispatcherCfg, GitHubCfg, GitHubEndpoint, GitHubOAuth, RouterCfg,
+                      RouterAddr, Shards};
 use protocol::sharding::{ShardId, SHARD_COUNT};
 
 use error::Error;
@@ -59,6 +60,11 @@ impl Default for Config {
 
 impl ConfigFile for Config {
     type Error = Error;
+    const ENV_PREFIX: &'static str = "HAB_BUILDER_SESSIONSRV";
+
+    fn service_name() -> &'static str {
+        "builder-sessionsrv"
+    }
 }
 
 impl DispatcherCfg for Config {
@@ -68,7 +74,7 @@ impl DispatcherCfg for Config {
 }
 
 impl GitHubOAuth for Config {
-    fn github_url(&self) -> &str {
+    fn github_url(&self) -> &GitHubEndpoint {
         &self.github.url
     }
 
@@ -79,6 +85,10 @@ impl GitHubOAuth for Config {
     fn github_client_secret(&self) -> &str {
         &self.github.client_secret
     }
+
+    fn github_idle_timeout(&self) -> u64 {
+        self.github.idle_timeout
+    }
 }
 
 impl RouterCfg for Config {
@@ -148,7 +158,7 @@ mod tests {
         assert_eq!(config.datastore.connection_timeout_sec, 4800);
         assert_eq!(config.datastore.connection_test, true);
         assert_eq!(config.datastore.pool_size, 1);
-        assert_eq!(config.github.url, "https://api.github.com");
+        assert_eq!(config.github.url.api_base(), "https://api.github.com");
         assert_eq!(config.github.client_id, "0c2f738a7d0bd300de10");
         assert_eq!(config.github.client_secret,
                    "438223113eeb6e7edf2d2f91a232b72de72b9bdf");
@@ -16,11 +16,12 @@ pub mod handlers;
 
 use std::ops::Deref;
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
 use hab_net::{Application, Supervisor};
 use hab_net::dispatcher::prelude::*;
 use hab_net::config::RouterCfg;
-use hab_net::routing::Broker;
+use hab_net::routing::{Broker, PING_TIMEOUT_MS};
 use hab_net::oauth::github::GitHubClient;
 use hab_net::server::{Envelope, NetIdent, RouteConn, Service, ZMQ_CONTEXT};
 use protocol::net;
@@ -168,6 +169,8 @@ impl Application for Server {
         {
             let cfg = self.config.read().unwrap();
             Broker::run(Self::net_ident(), cfg.route_addrs());
+            try!(Broker::connect_with_timeout(cfg.route_addrs(),
+                                              Duration::from_millis(PING_TIMEOUT_MS)));
         }
         info!("builder-sessionsrv is ready to go.");
         try!(zmq::proxy(&mut self.router.socket, &mut self.be_sock));
@@ -14,11 +14,22 @@
 
 //! Configuration for a Habitat JobSrv Worker
 
+use std::cmp;
 use std::net::{IpAddr, Ipv4Addr};
+use std::time::Duration;
 
+use depot_client;
+use hab_core;
 use hab_core::config::ConfigFile;
+use hab_core::os::process::ChildLimit;
+use hab_core::package::PackageTarget;
+use hab_core::package::target::deserialize_targets;
 
-use error::Error;
+use error::{Error, Result};
+use {PRODUCT, VERSION};
+
+/// Seconds a request to the Depot may wait on the network when none is configured
+pub const DEFAULT_DEPOT_TIMEOUT_SECS: u64 = 30;
 
 #[derive(Debug, Deserialize)]
 #[serde(default)]
@@ -27,8 +38,25 @@ pub struct Config {
     pub auth_token: String,
     /// Filepath where persistent application data is stored
     pub data_path: String,
+    /// URL of the Depot API packages are fetched from
+    pub depot_url: String,
+    /// Seconds a request to the Depot may wait on the network before failing
+    pub depot_timeout: u64,
     /// List of Job Servers to connect to
     pub jobsrv: JobSrvCfg,
+    /// Maximum number of studio processes to run at once. Further spawns wait for a free slot.
+    pub max_children: usize,
+    /// Size in gigabytes above which a job's workspace is removed entirely once the job is done.
+    /// Zero keeps workspaces regardless of their size.
+    pub max_total_gb: u64,
+    /// Targets of the jobs the worker accepts. Jobs for any other target are handed back to the
+    /// job server, which queues them behind its other pending jobs. Empty accepts jobs for every
+    /// target.
+    ///
+    /// Jobs don't carry a target, so a job's target is a guess made from its plan's file name:
+    /// `plan.ps1` plans are taken to build for x86_64-windows and all others for x86_64-linux.
+    #[serde(deserialize_with = "deserialize_targets")]
+    pub supported_targets: Vec<PackageTarget>,
 }
 
 impl Config {
@@ -41,6 +69,29 @@ impl Config {
         }
         addrs
     }
+
+    /// Returns an error if the Depot settings can't be used to talk to a Depot.
+    pub fn validate(&self) -> Result<()> {
+        if self.depot_timeout == 0 {
+            return Err(Error::InvalidConfigField("depot_timeout",
+                                                 "must be greater than zero".to_string()));
+        }
+        if let Err(e) = depot_client_from_config(self) {
+            return Err(Error::InvalidConfigField("depot_url", e.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Returns the limit studio processes are spawned through. A `max_children` of zero is
+    /// treated as one.
+    pub fn child_limit(&self) -> ChildLimit {
+        ChildLimit::new(cmp::max(self.max_children, 1))
+    }
+
+    /// Returns true if the worker accepts jobs building for the given target.
+    pub fn accepts_target(&self, target: &PackageTarget) -> bool {
+        self.supported_targets.is_empty() || self.supported_targets.contains(target)
+    }
 }
 
 impl Default for Config {
@@ -48,13 +99,36 @@ impl Default for Config {
         Config {
             auth_token: "".to_string(),
             data_path: "/tmp".to_string(),
+            depot_url: hab_core::url::default_depot_url(),
+            depot_timeout: DEFAULT_DEPOT_TIMEOUT_SECS,
             jobsrv: vec![JobSrvAddr::default()],
+            max_children: 1,
+            max_total_gb: 0,
+            supported_targets: vec![],
         }
     }
 }
 
 impl ConfigFile for Config {
     type Error = Error;
+    const ENV_PREFIX: &'static str = "HAB_BUILDER_WORKER";
+
+    fn service_name() -> &'static str {
+        "builder-worker"
+    }
+}
+
+/// Returns a client for the Depot at `depot_url` with the Depot settings of the configuration.
+pub fn depot_client_from_config(config: &Config) -> Result<depot_client::Client> {
+    depot_client_for_url(&config.depot_url, config.depot_timeout)
+}
+
+/// Returns a client for the Depot at the given URL, such as the one a project publishes to, with
+/// the given request timeout in seconds.
+pub fn depot_client_for_url(url: &str, timeout: u64) -> Result<depot_client::Client> {
+    let mut client = try!(depot_client::Client::new(url, PRODUCT, VERSION, None));
+    client.set_timeout(Duration::from_secs(timeout));
+    Ok(client)
 }
 
 pub type JobSrvCfg = Vec<JobSrvAddr>;
@@ -79,13 +153,25 @@ impl Default for JobSrvAddr {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::mpsc;
+    use std::thread;
+
     use super::*;
 
+    fn target(value: &str) -> PackageTarget {
+        PackageTarget::canonicalize(value).unwrap()
+    }
+
     #[test]
     fn config_from_file() {
         let content = r#"
         auth_token = "mytoken"
         data_path = "/path/to/data"
+        depot_url = "https://depot.example.com/v1/depot"
+        depot_timeout = 5
+        max_children = 4
+        max_total_gb = 10
+        supported_targets = ["x86_64-linux", "amd64-windows"]
 
         [[jobsrv]]
         host = "1:1:1:1:1:1:1:1"
@@ -100,6 +186,12 @@ mod tests {
         let config = Config::from_raw(&content).unwrap();
         assert_eq!(&config.auth_token, "mytoken");
         assert_eq!(&config.data_path, "/path/to/data");
+        assert_eq!(&config.depot_url, "https://depot.example.com/v1/depot");
+        assert_eq!(config.depot_timeout, 5);
+        assert_eq!(config.max_children, 4);
+        assert_eq!(config.max_total_gb, 10);
+        assert_eq!(config.supported_targets,
+                   vec![target("x86_64-linux"), target("x86_64-windows")]);
         assert_eq!(&format!("{}", config.jobsrv[0].host), "1:1:1:1:1:1:1:1");
         assert_eq!(config.jobsrv[0].port, 9000);
         assert_eq!(config.jobsrv[0].heartbeat, 9001);
@@ -107,4 +199,74 @@ mod tests {
         assert_eq!(config.jobsrv[1].port, 9000);
         assert_eq!(config.jobsrv[1].heartbeat, 5567);
     }
+
+    #[test]
+    fn depot_client_carries_config() {
+        let mut config = Config::default();
+        config.depot_url = "https://depot.example.com/v1/depot".to_string();
+        config.depot_timeout = 5;
+        let client = depot_client_from_config(&config).unwrap();
+        assert_eq!(client.endpoint().as_str(),
+                   "https://depot.example.com/v1/depot");
+        assert_eq!(client.timeout(), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn depot_client_default_timeout() {
+        let client = depot_client_from_config(&Config::default()).unwrap();
+        assert_eq!(client.endpoint().as_str(),
+                   hab_core::url::default_depot_url());
+        assert_eq!(client.timeout(),
+                   Duration::from_secs(DEFAULT_DEPOT_TIMEOUT_SECS));
+    }
+
+    #[test]
+    fn config_validate() {
+        assert!(Config::default().validate().is_ok());
+        assert!(Config::from_raw("depot_timeout = 0").unwrap().validate().is_err());
+        assert!(Config::from_raw("depot_url = \"not a url\"").unwrap().validate().is_err());
+    }
+
+    #[test]
+    fn accepts_supported_target() {
+        let mut config = Config::default();
+        config.supported_targets = vec![target("x86_64-linux")];
+        assert!(config.accepts_target(&target("x86_64-linux")));
+    }
+
+    #[test]
+    fn declines_unsupported_target() {
+        let mut config = Config::default();
+        config.supported_targets = vec![target("x86_64-linux")];
+        assert!(!config.accepts_target(&target("x86_64-windows")));
+    }
+
+    #[test]
+    fn accepts_any_target_by_default() {
+        let config = Config::default();
+        assert!(config.accepts_target(&target("x86_64-linux")));
+        assert!(config.accepts_target(&target("x86_64-windows")));
+    }
+
+    #[test]
+    fn child_limit_holds_spawns_beyond_max_children() {
+        let config = Config::from_raw("max_children = 0").unwrap();
+        let limit = config.child_limit();
+        let permit = limit.acquire();
+        let (tx, rx) = mpsc::channel();
+        let waiting = limit.clone();
+        let handle = thread::spawn(move || {
+                                       let _permit = waiting.acquire();
+                                       tx.send(()).unwrap();
+                                   });
+        assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+        drop(permit);
+        assert!(rx.recv_timeout(Duration::from_secs(5)).is_ok());
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn config_rejects_unknown_target() {
+        assert!(Config::from_raw("supported_targets = [\"x86_64-plan9\"]").is_err());
+    }
 }
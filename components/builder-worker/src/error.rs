@@ -17,6 +17,7 @@ use std::fmt;
 use std::io;
 use std::result;
 
+use depot_client;
 use git2;
 use hab_core;
 use protobuf;
@@ -25,8 +26,11 @@ use zmq;
 #[derive(Debug)]
 pub enum Error {
     BuildFailure(i32),
+    DepotClient(depot_client::Error),
     Git(git2::Error),
     HabitatCore(hab_core::Error),
+    InvalidConfigField(&'static str, String),
+    InvalidPublishMode(String),
     IO(io::Error),
     Protobuf(protobuf::ProtobufError),
     UnknownVCS,
@@ -43,8 +47,16 @@ impl fmt::Display for Error {
             Error::BuildFailure(ref e) => {
                 format!("Build studio exited with non-zero exit code, {}", e)
             }
+            Error::DepotClient(ref e) => format!("{}", e),
             Error::Git(ref e) => format!("{}", e),
             Error::HabitatCore(ref e) => format!("{}", e),
+            Error::InvalidConfigField(ref field, ref e) => {
+                format!("Invalid value for config field `{}`, {}", field, e)
+            }
+            Error::InvalidPublishMode(ref e) => {
+                format!("Invalid publish mode '{}', expected global-only, channel-only or both",
+                        e)
+            }
             Error::IO(ref e) => format!("{}", e),
             Error::Protobuf(ref e) => format!("{}", e),
             Error::UnknownVCS => format!("Job requires an unknown VCS"),
@@ -64,8 +76,11 @@ impl error::Error for Error {
     fn description(&self) -> &str {
         match *self {
             Error::BuildFailure(_) => "Build studio exited with a non-zero exit code",
+            Error::DepotClient(ref err) => err.description(),
             Error::Git(ref err) => err.description(),
             Error::HabitatCore(ref err) => err.description(),
+            Error::InvalidConfigField(_, _) => "Invalid value for a config field",
+            Error::InvalidPublishMode(_) => "Invalid publish mode",
             Error::IO(ref err) => err.description(),
             Error::Protobuf(ref err) => err.description(),
             Error::UnknownVCS => "Job requires an unknown VCS",
@@ -76,6 +91,12 @@ impl error::Error for Error {
     }
 }
 
+impl From<depot_client::Error> for Error {
+    fn from(err: depot_client::Error) -> Error {
+        Error::DepotClient(err)
+    }
+}
+
 impl From<git2::Error> for Error {
     fn from(err: git2::Error) -> Error {
         Error::Git(err)
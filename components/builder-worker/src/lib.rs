@@ -12,11 +12,15 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+extern crate builder_core as bld_core;
 extern crate habitat_builder_protocol as protocol;
 extern crate habitat_depot_client as depot_client;
 extern crate habitat_core as hab_core;
 extern crate habitat_net as hab_net;
+#[cfg(test)]
+extern crate habitat_http_test as http_test;
 extern crate git2;
+extern crate hyper;
 #[macro_use]
 extern crate log;
 #[macro_use]
@@ -28,21 +28,21 @@ use std::sync::{mpsc, Arc, RwLock};
 use std::thread::{self, JoinHandle};
 
 use depot_client;
-use hab_core::{self, crypto};
+use hab_core::crypto;
 use hab_core::package::archive::PackageArchive;
 use hab_core::package::install::PackageInstall;
-use hab_core::package::PackageIdent;
+use hab_core::package::{PackageIdent, PackageTarget};
+use hab_core::os::process::ChildLimit;
 use hab_net::server::ZMQ_CONTEXT;
 use protobuf::{parse_from_bytes, Message};
 use protocol::jobsrv as proto;
 use protocol::net::{self, ErrCode};
 use zmq;
 
-use {PRODUCT, VERSION};
 use self::logger::Logger;
 use self::postprocessor::PostProcessor;
 use self::workspace::Workspace;
-use config::Config;
+use config::{depot_client_from_config, Config};
 use error::{Error, Result};
 use vcs;
 
@@ -52,6 +52,8 @@ const INPROC_ADDR: &'static str = "inproc://runner";
 const WORK_ACK: &'static str = "A";
 /// Protocol message to indicate the Job Runner has completed a work request
 const WORK_COMPLETE: &'static str = "C";
+/// Bytes in a gigabyte, for the `max_total_gb` setting
+const BYTES_PER_GB: u64 = 1024 * 1024 * 1024;
 
 lazy_static! {
     // JW TODO: expose public API functions in the core crate to check if the Rust process which
@@ -62,7 +64,7 @@ lazy_static! {
     static ref STUDIO_PKG: PackageIdent = PackageIdent::from_str("core/hab-studio").unwrap();
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Job(proto::Job);
 
 impl Job {
@@ -91,6 +93,18 @@ impl Job {
                         self.0.get_project().get_id()));
         items[0]
     }
+
+    /// Returns the target the job most likely builds for. Jobs don't name a target, so this is a
+    /// guess from the project's plan, as Windows packages are built from PowerShell `plan.ps1`
+    /// plans.
+    pub fn target(&self) -> PackageTarget {
+        let target = if self.0.get_project().get_plan_path().ends_with(".ps1") {
+            "x86_64-windows"
+        } else {
+            "x86_64-linux"
+        };
+        PackageTarget::from_str(target).unwrap()
+    }
 }
 
 impl Deref for Job {
@@ -112,19 +126,23 @@ pub struct Runner {
     auth_token: String,
     logger: Option<Logger>,
     depot_cli: depot_client::Client,
+    depot_timeout: u64,
+    children: ChildLimit,
+    max_workspace_bytes: u64,
 }
 
 impl Runner {
-    pub fn new(job: Job, config: &Config) -> Self {
-        let depot_cli =
-            depot_client::Client::new(&hab_core::url::default_depot_url(), PRODUCT, VERSION, None)
-                .unwrap();
-        Runner {
+    pub fn new(job: Job, config: &Config, children: ChildLimit) -> Result<Self> {
+        let depot_cli = try!(depot_client_from_config(config));
+        Ok(Runner {
             auth_token: config.auth_token.clone(),
             workspace: Workspace::new(config.data_path.clone(), job),
             logger: None,
             depot_cli: depot_cli,
-        }
+            depot_timeout: config.depot_timeout,
+            children: children,
+            max_workspace_bytes: config.max_total_gb.saturating_mul(BYTES_PER_GB),
+        })
     }
 
     pub fn job(&self) -> &Job {
@@ -184,8 +202,10 @@ impl Runner {
             }
         };
 
-        let mut post_processor = PostProcessor::new(&self.workspace);
-        if !post_processor.run(&mut archive, &self.auth_token) {
+        let mut post_processor = PostProcessor::new(&self.workspace, self.depot_timeout);
+        let report = post_processor.run(&mut archive, &self.auth_token);
+        debug!("post processing report, {:?}", report);
+        if !report.is_success() {
             // JW TODO: We should shelve the built artifacts and allow a retry on post-processing.
             // If the job is killed then we can kill the shelved artifacts.
             return self.fail(net::err(ErrCode::POST_PROCESSOR, "wk:run:6"));
@@ -212,6 +232,7 @@ impl Runner {
                                            .unwrap())];
         let command = studio_cmd();
         debug!("building, cmd={:?}, args={:?}", command, args);
+        let _permit = self.children.acquire();
         let mut child = Command::new(command)
             .args(&args)
             .env_clear()
@@ -232,12 +253,14 @@ impl Runner {
 
     fn complete(mut self) -> Job {
         self.teardown().err().map(|e| error!("{}", e));
+        self.cleanup();
         self.workspace.job.set_state(JobState::Complete);
         self.workspace.job
     }
 
     fn fail(mut self, err: net::NetError) -> Job {
         self.teardown().err().map(|e| error!("{}", e));
+        self.cleanup();
         self.workspace.job.set_state(JobState::Failed);
         self.workspace.job.set_error(err);
         self.workspace.job
@@ -256,6 +279,37 @@ impl Runner {
         Ok(())
     }
 
+    // Removes the cloned source once a job is done, or the whole workspace if it has grown past
+    // the configured limit.
+    fn cleanup(&mut self) {
+        if let Err(err) = self.workspace.cleanup() {
+            error!("unable to clean up workspace ({}), ERR={:?}",
+                   self.workspace.root().display(),
+                   err);
+        }
+        if self.max_workspace_bytes == 0 {
+            return;
+        }
+        match self.workspace.size() {
+            Ok(size) if size > self.max_workspace_bytes => {
+                debug!("workspace size {} exceeds {} bytes, removing it",
+                       size,
+                       self.max_workspace_bytes);
+                if let Err(err) = self.workspace.cleanup_all() {
+                    error!("unable to remove workspace ({}), ERR={:?}",
+                           self.workspace.root().display(),
+                           err);
+                }
+            }
+            Ok(_) => (),
+            Err(err) => {
+                error!("unable to compute workspace size ({}), ERR={:?}",
+                       self.workspace.root().display(),
+                       err)
+            }
+        }
+    }
+
     fn teardown(&mut self) -> Result<()> {
         let args = vec![OsString::from("-s"),
                         OsString::from(self.workspace.src()),
@@ -267,6 +321,7 @@ impl Runner {
                                            .unwrap())];
         let command = studio_cmd();
         debug!("removing studio, cmd={:?}, args={:?}", command, args);
+        let _permit = self.children.acquire();
         let mut child = Command::new(command)
             .args(&args)
             .env_clear()
@@ -351,6 +406,7 @@ pub struct RunnerMgr {
     sock: zmq::Socket,
     msg: zmq::Message,
     config: Arc<RwLock<Config>>,
+    children: ChildLimit,
 }
 
 impl RunnerMgr {
@@ -372,10 +428,18 @@ impl RunnerMgr {
 
     fn new(config: Arc<RwLock<Config>>) -> Result<Self> {
         let sock = try!((**ZMQ_CONTEXT).as_mut().socket(zmq::DEALER));
+        let children = {
+            let config = config.read().unwrap();
+            if config.max_children == 0 {
+                warn!("max_children must be at least 1, running one studio process at a time");
+            }
+            config.child_limit()
+        };
         Ok(RunnerMgr {
                sock: sock,
                msg: zmq::Message::new().unwrap(),
                config: config,
+               children: children,
            })
     }
 
@@ -392,10 +456,21 @@ impl RunnerMgr {
 
     fn execute_job(&mut self, job: Job) -> Result<()> {
         let runner = {
-            Runner::new(job, &self.config.read().unwrap())
+            Runner::new(job.clone(), &self.config.read().unwrap(), self.children.clone())
+        };
+        let job = match runner {
+            Ok(runner) => {
+                debug!("executing work, job={:?}", runner.job());
+                runner.run()
+            }
+            Err(err) => {
+                error!("Unable to set up job runner, err={}", err);
+                let mut job = job;
+                job.set_state(JobState::Failed);
+                job.set_error(net::err(ErrCode::WORKSPACE_SETUP, "wk:run:0"));
+                job
+            }
         };
-        debug!("executing work, job={:?}", runner.job());
-        let job = runner.run();
         self.send_complete(&job)
     }
 
@@ -444,4 +519,17 @@ mod tests {
         let job = Job::new(inner);
         assert_eq!(job.origin(), "core");
     }
+
+    #[test]
+    fn job_target_from_plan() {
+        let mut inner = jobsrv::Job::new();
+        let mut project = originsrv::OriginProject::new();
+        project.set_plan_path("nginx/plan.sh".to_string());
+        inner.set_project(project.clone());
+        assert_eq!(Job::new(inner.clone()).target().to_string(), "x86_64-linux");
+
+        project.set_plan_path("nginx/plan.ps1".to_string());
+        inner.set_project(project);
+        assert_eq!(Job::new(inner).target().to_string(), "x86_64-windows");
+    }
 }
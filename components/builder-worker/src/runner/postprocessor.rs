@@ -12,19 +12,115 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::fmt;
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
+use std::result;
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
 
+use bld_core::channel::{ChannelName, InvalidChannelName};
 use hab_core;
 use hab_core::package::archive::PackageArchive;
 use hab_core::config::ConfigFile;
+use hab_core::util::deserialize_using_from_str;
 
 use super::workspace::Workspace;
-use depot_client;
+use config::{depot_client_for_url, DEFAULT_DEPOT_TIMEOUT_SECS};
+use depot_client::{self, DisplayProgress};
+use hab_net;
+use hyper;
 use error::Error;
-use {PRODUCT, VERSION};
 
 /// Postprocessing config file name
 const CONFIG_FILE: &'static str = "builder.toml";
+/// Percentage of an upload between progress log messages
+const UPLOAD_PROGRESS_STEP: u64 = 10;
+/// Times a Depot request failing with a transient error is retried while publishing
+const PUBLISH_RETRIES: u64 = 2;
+/// Milliseconds to wait before the first retry of a Depot request, growing with each retry
+const PUBLISH_RETRY_DELAY_MS: u64 = 1_000;
+
+/// Outcome of post processing a built package.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PostProcessReport {
+    /// Whether the package was uploaded to the Depot
+    pub published: bool,
+    /// Channel the package was published to, set once the package is in the channel
+    pub channel: Option<String>,
+    /// Channel the package was promoted to
+    pub promoted_to: Option<String>,
+    /// Whether notifications were sent. Post processing has no notification step yet.
+    pub notified: bool,
+    /// Errors that failed post processing
+    pub errors: Vec<String>,
+    /// Failures which were tolerated, such as those of a publish which isn't required
+    pub warnings: Vec<String>,
+}
+
+impl PostProcessReport {
+    /// Returns true if post processing succeeded well enough for the job to complete.
+    pub fn is_success(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// Where a published package is made available.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PublishMode {
+    /// Upload the package to the Depot without adding it to a channel
+    GlobalOnly,
+    /// Add an already uploaded package to the channel without uploading it
+    ChannelOnly,
+    /// Upload the package and add it to the channel
+    Both,
+}
+
+impl PublishMode {
+    fn as_str(&self) -> &str {
+        match *self {
+            PublishMode::GlobalOnly => "global-only",
+            PublishMode::ChannelOnly => "channel-only",
+            PublishMode::Both => "both",
+        }
+    }
+
+    /// Returns true if the package is uploaded to the Depot.
+    pub fn uploads(&self) -> bool {
+        *self != PublishMode::ChannelOnly
+    }
+
+    /// Returns true if the package is added to the publish channel.
+    pub fn promotes(&self) -> bool {
+        *self != PublishMode::GlobalOnly
+    }
+}
+
+impl FromStr for PublishMode {
+    type Err = Error;
+
+    fn from_str(mode: &str) -> result::Result<Self, Self::Err> {
+        match mode {
+            "global-only" => Ok(PublishMode::GlobalOnly),
+            "channel-only" => Ok(PublishMode::ChannelOnly),
+            "both" => Ok(PublishMode::Both),
+            _ => Err(Error::InvalidPublishMode(mode.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for PublishMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Default for PublishMode {
+    fn default() -> PublishMode {
+        PublishMode::Both
+    }
+}
 
 #[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
 #[serde(default)]
@@ -35,34 +131,163 @@ pub struct Publish {
     pub url: String,
     /// Channel to publish to
     pub channel: String,
+    /// Whether a failure to publish fails the build. When false the failure is only logged.
+    pub required: bool,
+    /// Whether the package is uploaded, added to the channel, or both
+    #[serde(deserialize_with = "deserialize_using_from_str")]
+    pub mode: PublishMode,
+    /// Seconds a request to the Depot may wait on the network. This comes from the worker's
+    /// configuration rather than from `builder.toml`.
+    #[serde(skip_deserializing)]
+    pub timeout: u64,
 }
 
 impl Publish {
-    pub fn run(&mut self, archive: &mut PackageArchive, auth_token: &str) -> bool {
+    /// Returns the channel to publish to, validated with the same rules as the Depot.
+    pub fn channel_name(&self) -> result::Result<ChannelName, InvalidChannelName> {
+        self.channel.parse()
+    }
+
+    pub fn run(&mut self, archive: &mut PackageArchive, auth_token: &str) -> PostProcessReport {
+        let mut report = PostProcessReport::default();
         if !self.enabled {
-            return true;
+            return report;
         }
+        if let Err(err) = self.publish(archive, auth_token, &mut report) {
+            if self.required {
+                report.errors.push(err);
+            } else {
+                warn!("post processing: publish failed but is not required, continuing, {}",
+                      err);
+                report.warnings.push(err);
+            }
+        }
+        report
+    }
 
-        debug!("post process: publish (url: {}, channel: {})",
+    fn publish(&mut self,
+               archive: &mut PackageArchive,
+               auth_token: &str,
+               report: &mut PostProcessReport)
+               -> result::Result<(), String> {
+        if self.mode.promotes() {
+            if let Err(err) = self.channel_name() {
+                error!("post processing error, {}", err);
+                return Err(err.to_string());
+            }
+        }
+
+        debug!("post process: publish (url: {}, channel: {}, mode: {})",
                self.url,
-               self.channel);
+               self.channel,
+               self.mode);
 
         // Things to solve right now
         // * Where do we get the token for authentication?
         // * Should the workers ask for a lease from the JobSrv?
-        let client = depot_client::Client::new(&self.url, PRODUCT, VERSION, None).unwrap();
-        if let Some(err) = client.x_put_package(archive, auth_token).err() {
-            error!("post processing error uploading package, ERR={:?}", err);
-            return false;
+        let client = match depot_client_for_url(&self.url, self.timeout) {
+            Ok(client) => client,
+            Err(err) => {
+                error!("post processing error creating depot client, ERR={:?}", err);
+                return Err(format!("Error creating depot client for {}, {}", self.url, err));
+            }
         };
-
-        if let Some(err) = client
-               .promote_package(archive, &self.channel, auth_token)
-               .err() {
+        if self.mode.uploads() {
+            if let Some(err) = with_retries("uploading package", || {
+                client.x_put_package(archive, auth_token, Some(UploadProgress::default()))
+            })
+                       .err() {
+                error!("post processing error uploading package, ERR={:?}", err);
+                return Err(format!("Error uploading package, {}", err));
+            };
+            report.published = true;
+        }
+        if !self.mode.promotes() {
+            return Ok(());
+        }
+        if let Some(err) = with_retries("promoting package", || {
+            client.promote_package(archive, &self.channel, auth_token)
+        })
+                   .err() {
             error!("post processing error promoting package, ERR={:?}", err);
-            return false;
+            return Err(format!("Error promoting package to {}, {}", self.channel, err));
         };
-        true
+        report.channel = Some(self.channel.clone());
+        report.promoted_to = Some(self.channel.clone());
+        Ok(())
+    }
+}
+
+// Runs a request to the Depot, running it again after a growing delay while it fails with an
+// error which may be transient, such as a refused connection or an unavailable Depot.
+fn with_retries<T, F>(action: &str, mut request: F) -> depot_client::Result<T>
+    where F: FnMut() -> depot_client::Result<T>
+{
+    let mut retries = 0;
+    loop {
+        match request() {
+            Err(ref err) if retries < PUBLISH_RETRIES && is_transient(err) => {
+                retries += 1;
+                warn!("post process: {} failed, retrying ({}/{}), ERR={:?}",
+                      action,
+                      retries,
+                      PUBLISH_RETRIES,
+                      err);
+                thread::sleep(Duration::from_millis(PUBLISH_RETRY_DELAY_MS * retries));
+            }
+            result => return result,
+        }
+    }
+}
+
+// Returns true if a request to the Depot which failed with the given error may succeed if run
+// again.
+fn is_transient(err: &depot_client::Error) -> bool {
+    let err = match *err {
+        depot_client::Error::APIError(status, _) => hab_net::Error::HTTP(status),
+        depot_client::Error::IO(ref e) |
+        depot_client::Error::HyperError(hyper::Error::Io(ref e)) => {
+            hab_net::Error::IO(io::Error::new(e.kind(), e.to_string()))
+        }
+        _ => return false,
+    };
+    err.is_transient()
+}
+
+/// Logs the progress of a package upload to the Depot.
+#[derive(Default)]
+struct UploadProgress {
+    total: u64,
+    sent: u64,
+    logged: u64,
+}
+
+impl DisplayProgress for UploadProgress {
+    fn size(&mut self, size: u64) {
+        self.total = size;
+    }
+
+    fn finish(&mut self) {}
+}
+
+impl Write for UploadProgress {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.sent += buf.len() as u64;
+        if self.total > 0 {
+            let percent = self.sent * 100 / self.total;
+            if percent >= self.logged + UPLOAD_PROGRESS_STEP || self.sent == self.total {
+                info!("post process: uploading package, {}% ({}/{} bytes)",
+                      percent,
+                      self.sent,
+                      self.total);
+                self.logged = percent;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
     }
 }
 
@@ -74,29 +299,37 @@ impl Default for Publish {
                 .unwrap(),
             url: hab_core::url::default_depot_url(),
             channel: hab_core::url::default_depot_channel(),
+            required: true,
+            mode: PublishMode::default(),
+            timeout: DEFAULT_DEPOT_TIMEOUT_SECS,
         }
     }
 }
 
 impl ConfigFile for Publish {
     type Error = Error;
+    const ENV_PREFIX: &'static str = "HAB_BUILDER_WORKER_PUBLISH";
 }
 
 pub struct PostProcessor {
     config_path: PathBuf,
+    depot_timeout: u64,
 }
 
 impl PostProcessor {
-    pub fn new(workspace: &Workspace) -> Self {
+    pub fn new(workspace: &Workspace, depot_timeout: u64) -> Self {
         let parent_path = Path::new(workspace.job.get_project().get_plan_path())
             .parent()
             .unwrap();
         let file_path = workspace.src().join(parent_path.join(CONFIG_FILE));
 
-        PostProcessor { config_path: file_path }
+        PostProcessor {
+            config_path: file_path,
+            depot_timeout: depot_timeout,
+        }
     }
 
-    pub fn run(&mut self, archive: &mut PackageArchive, auth_token: &str) -> bool {
+    pub fn run(&mut self, archive: &mut PackageArchive, auth_token: &str) -> PostProcessReport {
         let mut cfg = if !self.config_path.exists() {
             debug!("no post processing config - using defaults");
             Publish::default()
@@ -106,21 +339,64 @@ impl PostProcessor {
                 Ok(value) => value,
                 Err(e) => {
                     debug!("failed to parse config file! {:?}", e);
-                    return false;
+                    let mut report = PostProcessReport::default();
+                    report.errors.push(format!("Error parsing {}, {}", CONFIG_FILE, e));
+                    return report;
                 }
             }
         };
 
         debug!("starting post processing");
+        cfg.timeout = self.depot_timeout;
         cfg.run(archive, auth_token)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use bld_core::channel::{INVALID_CHANNEL_NAMES, VALID_CHANNEL_NAMES};
     use hab_core::config::ConfigFile;
+    use http_test::{MockServer, Response};
     use super::*;
 
+    // A publish config whose upload always fails, as the archive it's given doesn't exist.
+    fn failing_publish(required: bool) -> (Publish, PackageArchive) {
+        let cfg = Publish {
+            enabled: true,
+            url: "http://127.0.0.1:1/v1/depot".to_string(),
+            channel: "unstable".to_string(),
+            required: required,
+            mode: PublishMode::Both,
+            timeout: DEFAULT_DEPOT_TIMEOUT_SECS,
+        };
+        let archive =
+            PackageArchive::new("/nonexistent/core-foo-1.0.0-20170101000000-x86_64-linux.hart");
+        (cfg, archive)
+    }
+
+    fn fixture_archive() -> PackageArchive {
+        PackageArchive::new(concat!(env!("CARGO_MANIFEST_DIR"),
+                                    "/../core/tests/fixtures/",
+                                    "happyhumans-possums-8.1.4-20160427165340-x86_64-linux.hart"))
+    }
+
+    // Answers requests with each of the given statuses in turn.
+    fn mock_depot(statuses: Vec<&'static str>) -> (String, MockServer) {
+        let depot = MockServer::respond_with(statuses.into_iter().map(Response::new).collect());
+        (format!("{}/v1/depot", depot.url()), depot)
+    }
+
+    fn publish_to(url: String) -> Publish {
+        Publish {
+            enabled: true,
+            url: url,
+            channel: "unstable".to_string(),
+            required: true,
+            mode: PublishMode::Both,
+            timeout: DEFAULT_DEPOT_TIMEOUT_SECS,
+        }
+    }
+
     #[test]
     fn test_publish_config_from_toml() {
         let toml = r#"
@@ -135,4 +411,135 @@ mod tests {
         assert_eq!(false, cfg.enabled);
         assert_eq!("unstable", cfg.channel);
     }
+
+    #[test]
+    fn test_publish_config_channel_name() {
+        for name in VALID_CHANNEL_NAMES.iter().chain(INVALID_CHANNEL_NAMES) {
+            let toml = format!("channel = \"{}\"", name);
+            let cfg = Publish::from_raw(&toml).unwrap();
+            assert_eq!(cfg.channel_name().is_ok(),
+                       VALID_CHANNEL_NAMES.contains(name),
+                       "channel {:?}",
+                       name);
+        }
+    }
+
+    #[test]
+    fn test_publish_config_required_default() {
+        let cfg = Publish::from_raw("enabled = true").unwrap();
+        assert!(cfg.required);
+    }
+
+    #[test]
+    fn test_publish_config_mode() {
+        assert_eq!(Publish::from_raw("").unwrap().mode, PublishMode::Both);
+        for mode in &[PublishMode::GlobalOnly, PublishMode::ChannelOnly, PublishMode::Both] {
+            let toml = format!("mode = \"{}\"", mode);
+            assert_eq!(Publish::from_raw(&toml).unwrap().mode, *mode);
+        }
+        assert!(Publish::from_raw("mode = \"everywhere\"").is_err());
+    }
+
+    #[test]
+    fn test_publish_required_failure_fails() {
+        let (mut cfg, mut archive) = failing_publish(true);
+        let report = cfg.run(&mut archive, "token");
+        assert!(!report.is_success());
+        assert!(!report.published);
+        assert_eq!(report.errors.len(), 1);
+    }
+
+    #[test]
+    fn test_publish_not_required_failure_is_tolerated() {
+        let (mut cfg, mut archive) = failing_publish(false);
+        let report = cfg.run(&mut archive, "token");
+        assert!(report.is_success());
+        assert!(!report.published);
+        assert_eq!(report.warnings.len(), 1);
+        assert!(report.warnings[0].starts_with("Error uploading package"));
+    }
+
+    #[test]
+    fn test_publish_disabled_report() {
+        let mut cfg = Publish::default();
+        cfg.enabled = false;
+        let report = cfg.run(&mut fixture_archive(), "token");
+        assert_eq!(report, PostProcessReport::default());
+        assert!(report.is_success());
+    }
+
+    #[test]
+    fn test_publish_report() {
+        let (url, depot) = mock_depot(vec!["201 Created", "200 OK"]);
+        let report = publish_to(url).run(&mut fixture_archive(), "token");
+        let requests = depot.request_lines();
+
+        assert!(requests[0].starts_with("POST /v1/depot/pkgs/happyhumans/possums/8.1.4/"));
+        assert!(requests[1].starts_with("PUT /v1/depot/channels/happyhumans/unstable/pkgs/"));
+        assert!(report.is_success());
+        assert!(report.published);
+        assert_eq!(report.channel, Some("unstable".to_string()));
+        assert_eq!(report.promoted_to, Some("unstable".to_string()));
+        assert!(!report.notified);
+        assert!(report.errors.is_empty());
+        assert!(report.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_publish_retries_transient_failure() {
+        let (url, depot) = mock_depot(vec!["503 Service Unavailable", "201 Created", "200 OK"]);
+        let report = publish_to(url).run(&mut fixture_archive(), "token");
+        let requests = depot.request_lines();
+
+        assert_eq!(requests.len(), 3);
+        assert!(requests[0].starts_with("POST /v1/depot/pkgs/happyhumans/possums/8.1.4/"));
+        assert!(requests[1].starts_with("POST /v1/depot/pkgs/happyhumans/possums/8.1.4/"));
+        assert!(report.is_success());
+        assert!(report.published);
+    }
+
+    #[test]
+    fn test_publish_report_promote_failure() {
+        let (url, depot) = mock_depot(vec!["201 Created", "500 Internal Server Error"]);
+        let report = publish_to(url).run(&mut fixture_archive(), "token");
+        assert_eq!(depot.requests().len(), 2);
+
+        assert!(!report.is_success());
+        assert!(report.published);
+        assert_eq!(report.channel, None);
+        assert_eq!(report.promoted_to, None);
+        assert_eq!(report.errors.len(), 1);
+    }
+
+    #[test]
+    fn test_publish_global_only() {
+        let (url, depot) = mock_depot(vec!["201 Created"]);
+        let mut cfg = publish_to(url);
+        cfg.mode = PublishMode::GlobalOnly;
+        let report = cfg.run(&mut fixture_archive(), "token");
+        let requests = depot.request_lines();
+
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].starts_with("POST /v1/depot/pkgs/happyhumans/possums/8.1.4/"));
+        assert!(report.is_success());
+        assert!(report.published);
+        assert_eq!(report.channel, None);
+        assert_eq!(report.promoted_to, None);
+    }
+
+    #[test]
+    fn test_publish_channel_only() {
+        let (url, depot) = mock_depot(vec!["200 OK"]);
+        let mut cfg = publish_to(url);
+        cfg.mode = PublishMode::ChannelOnly;
+        let report = cfg.run(&mut fixture_archive(), "token");
+        let requests = depot.request_lines();
+
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].starts_with("PUT /v1/depot/channels/happyhumans/unstable/pkgs/"));
+        assert!(report.is_success());
+        assert!(!report.published);
+        assert_eq!(report.channel, Some("unstable".to_string()));
+        assert_eq!(report.promoted_to, Some("unstable".to_string()));
+    }
 }
@@ -12,8 +12,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::fs::File;
-use std::io::Read;
+use std::fs::{self, File};
+use std::io::{self, Read};
 use std::path::{Path, PathBuf};
 
 use hab_core::package::PackageArchive;
@@ -66,6 +66,46 @@ impl Workspace {
     pub fn studio(&self) -> &Path {
         &self.studio
     }
+
+    /// Total size in bytes of the files in the workspace
+    pub fn size(&self) -> io::Result<u64> {
+        dir_size(self.root())
+    }
+
+    /// Removes the cloned source of the build, keeping its built artifacts and logs.
+    pub fn cleanup(&self) -> io::Result<()> {
+        remove_dir(self.src())
+    }
+
+    /// Removes the entire workspace. The studio must have been removed first, as a studio which
+    /// is still present may have host directories mounted inside it.
+    pub fn cleanup_all(&self) -> io::Result<()> {
+        if self.studio().exists() {
+            return Err(io::Error::new(io::ErrorKind::Other,
+                                      format!("studio {} has not been removed",
+                                              self.studio().display())));
+        }
+        remove_dir(self.root())
+    }
+}
+
+fn remove_dir(path: &Path) -> io::Result<()> {
+    match fs::remove_dir_all(path) {
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+fn dir_size(path: &Path) -> io::Result<u64> {
+    let metadata = try!(fs::symlink_metadata(path));
+    if !metadata.is_dir() {
+        return Ok(metadata.len());
+    }
+    let mut size = 0;
+    for entry in try!(fs::read_dir(path)) {
+        size += try!(dir_size(&try!(entry).path()));
+    }
+    Ok(size)
 }
 
 #[derive(Debug)]
@@ -126,6 +166,11 @@ impl Default for LastBuild {
 
 #[cfg(test)]
 mod tests {
+    use std::env;
+    use std::io::Write;
+
+    use protocol::jobsrv;
+
     use super::*;
 
     const ENV: &'static str = "
@@ -154,4 +199,57 @@ mod tests {
         assert_eq!(build.pkg_blake2bsum,
                    "3b38af666a8f307b89ae47ff098cb75503ee15892d1a8a98d0ae24da1cfd153b");
     }
+
+    // Creates a workspace with cloned source, a built artifact and logs on disk.
+    fn populated_workspace(name: &str) -> Workspace {
+        let data_path = env::temp_dir().join(format!("workspace-tests-{}", name));
+        let _ = fs::remove_dir_all(&data_path);
+        let mut job = jobsrv::Job::new();
+        job.set_id(1);
+        let workspace = Workspace::new(data_path.to_string_lossy().into_owned(), Job::new(job));
+        for dir in &[workspace.src().join(".git"), workspace.out().to_path_buf()] {
+            fs::create_dir_all(dir).unwrap();
+        }
+        for &(ref path, size) in &[(workspace.src().join("plan.sh"), 100),
+                               (workspace.out().join("core-foo.hart"), 1000),
+                               (workspace.root().join("stdout.log"), 10)] {
+            File::create(path)
+                .unwrap()
+                .write_all(&vec![0; size])
+                .unwrap();
+        }
+        workspace
+    }
+
+    #[test]
+    fn workspace_size() {
+        let workspace = populated_workspace("size");
+        assert_eq!(workspace.size().unwrap(), 1110);
+    }
+
+    #[test]
+    fn cleanup_keeps_artifacts_and_logs() {
+        let workspace = populated_workspace("cleanup");
+        workspace.cleanup().unwrap();
+        assert!(!workspace.src().exists());
+        assert!(workspace.out().join("core-foo.hart").is_file());
+        assert!(workspace.root().join("stdout.log").is_file());
+        // cleaning up again is not an error
+        workspace.cleanup().unwrap();
+    }
+
+    #[test]
+    fn cleanup_all_removes_workspace() {
+        let workspace = populated_workspace("cleanup-all");
+        workspace.cleanup_all().unwrap();
+        assert!(!workspace.root().exists());
+    }
+
+    #[test]
+    fn cleanup_all_refuses_with_studio_present() {
+        let workspace = populated_workspace("cleanup-all-studio");
+        fs::create_dir_all(workspace.studio()).unwrap();
+        assert!(workspace.cleanup_all().is_err());
+        assert!(workspace.out().join("core-foo.hart").is_file());
+    }
 }
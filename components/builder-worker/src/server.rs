@@ -15,14 +15,15 @@
 use std::sync::{Arc, RwLock};
 
 use hab_net::server::{NetIdent, ZMQ_CONTEXT};
-use protobuf::{parse_from_bytes, Message};
-use protocol;
+use protobuf::{parse_from_bytes, Message, ProtobufError};
+use protocol::jobsrv;
+use protocol::net::{self, ErrCode};
 use zmq;
 
 use config::Config;
 use error::Result;
 use heartbeat::{HeartbeatCli, HeartbeatMgr};
-use runner::{RunnerCli, RunnerMgr};
+use runner::{Job, RunnerCli, RunnerMgr};
 
 enum State {
     Ready,
@@ -101,6 +102,14 @@ impl Server {
                 try!(self.fe_sock.recv(&mut self.msg, 0));
                 match self.state {
                     State::Ready => {
+                        let declined = {
+                            screen_job(&self.config.read().unwrap(), &self.msg)
+                        };
+                        if let Some(reply) = declined {
+                            try!(self.fe_sock.send(&reply.write_to_bytes().unwrap(), 0));
+                            fe_msg = false;
+                            continue;
+                        }
                         try!(self.runner_cli.send(&self.msg));
                         {
                             let reply = try!(self.runner_cli.recv_ack());
@@ -109,8 +118,13 @@ impl Server {
                         try!(self.set_busy());
                     }
                     State::Busy => {
-                        let mut reply: protocol::jobsrv::Job = parse_from_bytes(&self.msg).unwrap();
-                        reply.set_state(protocol::jobsrv::JobState::Rejected);
+                        let reply = match parse_from_bytes::<jobsrv::Job>(&self.msg) {
+                            Ok(mut reply) => {
+                                reply.set_state(jobsrv::JobState::Rejected);
+                                reply
+                            }
+                            Err(err) => malformed_job_reply(err),
+                        };
                         try!(self.fe_sock.send(&reply.write_to_bytes().unwrap(), 0));
                     }
                 }
@@ -134,6 +148,69 @@ impl Server {
 
 impl NetIdent for Server {}
 
+// Returns the reply to a job offered by the job server which the worker won't run, or `None` if
+// the runner should take it. A job for a target the worker doesn't support is handed back as
+// pending so it's dispatched to another worker.
+fn screen_job(config: &Config, msg: &[u8]) -> Option<jobsrv::Job> {
+    let job = match parse_from_bytes::<jobsrv::Job>(msg) {
+        Ok(job) => Job::new(job),
+        Err(err) => return Some(malformed_job_reply(err)),
+    };
+    let target = job.target();
+    if config.accepts_target(&target) {
+        return None;
+    }
+    debug!("declining work for unsupported target, target={}, job={:?}",
+           target,
+           job);
+    let mut reply: jobsrv::Job = (*job).clone();
+    reply.set_state(jobsrv::JobState::Pending);
+    Some(reply)
+}
+
+fn malformed_job_reply(err: ProtobufError) -> jobsrv::Job {
+    error!("Unable to read job from job server, err={}", err);
+    let mut reply = jobsrv::Job::new();
+    reply.set_state(jobsrv::JobState::Failed);
+    reply.set_error(net::err(ErrCode::BAD_REMOTE_REPLY, "wk:srv:0"));
+    reply
+}
+
 pub fn run(config: Config) -> Result<()> {
     try!(Server::new(config)).run()
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use hab_core::package::PackageTarget;
+
+    use super::*;
+
+    fn job_msg(plan_path: &str) -> Vec<u8> {
+        let mut job = jobsrv::Job::new();
+        job.set_id(1);
+        job.set_state(jobsrv::JobState::Dispatched);
+        job.mut_project().set_plan_path(plan_path.to_string());
+        job.write_to_bytes().unwrap()
+    }
+
+    #[test]
+    fn screen_job_declines_unsupported_target() {
+        let mut config = Config::default();
+        config.supported_targets = vec![PackageTarget::from_str("x86_64-linux").unwrap()];
+
+        let reply = screen_job(&config, &job_msg("plan.ps1")).unwrap();
+        assert_eq!(reply.get_id(), 1);
+        assert_eq!(reply.get_state(), jobsrv::JobState::Pending);
+        assert!(screen_job(&config, &job_msg("plan.sh")).is_none());
+    }
+
+    #[test]
+    fn screen_job_fails_malformed_job() {
+        let reply = screen_job(&Config::default(), &[0xff, 0xff, 0xff]).unwrap();
+        assert_eq!(reply.get_state(), jobsrv::JobState::Failed);
+        assert_eq!(reply.get_error().get_code(), ErrCode::BAD_REMOTE_REPLY);
+    }
+}
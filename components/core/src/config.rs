@@ -12,35 +12,856 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::BTreeMap;
+use std::env;
 use std::error::Error as StdError;
+use std::fmt;
 use std::fs::File;
 use std::io::Read;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "config_url")]
+use std::time::Duration;
 
-use serde::de::DeserializeOwned;
+use extern_url::Url;
+#[cfg(feature = "config_url")]
+use hyper;
+#[cfg(feature = "config_url")]
+use hyper::net::HttpsConnector;
+#[cfg(feature = "config_url")]
+use hyper_openssl::OpensslClient;
+use serde::de::{Deserialize, DeserializeOwned, Deserializer, IntoDeserializer, Visitor};
+use serde::de::value::MapDeserializer;
 use toml;
 
 use error::Error;
 
+/// Separator used between nested keys of an environment variable, for example
+/// `HAB_HTTP__PORT` maps to the `port` key of the `[http]` table.
+pub const ENV_KEY_SEPARATOR: &'static str = "__";
+
+/// Environment variable which, when set, overrides the path returned by `default_path()`.
+pub const CONFIG_PATH_ENVVAR: &'static str = "HAB_CONFIG_PATH";
+
+/// Environment variable which, when set, names a URL to read configuration from instead of the
+/// file at `default_path()`.
+pub const CONFIG_URL_ENVVAR: &'static str = "HAB_CONFIG_URL";
+
+/// How long, in seconds, fetching configuration over HTTP may wait on each read from or write to
+/// the remote end. This is not a deadline for the whole request, so a server which keeps sending
+/// data slowly can take longer.
+pub const CONFIG_URL_TIMEOUT_SECS: u64 = 10;
+
+/// Environment variable which, when set, adds an excerpt of the configuration around a syntax
+/// error to the error. Values of keys which look like secrets are redacted from the excerpt.
+pub const CONFIG_VERBOSE_ERRORS_ENVVAR: &'static str = "HAB_CONFIG_VERBOSE_ERRORS";
+
+// Lines shown on either side of the offending line in the excerpt of a verbose syntax error.
+const SNIPPET_CONTEXT_LINES: usize = 2;
+
+// Words which mark a line of configuration as holding a secret.
+const SECRET_WORDS: &'static [&'static str] = &["secret", "password", "passwd", "token", "key",
+                                                 "credential"];
+
 pub trait ConfigFile: DeserializeOwned + Sized {
     type Error: StdError + From<Error>;
 
+    /// Prefix of the environment variables read by `from_env()`, such as `HAB_BUILDER_API`.
+    /// Every component has its own so that services sharing an environment don't read each
+    /// other's settings.
+    const ENV_PREFIX: &'static str;
+
+    /// Name of the service reading this configuration, used to locate its default configuration
+    /// file.
+    fn service_name() -> &'static str {
+        "hab"
+    }
+
+    /// Path of the configuration file read when none is given. This is the value of
+    /// `CONFIG_PATH_ENVVAR` if it is set and `/hab/svc/<service_name>/config.toml` otherwise.
+    fn default_path() -> PathBuf {
+        match env::var_os(CONFIG_PATH_ENVVAR) {
+            Some(ref path) if !path.is_empty() => PathBuf::from(path),
+            _ => PathBuf::from(format!("/hab/svc/{}/config.toml", Self::service_name())),
+        }
+    }
+
+    /// URL of the configuration to read in place of the file at `default_path()`, if
+    /// `CONFIG_URL_ENVVAR` is set.
+    fn default_url() -> Option<String> {
+        match env::var(CONFIG_URL_ENVVAR) {
+            Ok(ref url) if !url.is_empty() => Some(url.to_string()),
+            _ => None,
+        }
+    }
+
     fn from_file<T: AsRef<Path>>(filepath: T) -> Result<Self, Self::Error> {
-        let mut file = match File::open(filepath.as_ref()) {
+        let file = match File::open(filepath.as_ref()) {
             Ok(f) => f,
             Err(e) => return Err(Self::Error::from(Error::ConfigFileIO(e))),
         };
+        Self::from_reader(file)
+    }
+
+    /// Build a configuration from the TOML contents of any reader, such as standard input or a
+    /// stream from a secrets store.
+    fn from_reader<R: Read>(mut reader: R) -> Result<Self, Self::Error> {
         let mut raw = String::new();
-        match file.read_to_string(&mut raw) {
+        match reader.read_to_string(&mut raw) {
             Ok(_) => (),
             Err(e) => return Err(Self::Error::from(Error::ConfigFileIO(e))),
         }
         Self::from_raw(&raw)
     }
 
+    /// Build a configuration from the TOML document at a URL. `file://` URLs are read from disk
+    /// while `http://` and `https://` URLs are fetched, such as from a key-value store's HTTP
+    /// API. Fetching requires the `config_url` feature and fails without it.
+    fn from_url(url: &str) -> Result<Self, Self::Error> {
+        let parsed = match Url::parse(url) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                let msg = format!("{}, {}", url, e);
+                return Err(Self::Error::from(Error::ConfigUrlFetch(msg)));
+            }
+        };
+        match parsed.scheme() {
+            "file" => {
+                match parsed.to_file_path() {
+                    Ok(path) => Self::from_file(path),
+                    Err(_) => {
+                        let msg = format!("{}, not a local file path", url);
+                        Err(Self::Error::from(Error::ConfigUrlFetch(msg)))
+                    }
+                }
+            }
+            "http" | "https" => {
+                let raw = fetch_url(parsed)?;
+                Self::from_raw(&raw)
+            }
+            scheme => {
+                let msg = format!("{}, unsupported scheme '{}'", url, scheme);
+                Err(Self::Error::from(Error::ConfigUrlFetch(msg)))
+            }
+        }
+    }
+
+    /// Build a configuration entirely from environment variables.
+    ///
+    /// Every variable starting with `ENV_PREFIX` followed by an underscore is turned into a
+    /// key of a TOML document, lowercased and split on `ENV_KEY_SEPARATOR` into nested tables.
+    /// Values of string fields are taken as they are, less the quotes of a quoted TOML string,
+    /// and values of other fields are read as TOML values. Empty values are skipped so that
+    /// defaults apply.
+    fn from_env() -> Result<Self, Self::Error> {
+        Ok(Self::resolve(None, toml::value::Table::new())?.config)
+    }
+
+    /// Build a configuration from the file at `default_path()`, if there is one, and the
+    /// environment, recording where each value came from.
+    fn resolved() -> Result<ResolvedConfig<Self>, Self::Error> {
+        let path = Self::default_path();
+        if path.is_file() {
+            Self::resolve(Some(path.as_path()), toml::value::Table::new())
+        } else {
+            Self::resolve(None, toml::value::Table::new())
+        }
+    }
+
+    /// Build a configuration by layering, from lowest to highest precedence, the defaults, the
+    /// given file, the environment variables read by `from_env()` and the given overrides. The
+    /// source of every value set by a layer is recorded in the returned `ResolvedConfig`.
+    fn resolve(file: Option<&Path>,
+               overrides: toml::value::Table)
+               -> Result<ResolvedConfig<Self>, Self::Error> {
+        let mut root = toml::value::Table::new();
+        let mut sources = BTreeMap::new();
+        if let Some(path) = file {
+            let mut raw = String::new();
+            match File::open(path).and_then(|mut f| f.read_to_string(&mut raw)) {
+                Ok(_) => (),
+                Err(e) => return Err(Self::Error::from(Error::ConfigFileIO(e))),
+            }
+            let table = toml::from_str::<toml::value::Table>(&raw)
+                .map_err(|e| syntax_error(&raw, e, verbose_errors()))?;
+            let source = ValueSource::File(path.to_path_buf());
+            merge_values(&mut root, table, "", &source, &mut sources);
+        }
+        for (var, path, value) in env_values(Self::ENV_PREFIX) {
+            let mut table = toml::value::Table::new();
+            insert_env_value(&mut table, &path, value);
+            merge_values(&mut root, table, "", &ValueSource::Env(var), &mut sources);
+        }
+        merge_values(&mut root, overrides, "", &ValueSource::Override, &mut sources);
+        let config = Self::deserialize(ConfigValue::from_table(root, "", &sources))
+            .map_err(|e| Error::ConfigFileSyntax(e))?;
+        Ok(ResolvedConfig {
+               config: config,
+               sources: sources,
+           })
+    }
+
     fn from_raw(raw: &str) -> Result<Self, Self::Error> {
         let value = toml::from_str(&raw)
-            .map_err(|e| Error::ConfigFileSyntax(e))?;
+            .map_err(|e| syntax_error(raw, e, verbose_errors()))?;
         Ok(value)
     }
 }
+
+/// Where the effective value of a configuration key came from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ValueSource {
+    /// The key was not set, so the configuration's default applies
+    Default,
+    /// Set by the configuration file at the given path
+    File(PathBuf),
+    /// Set by the named environment variable
+    Env(String),
+    /// Set by an override given to `ConfigFile::resolve()`
+    Override,
+}
+
+impl fmt::Display for ValueSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ValueSource::Default => write!(f, "default"),
+            ValueSource::File(ref path) => write!(f, "file {}", path.display()),
+            ValueSource::Env(ref var) => write!(f, "env {}", var),
+            ValueSource::Override => write!(f, "override"),
+        }
+    }
+}
+
+/// A configuration along with the source of each value set explicitly, keyed by the dotted path
+/// of the value such as `http.port`.
+#[derive(Debug)]
+pub struct ResolvedConfig<T> {
+    pub config: T,
+    sources: BTreeMap<String, ValueSource>,
+}
+
+impl<T> ResolvedConfig<T> {
+    /// Returns where the value at the given dotted key path came from.
+    pub fn source(&self, key: &str) -> ValueSource {
+        match self.sources.get(key) {
+            Some(source) => source.clone(),
+            None => ValueSource::Default,
+        }
+    }
+
+    /// Returns the sources of all values set explicitly, ordered by key.
+    pub fn sources(&self) -> &BTreeMap<String, ValueSource> {
+        &self.sources
+    }
+}
+
+#[cfg(feature = "config_url")]
+fn fetch_url(url: Url) -> Result<String, Error> {
+    let ssl = match OpensslClient::new() {
+        Ok(ssl) => ssl,
+        Err(e) => return Err(Error::ConfigUrlFetch(format!("{}, {}", url, e))),
+    };
+    let mut client = hyper::Client::with_connector(HttpsConnector::new(ssl));
+    client.set_read_timeout(Some(Duration::from_secs(CONFIG_URL_TIMEOUT_SECS)));
+    client.set_write_timeout(Some(Duration::from_secs(CONFIG_URL_TIMEOUT_SECS)));
+    let mut response = match client.get(url.as_str()).send() {
+        Ok(response) => response,
+        Err(e) => return Err(Error::ConfigUrlFetch(format!("{}, {}", url, e))),
+    };
+    if !response.status.is_success() {
+        return Err(Error::ConfigUrlFetch(format!("{}, server answered {}", url, response.status)));
+    }
+    let mut raw = String::new();
+    response
+        .read_to_string(&mut raw)
+        .map_err(Error::ConfigFileIO)?;
+    Ok(raw)
+}
+
+#[cfg(not(feature = "config_url"))]
+fn fetch_url(url: Url) -> Result<String, Error> {
+    Err(Error::ConfigUrlFetch(format!("{}, built without the config_url feature", url)))
+}
+
+fn verbose_errors() -> bool {
+    match env::var_os(CONFIG_VERBOSE_ERRORS_ENVVAR) {
+        Some(ref value) => !value.is_empty(),
+        None => false,
+    }
+}
+
+// Wraps an error parsing the given raw configuration. Verbose errors carry an excerpt of the
+// lines around the error, if the error has a position, while terse ones only give the position.
+fn syntax_error(raw: &str, err: toml::de::Error, verbose: bool) -> Error {
+    if verbose {
+        if let Some(line) = error_line(&err) {
+            return Error::ConfigFileSyntaxSnippet(err, snippet(raw, line));
+        }
+    }
+    Error::ConfigFileSyntax(err)
+}
+
+// Returns the one-based line of a parsing error, which the error only exposes in its message.
+fn error_line(err: &toml::de::Error) -> Option<usize> {
+    let msg = err.to_string();
+    match msg.rfind(" at line ") {
+        Some(idx) => msg[idx + " at line ".len()..].trim().parse().ok(),
+        None => None,
+    }
+}
+
+// Renders the lines of `raw` around the given one-based line, marking that line.
+fn snippet(raw: &str, line: usize) -> String {
+    let lines = redact(raw);
+    let first = if line > SNIPPET_CONTEXT_LINES {
+        line - SNIPPET_CONTEXT_LINES
+    } else {
+        1
+    };
+    let last = (line + SNIPPET_CONTEXT_LINES).min(lines.len());
+    let mut out = Vec::new();
+    for n in first..last + 1 {
+        let marker = if n == line { ">" } else { " " };
+        out.push(format!("{} {:>4} | {}", marker, n, lines[n - 1]));
+    }
+    out.join("\n")
+}
+
+// Returns the lines of a configuration with the values of secrets replaced. A value is secret if
+// its key, or the name of the table it is in, looks like it names a secret. Lines continuing a
+// secret multi-line string or array are replaced as well.
+fn redact(raw: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut secret_table = false;
+    let mut pending = Pending::Nothing;
+    let mut pending_secret = false;
+    for line in raw.lines() {
+        match pending {
+            Pending::Nothing => (),
+            Pending::Str(delim) => {
+                if line.contains(delim) {
+                    pending = Pending::Nothing;
+                }
+                lines.push(redact_continuation(line, pending_secret));
+                continue;
+            }
+            Pending::Array(depth) => {
+                let depth = depth + bracket_balance(line);
+                if depth <= 0 {
+                    pending = Pending::Nothing;
+                } else {
+                    pending = Pending::Array(depth);
+                }
+                lines.push(redact_continuation(line, pending_secret));
+                continue;
+            }
+        }
+        if line.trim_left().starts_with('[') {
+            secret_table = is_secret(line);
+            lines.push(line.to_string());
+            continue;
+        }
+        match line.find('=') {
+            Some(idx) => {
+                let value = line[idx + 1..].trim();
+                pending = Pending::from_value(value);
+                pending_secret = secret_table || is_secret(&line[..idx]);
+                if pending_secret {
+                    lines.push(format!("{}= <redacted>", &line[..idx]));
+                } else {
+                    lines.push(line.to_string());
+                }
+            }
+            None => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+// A value which carries on past the line it starts on.
+#[derive(Clone, Copy)]
+enum Pending {
+    Nothing,
+    // A multi-line string, ended by the given delimiter.
+    Str(&'static str),
+    // An array, with the given number of brackets left open.
+    Array(i32),
+}
+
+impl Pending {
+    fn from_value(value: &str) -> Self {
+        for &delim in &["\"\"\"", "'''"] {
+            if value.starts_with(delim) && !value[delim.len()..].contains(delim) {
+                return Pending::Str(delim);
+            }
+        }
+        if value.starts_with('[') {
+            let depth = bracket_balance(value);
+            if depth > 0 {
+                return Pending::Array(depth);
+            }
+        }
+        Pending::Nothing
+    }
+}
+
+fn is_secret(text: &str) -> bool {
+    let lower = text.to_lowercase();
+    SECRET_WORDS.iter().any(|word| lower.contains(word))
+}
+
+fn bracket_balance(text: &str) -> i32 {
+    text.chars()
+        .map(|c| match c {
+                 '[' => 1,
+                 ']' => -1,
+                 _ => 0,
+             })
+        .sum()
+}
+
+fn redact_continuation(line: &str, secret: bool) -> String {
+    if secret {
+        let indent = line.len() - line.trim_left().len();
+        format!("{}<redacted>", &line[..indent])
+    } else {
+        line.to_string()
+    }
+}
+
+// Returns the name, key path and value of every non-empty environment variable starting with
+// the given prefix followed by an underscore.
+fn env_values(prefix: &str) -> Vec<(String, Vec<String>, toml::Value)> {
+    let prefix = format!("{}_", prefix);
+    let mut values = vec![];
+    for (key, value) in env::vars() {
+        if !key.starts_with(&prefix) || value.is_empty() {
+            continue;
+        }
+        let path: Vec<String> = key[prefix.len()..]
+            .split(ENV_KEY_SEPARATOR)
+            .map(|k| k.to_lowercase())
+            .collect();
+        if path.iter().any(|k| k.is_empty()) {
+            continue;
+        }
+        values.push((key.clone(), path, toml::Value::String(value)));
+    }
+    values
+}
+
+// Merges `src` into `dst`, recording `source` as the origin of every leaf value taken from `src`.
+// Leaves replaced by a table, or tables replaced by a leaf, lose their recorded sources.
+fn merge_values(dst: &mut toml::value::Table,
+                src: toml::value::Table,
+                prefix: &str,
+                source: &ValueSource,
+                sources: &mut BTreeMap<String, ValueSource>) {
+    for (key, value) in src {
+        let path = format!("{}{}", prefix, key);
+        let nested = format!("{}.", path);
+        match value {
+            toml::Value::Table(table) => {
+                let entry = dst.entry(key)
+                    .or_insert_with(|| toml::Value::Table(toml::value::Table::new()));
+                if !entry.is_table() {
+                    *entry = toml::Value::Table(toml::value::Table::new());
+                    sources.remove(&path);
+                }
+                if let toml::Value::Table(ref mut dst_table) = *entry {
+                    merge_values(dst_table, table, &nested, source, sources);
+                }
+            }
+            value => {
+                dst.insert(key, value);
+                let stale: Vec<String> = sources
+                    .keys()
+                    .filter(|k| k.starts_with(&nested))
+                    .cloned()
+                    .collect();
+                for key in stale {
+                    sources.remove(&key);
+                }
+                sources.insert(path, source.clone());
+            }
+        }
+    }
+}
+
+// Reads the value of an environment variable as a TOML value, or as a string if it isn't one.
+fn env_value(raw: &str) -> toml::Value {
+    match toml::from_str::<toml::value::Table>(&format!("value = {}", raw)) {
+        Ok(mut table) => table.remove("value").unwrap(),
+        Err(_) => toml::Value::String(raw.to_string()),
+    }
+}
+
+fn insert_env_value(table: &mut toml::value::Table, path: &[String], value: toml::Value) {
+    if path.len() == 1 {
+        table.insert(path[0].clone(), value);
+        return;
+    }
+    let entry = table
+        .entry(path[0].clone())
+        .or_insert_with(|| toml::Value::Table(toml::value::Table::new()));
+    if !entry.is_table() {
+        *entry = toml::Value::Table(toml::value::Table::new());
+    }
+    if let toml::Value::Table(ref mut nested) = *entry {
+        insert_env_value(nested, &path[1..], value);
+    }
+}
+
+// A configuration value being deserialized. Values read from environment variables are kept as
+// the strings they were set to until the type of the field they're read into is known, so that
+// a string field set to `1.0` or `true` isn't handed a number or a boolean.
+enum ConfigValue {
+    Table(BTreeMap<String, ConfigValue>),
+    Env(String),
+    Toml(toml::Value),
+}
+
+impl ConfigValue {
+    // Converts a merged configuration, marking the values `sources` records as coming from the
+    // environment.
+    fn from_table(table: toml::value::Table,
+                  prefix: &str,
+                  sources: &BTreeMap<String, ValueSource>)
+                  -> Self {
+        let mut values = BTreeMap::new();
+        for (key, value) in table {
+            let path = format!("{}{}", prefix, key);
+            let value = match value {
+                toml::Value::Table(nested) => {
+                    ConfigValue::from_table(nested, &format!("{}.", path), sources)
+                }
+                toml::Value::String(raw) => {
+                    match sources.get(&path) {
+                        Some(&ValueSource::Env(_)) => ConfigValue::Env(raw),
+                        _ => ConfigValue::Toml(toml::Value::String(raw)),
+                    }
+                }
+                value => ConfigValue::Toml(value),
+            };
+            values.insert(key, value);
+        }
+        ConfigValue::Table(values)
+    }
+}
+
+impl<'de> Deserializer<'de> for ConfigValue {
+    type Error = toml::de::Error;
+
+    // Fields which don't say what they expect get environment values read as TOML values.
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where V: Visitor<'de>
+    {
+        match self {
+            ConfigValue::Table(values) => {
+                let mut map: MapDeserializer<_, toml::de::Error> =
+                    MapDeserializer::new(values.into_iter());
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+            ConfigValue::Env(raw) => env_value(&raw).deserialize_any(visitor),
+            ConfigValue::Toml(value) => value.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where V: Visitor<'de>
+    {
+        match self {
+            ConfigValue::Env(raw) => {
+                match env_value(&raw) {
+                    toml::Value::String(value) => visitor.visit_string(value),
+                    _ => visitor.visit_string(raw),
+                }
+            }
+            value => value.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where V: Visitor<'de>
+    {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_char<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where V: Visitor<'de>
+    {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where V: Visitor<'de>
+    {
+        match self {
+            ConfigValue::Toml(value) => value.deserialize_option(visitor),
+            value => visitor.visit_some(value),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(self,
+                                     _name: &'static str,
+                                     visitor: V)
+                                     -> Result<V::Value, Self::Error>
+        where V: Visitor<'de>
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(self,
+                           name: &'static str,
+                           variants: &'static [&'static str],
+                           visitor: V)
+                           -> Result<V::Value, Self::Error>
+        where V: Visitor<'de>
+    {
+        match self {
+            ConfigValue::Env(raw) => env_value(&raw).deserialize_enum(name, variants, visitor),
+            ConfigValue::Toml(value) => value.deserialize_enum(name, variants, visitor),
+            value => value.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, toml::de::Error> for ConfigValue {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::{Cursor, Write};
+    use std::path::PathBuf;
+    use std::sync::Mutex;
+
+    use extern_url::Url;
+    #[cfg(feature = "config_url")]
+    use habitat_http_test::{MockServer, Response};
+    use toml;
+
+    use error::Error;
+    use super::{redact, syntax_error, ConfigFile, ValueSource, CONFIG_PATH_ENVVAR};
+
+    lazy_static! {
+        // Held by tests which change environment variables, as the environment is shared by
+        // every test thread.
+        static ref ENV_LOCK: Mutex<()> = Mutex::new(());
+    }
+
+    // Answers requests with the given status line and body, returning the configuration's URL.
+    #[cfg(feature = "config_url")]
+    fn serve_once(status: &'static str, body: &'static str) -> String {
+        let server = MockServer::start(move |_| Response::new(status).body(body));
+        format!("{}/config.toml", server.url())
+    }
+
+    #[derive(Debug, Default, Deserialize)]
+    #[serde(default)]
+    struct TestConfig {
+        name: String,
+        port: u16,
+    }
+
+    impl ConfigFile for TestConfig {
+        type Error = Error;
+        const ENV_PREFIX: &'static str = "HAB_TEST_SERVICE";
+
+        fn service_name() -> &'static str {
+            "test-service"
+        }
+    }
+
+    #[test]
+    fn config_from_reader() {
+        let reader = Cursor::new("name = \"test\"\nport = 9000\n");
+        let config = TestConfig::from_reader(reader).unwrap();
+        assert_eq!(config.name, "test");
+        assert_eq!(config.port, 9000);
+    }
+
+    #[test]
+    fn config_default_path() {
+        let _env = ENV_LOCK.lock().unwrap();
+        env::remove_var(CONFIG_PATH_ENVVAR);
+        assert_eq!(TestConfig::default_path(),
+                   PathBuf::from("/hab/svc/test-service/config.toml"));
+        env::set_var(CONFIG_PATH_ENVVAR, "/tmp/test-service.toml");
+        assert_eq!(TestConfig::default_path(),
+                   PathBuf::from("/tmp/test-service.toml"));
+        env::remove_var(CONFIG_PATH_ENVVAR);
+    }
+
+    #[test]
+    #[cfg(feature = "config_url")]
+    fn config_from_http_url() {
+        let url = serve_once("200 OK", "name = \"remote\"\nport = 9001\n");
+        let config = TestConfig::from_url(&url).unwrap();
+        assert_eq!(config.name, "remote");
+        assert_eq!(config.port, 9001);
+    }
+
+    #[test]
+    #[cfg(feature = "config_url")]
+    fn config_from_http_url_error_status() {
+        let url = serve_once("404 Not Found", "");
+        match TestConfig::from_url(&url) {
+            Err(Error::ConfigUrlFetch(ref e)) => assert!(e.contains("404"), "{}", e),
+            e => panic!("Unexpected result: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn config_from_file_url() {
+        let path = env::temp_dir().join("config-from-file-url.toml");
+        File::create(&path)
+            .unwrap()
+            .write_all(b"name = \"local\"\n")
+            .unwrap();
+        let url = Url::from_file_path(&path).unwrap();
+        let config = TestConfig::from_url(url.as_str()).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(config.name, "local");
+    }
+
+    #[derive(Debug, Default, Deserialize)]
+    #[serde(default)]
+    struct ResolvedTestConfig {
+        name: String,
+        port: u16,
+        listen: String,
+    }
+
+    impl ConfigFile for ResolvedTestConfig {
+        type Error = Error;
+        const ENV_PREFIX: &'static str = "HAB_RESOLVED_TEST";
+    }
+
+    #[test]
+    fn config_resolve_sources() {
+        let path = env::temp_dir().join("config-resolve-sources.toml");
+        File::create(&path)
+            .unwrap()
+            .write_all(b"name = \"file\"\nport = 9000\n")
+            .unwrap();
+        let _env = ENV_LOCK.lock().unwrap();
+        env::set_var("HAB_RESOLVED_TEST_PORT", "9001");
+        let resolved = ResolvedTestConfig::resolve(Some(path.as_path()), toml::value::Table::new());
+        env::remove_var("HAB_RESOLVED_TEST_PORT");
+        fs::remove_file(&path).unwrap();
+        let resolved = resolved.unwrap();
+
+        assert_eq!(resolved.config.name, "file");
+        assert_eq!(resolved.source("name"), ValueSource::File(path.clone()));
+        assert_eq!(resolved.config.port, 9001);
+        assert_eq!(resolved.source("port"),
+                   ValueSource::Env("HAB_RESOLVED_TEST_PORT".to_string()));
+        assert_eq!(resolved.config.listen, "");
+        assert_eq!(resolved.source("listen"), ValueSource::Default);
+        assert_eq!(resolved.sources().len(), 2);
+    }
+
+    #[test]
+    fn config_resolve_overrides() {
+        let mut overrides = toml::value::Table::new();
+        overrides.insert("listen".to_string(),
+                         toml::Value::String("0.0.0.0".to_string()));
+        let _env = ENV_LOCK.lock().unwrap();
+        let resolved = ResolvedTestConfig::resolve(None, overrides).unwrap();
+        assert_eq!(resolved.config.listen, "0.0.0.0");
+        assert_eq!(resolved.source("listen"), ValueSource::Override);
+        assert_eq!(resolved.source("name"), ValueSource::Default);
+    }
+
+    #[test]
+    fn config_from_env_reads_values_as_field_types() {
+        let vars = [("HAB_RESOLVED_TEST_NAME", "1.0"),
+                    ("HAB_RESOLVED_TEST_PORT", "9001"),
+                    ("HAB_RESOLVED_TEST_LISTEN", "\"true\"")];
+        let _env = ENV_LOCK.lock().unwrap();
+        for &(key, value) in vars.iter() {
+            env::set_var(key, value);
+        }
+        let config = ResolvedTestConfig::from_env();
+        for &(key, _) in vars.iter() {
+            env::remove_var(key);
+        }
+
+        let config = config.unwrap();
+        assert_eq!(config.name, "1.0");
+        assert_eq!(config.port, 9001);
+        assert_eq!(config.listen, "true");
+    }
+
+    #[test]
+    fn config_from_env_rejects_mistyped_value() {
+        let _env = ENV_LOCK.lock().unwrap();
+        env::set_var("HAB_RESOLVED_TEST_PORT", "http");
+        let config = ResolvedTestConfig::from_env();
+        env::remove_var("HAB_RESOLVED_TEST_PORT");
+        assert!(config.is_err());
+    }
+
+    #[test]
+    fn config_from_url_unsupported_scheme() {
+        match TestConfig::from_url("ftp://example.com/config.toml") {
+            Err(Error::ConfigUrlFetch(_)) => (),
+            e => panic!("Unexpected result: {:?}", e),
+        }
+    }
+
+    const BAD_CONFIG: &'static str = "name = \"test\"\nclient_secret = \"hunter2\"\nport = \n";
+
+    #[test]
+    fn config_syntax_error_terse() {
+        let err = toml::from_str::<toml::Value>(BAD_CONFIG).unwrap_err();
+        let msg = syntax_error(BAD_CONFIG, err, false).to_string();
+        assert!(msg.contains("at line 3"), "{}", msg);
+        assert!(!msg.contains("name = \"test\""), "{}", msg);
+        assert!(!msg.contains("hunter2"), "{}", msg);
+    }
+
+    #[test]
+    fn config_syntax_error_verbose() {
+        let err = toml::from_str::<toml::Value>(BAD_CONFIG).unwrap_err();
+        let msg = syntax_error(BAD_CONFIG, err, true).to_string();
+        assert!(msg.contains("at line 3"), "{}", msg);
+        assert!(msg.contains("     1 | name = \"test\""), "{}", msg);
+        assert!(msg.contains("     2 | client_secret = <redacted>"), "{}", msg);
+        assert!(msg.contains(">    3 | port ="), "{}", msg);
+        assert!(!msg.contains("hunter2"), "{}", msg);
+    }
+
+    #[test]
+    fn redact_multi_line_values_and_secret_tables() {
+        let raw = "password = \"\"\"\nhunter2\n\"\"\"\ntokens = [\n  \"abc\",\n]\n\
+                   notes = '''\n[keys]\n'''\n[keys]\nprivate = \"def\"\n[http]\nport = 80\n";
+        assert_eq!(redact(raw),
+                   vec!["password = <redacted>",
+                        "<redacted>",
+                        "<redacted>",
+                        "tokens = <redacted>",
+                        "  <redacted>",
+                        "<redacted>",
+                        "notes = '''",
+                        "[keys]",
+                        "'''",
+                        "[keys]",
+                        "private = <redacted>",
+                        "[http]",
+                        "port = 80"]);
+    }
+}
@@ -33,12 +33,19 @@ pub type Result<T> = result::Result<T, Error>;
 pub enum Error {
     /// Occurs when a `habitat_core::package::PackageArchive` is being read.
     ArchiveError(libarchive::error::ArchiveError),
+    /// Occurs when an entry of a package archive would be extracted outside of its destination.
+    ArchiveEntryUnsafePath(String),
     /// An invalid path to a keyfile was given.
     BadKeyPath(String),
     /// Error reading raw contents of configuration file.
     ConfigFileIO(io::Error),
     /// Parsing error while reading a configuration file.
     ConfigFileSyntax(toml::de::Error),
+    /// Parsing error while reading a configuration file, along with an excerpt of the file
+    /// around the error.
+    ConfigFileSyntaxSnippet(toml::de::Error, String),
+    /// A configuration document could not be fetched from a URL.
+    ConfigUrlFetch(String),
     /// Expected an array of socket addrs for configuration field value.
     ConfigInvalidArraySocketAddr(&'static str),
     /// Expected an array of tables containing string feilds and values for configuration
@@ -66,6 +73,8 @@ pub enum Error {
     ConfigInvalidTableString(&'static str),
     /// Expected a package target for configuration field value.
     ConfigInvalidTarget(&'static str),
+    /// A package target written in configuration is neither a known target nor an alias of one.
+    ConfigInvalidTargetString(String),
     /// Expected a u16 for configuration field value.
     ConfigInvalidU16(&'static str),
     /// Expected a u32 for configuration field value.
@@ -86,6 +95,8 @@ pub enum Error {
     InvalidArchitecture(String),
     /// Occurs when validating a package target for an unsupported platform.
     InvalidPlatform(String),
+    /// Occurs when a Software Bill of Materials format string cannot be successfully parsed.
+    InvalidSbomFormat(String),
     /// Occurs when a service group string cannot be successfully parsed.
     InvalidServiceGroup(String),
     /// Occurs when making lower level IO calls.
@@ -98,6 +109,8 @@ pub enum Error {
     MetaFileNotFound(package::metadata::MetaFile),
     /// When an IO error while accessing a MetaFile.
     MetaFileIO(io::Error),
+    /// Occurs when the header of a package archive does not name the key which signed it.
+    MissingSigningKeyName(String),
     /// Occurs when we can't find an outbound IP address
     NoOutboundAddr,
     /// Occurs when a suitable installed package cannot be found.
@@ -116,6 +129,8 @@ pub enum Error {
     TargetMatchError(String),
     /// Occurs when a `uname` libc call returns an error.
     UnameFailed(String),
+    /// When the contents of a file are in a text encoding which cannot be read.
+    UnsupportedEncoding(String),
     /// Occurs when a `waitpid` libc call returns an error.
     WaitpidFailed(String),
     /// Occurs when a `kill` libc call returns an error.
@@ -134,6 +149,10 @@ impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let msg = match *self {
             Error::ArchiveError(ref err) => format!("{}", err),
+            Error::ArchiveEntryUnsafePath(ref e) => {
+                format!("Package archive entry would be extracted outside of its destination, {}",
+                        e)
+            }
             Error::BadKeyPath(ref e) => {
                 format!("Invalid keypath: {}. Specify an absolute path to a file on disk.",
                         e)
@@ -143,6 +162,12 @@ impl fmt::Display for Error {
                 format!("Syntax errors while parsing TOML configuration file:\n\n{}",
                         e)
             }
+            Error::ConfigFileSyntaxSnippet(ref e, ref snippet) => {
+                format!("Syntax errors while parsing TOML configuration file:\n\n{}\n\n{}",
+                        e,
+                        snippet)
+            }
+            Error::ConfigUrlFetch(ref e) => format!("Unable to fetch configuration, {}", e),
             Error::ConfigInvalidArraySocketAddr(ref f) => {
                 format!("Invalid array value of network address pair strings config, field={}. \
                          (example: [\"127.0.0.1:8080\", \"10.0.0.4:22\"])",
@@ -201,6 +226,10 @@ impl fmt::Display for Error {
                          \"x86_64-linux\")",
                         f)
             }
+            Error::ConfigInvalidTargetString(ref value) => {
+                format!("Unknown package target in config: {:?} (example: \"x86_64-linux\")",
+                        value)
+            }
             Error::ConfigInvalidU16(ref f) => format!("Invalid u16 value in config, field={}", f),
             Error::ConfigInvalidU32(ref f) => format!("Invalid u32 value in config, field={}", f),
             Error::ConfigInvalidU64(ref f) => format!("Invalid u64 value in config, field={}", f),
@@ -221,6 +250,10 @@ impl fmt::Display for Error {
             }
             Error::InvalidArchitecture(ref e) => format!("Invalid architecture: {}.", e),
             Error::InvalidPlatform(ref e) => format!("Invalid platform: {}.", e),
+            Error::InvalidSbomFormat(ref e) => {
+                format!("Invalid SBOM format: {}. Supported formats are cyclonedx and spdx.",
+                        e)
+            }
             Error::InvalidServiceGroup(ref e) => {
                 format!("Invalid service group: {}. A valid service group string is in the form \
                          service.group (example: redis.production)",
@@ -233,6 +266,11 @@ impl fmt::Display for Error {
             }
             Error::MetaFileNotFound(ref e) => format!("Couldn't read MetaFile: {}, not found", e),
             Error::MetaFileIO(ref e) => format!("IO error while accessing MetaFile: {:?}", e),
+            Error::MissingSigningKeyName(ref e) => {
+                format!("Package archive header does not name a signing key of the form \
+                         origin-YYYYMMDDHHMMSS, {}",
+                        e)
+            }
             Error::NoOutboundAddr => format!("Failed to discover this hosts outbound IP address"),
             Error::PackageNotFound(ref pkg) => {
                 if pkg.fully_qualified() {
@@ -248,6 +286,11 @@ impl fmt::Display for Error {
             Error::StringFromUtf8Error(ref e) => format!("{}", e),
             Error::TargetMatchError(ref e) => format!("{}", e),
             Error::UnameFailed(ref e) => format!("{}", e),
+            Error::UnsupportedEncoding(ref e) => {
+                format!("Unsupported text encoding: {}. Use UTF-8 or UTF-16 with a byte order \
+                         mark.",
+                        e)
+            }
             Error::WaitpidFailed(ref e) => format!("{}", e),
             Error::SignalFailed(ref e) => {
                 format!("Failed to send a signal to the child process: {}", e)
@@ -265,9 +308,14 @@ impl error::Error for Error {
     fn description(&self) -> &str {
         match *self {
             Error::ArchiveError(ref err) => err.description(),
+            Error::ArchiveEntryUnsafePath(_) => {
+                "Package archive entry would be extracted outside of its destination"
+            }
             Error::BadKeyPath(_) => "An absolute path to a file on disk is required",
             Error::ConfigFileIO(_) => "Unable to read the raw contents of a configuration file",
-            Error::ConfigFileSyntax(_) => "Error parsing contents of configuration file",
+            Error::ConfigFileSyntax(_) |
+            Error::ConfigFileSyntaxSnippet(_, _) => "Error parsing contents of configuration file",
+            Error::ConfigUrlFetch(_) => "Unable to fetch configuration from a URL",
             Error::ConfigInvalidArraySocketAddr(_) => {
                 "Invalid array value of network address pair strings encountered while parsing a \
                  configuration file"
@@ -299,6 +347,7 @@ impl error::Error for Error {
                  configuration file"
             }
             Error::ConfigInvalidTarget(_) => "Invalid package target string value encountered while parsing a configuration file",
+            Error::ConfigInvalidTargetString(_) => "Unknown package target encountered while parsing a configuration file",
             Error::ConfigInvalidU16(_) => "Invalid u16 value encountered while parsing a configuration file",
             Error::ConfigInvalidU32(_) => "Invalid u32 value encountered while parsing a configuration file",
             Error::ConfigInvalidU64(_) => "Invalid u64 value encountered while parsing a configuration file",
@@ -309,12 +358,14 @@ impl error::Error for Error {
             Error::InvalidPackageTarget(_) => "Package targets must be in architecture-platform format (example: x86_64-linux)",
             Error::InvalidArchitecture(_) => "Unsupported target architecture supplied.",
             Error::InvalidPlatform(_) => "Unsupported target platform supplied.",
+            Error::InvalidSbomFormat(_) => "Unsupported Software Bill of Materials format supplied.",
             Error::InvalidServiceGroup(_) => "Service group strings must be in service.group format (example: redis.production)",
             Error::IO(ref err) => err.description(),
             Error::MetaFileBadBind => "Bad value parsed from BIND or BIND_OPTIONAL MetaFile",
             Error::MetaFileMalformed(_) => "MetaFile didn't contain a valid UTF-8 string",
             Error::MetaFileNotFound(_) => "Failed to read an archive's metafile",
             Error::MetaFileIO(_) => "MetaFile could not be read or written to",
+            Error::MissingSigningKeyName(_) => "Package archive header does not name a signing key",
             Error::NoOutboundAddr => "Failed to discover the outbound IP address",
             Error::PackageNotFound(_) => "Cannot find a package",
             Error::ParseIntError(_) => "Failed to parse an integer from a string!",
@@ -324,6 +375,7 @@ impl error::Error for Error {
             Error::StringFromUtf8Error(_) => "Failed to convert a string from a Vec<u8> as UTF-8",
             Error::TargetMatchError(_) => "System target does not match package target",
             Error::UnameFailed(_) => "uname failed",
+            Error::UnsupportedEncoding(_) => "Unsupported text encoding",
             Error::SignalFailed(_) => "Failed to send a signal to the child process",
             Error::WaitpidFailed(_) => "waitpid failed",
             Error::GetExitCodeProcessFailed(_) => "GetExitCodeProcess failed",
@@ -14,9 +14,13 @@
 
 extern crate base64;
 extern crate errno;
-extern crate hex;
 #[cfg(test)]
+extern crate habitat_http_test;
+extern crate hex;
+#[cfg(any(test, feature = "config_url"))]
 extern crate hyper;
+#[cfg(feature = "config_url")]
+extern crate hyper_openssl;
 #[macro_use]
 extern crate lazy_static;
 extern crate libc;
@@ -24,6 +28,7 @@ extern crate libarchive;
 #[macro_use]
 extern crate log;
 extern crate regex;
+#[macro_use]
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
@@ -14,9 +14,11 @@
 
 use libc;
 use std::ffi::OsString;
+use std::fs::File;
 use std::path::PathBuf;
+use std::os::unix::io::{FromRawFd, IntoRawFd};
 use std::os::unix::process::CommandExt;
-use std::process::{self, Command};
+use std::process::{self, Command, Stdio};
 use time::{Duration, SteadyTime};
 
 use error::{Error, Result};
@@ -38,6 +40,12 @@ pub fn is_alive(pid: u32) -> bool {
     process_group_id >= 0
 }
 
+/// Converts an open file into a `Stdio` so it can be handed to a child process as one of its
+/// standard streams.
+pub fn stdio_from_file(file: File) -> Stdio {
+    unsafe { Stdio::from_raw_fd(file.into_raw_fd()) }
+}
+
 /// send a Unix signal to a pid
 fn send_signal(pid: u32, sig: libc::c_int) -> Result<()> {
     unsafe {
@@ -101,6 +109,10 @@ impl Child {
         }
     }
 
+    pub fn raw_exit_state(&mut self) -> Result<Option<u32>> {
+        self.status().map(|status| status.status)
+    }
+
     pub fn kill(&mut self) -> Result<ShutdownMethod> {
         try!(send_signal(self.pid, libc::SIGTERM));
 
@@ -153,9 +165,93 @@ impl ExitStatusExt for HabExitStatus {
 #[cfg(test)]
 mod tests {
     use libc;
+    use std::fs::File;
+    use std::io::Read;
+    use std::path::Path;
     use std::process::Command;
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+    use tempdir::TempDir;
     use super::super::*;
 
+    fn wait_for_exit(hab_child: &mut HabChild) -> HabExitStatus {
+        let mut exit = hab_child.status().unwrap();
+        while exit.no_status() {
+            exit = hab_child.status().unwrap();
+        }
+        exit
+    }
+
+    fn read_file(path: &Path) -> String {
+        let mut content = String::new();
+        File::open(path)
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        content
+    }
+
+    #[test]
+    fn stdout_redirected_to_file() {
+        let tmpdir = TempDir::new("hab-child").unwrap();
+        let out = tmpdir.path().join("out.log");
+        let mut cmd = Command::new("echo");
+        cmd.arg("hello");
+
+        let mut hab_child = HabChildBuilder::new(cmd)
+            .stdout_to_file(&out)
+            .spawn()
+            .unwrap();
+
+        assert_eq!(wait_for_exit(&mut hab_child).code(), Some(0));
+        assert_eq!(read_file(&out), "hello\n");
+    }
+
+    #[test]
+    fn stderr_redirected_to_file() {
+        let tmpdir = TempDir::new("hab-child").unwrap();
+        let out = tmpdir.path().join("out.log");
+        let err = tmpdir.path().join("err.log");
+        let mut cmd = Command::new("/bin/bash");
+        cmd.arg("-c").arg("echo out; echo err 1>&2");
+
+        let mut hab_child = HabChildBuilder::new(cmd)
+            .stdout_to_file(&out)
+            .stderr_to_file(&err)
+            .spawn()
+            .unwrap();
+
+        assert_eq!(wait_for_exit(&mut hab_child).code(), Some(0));
+        assert_eq!(read_file(&out), "out\n");
+        assert_eq!(read_file(&err), "err\n");
+    }
+
+    #[test]
+    fn stderr_redirected_to_stdout() {
+        let tmpdir = TempDir::new("hab-child").unwrap();
+        let out = tmpdir.path().join("out.log");
+        let mut cmd = Command::new("/bin/bash");
+        cmd.arg("-c").arg("echo out; echo err 1>&2");
+
+        let mut hab_child = HabChildBuilder::new(cmd)
+            .stdout_to_file(&out)
+            .stderr_to_stdout()
+            .spawn()
+            .unwrap();
+
+        assert_eq!(wait_for_exit(&mut hab_child).code(), Some(0));
+        assert_eq!(read_file(&out), "out\nerr\n");
+    }
+
+    #[test]
+    fn stderr_to_stdout_requires_stdout_file() {
+        let mut cmd = Command::new("echo");
+        cmd.arg("hello");
+
+        assert!(HabChildBuilder::new(cmd).stderr_to_stdout().spawn().is_err());
+    }
+
     #[test]
     fn running_process_returns_no_exit_status() {
         let mut cmd = Command::new("/bin/bash");
@@ -232,4 +328,57 @@ mod tests {
 
         assert_eq!(exit.code(), Some(5))
     }
+
+    fn sleeper(limit: &ChildLimit) -> LimitedChild {
+        let mut cmd = Command::new("/bin/bash");
+        cmd.arg("-c").arg("sleep 60");
+        limit.spawn(&mut HabChildBuilder::new(cmd)).unwrap()
+    }
+
+    #[test]
+    fn child_limit_blocks_spawns_until_a_child_exits() {
+        let limit = ChildLimit::new(2);
+        let mut first = sleeper(&limit);
+        let mut second = sleeper(&limit);
+
+        let (tx, rx) = mpsc::channel();
+        let third_limit = limit.clone();
+        let handle = thread::spawn(move || {
+                                       let third = sleeper(&third_limit);
+                                       tx.send(()).unwrap();
+                                       third
+                                   });
+        assert!(rx.recv_timeout(Duration::from_millis(500)).is_err());
+
+        first.kill().unwrap();
+        assert!(rx.recv_timeout(Duration::from_secs(10)).is_ok());
+
+        let mut third = handle.join().unwrap();
+        second.kill().unwrap();
+        third.kill().unwrap();
+    }
+
+    #[test]
+    fn child_limit_frees_slot_when_child_exits() {
+        let limit = ChildLimit::new(1);
+        let mut cmd = Command::new("/bin/bash");
+        cmd.arg("-c").arg("exit 0");
+        let mut child = limit.spawn(&mut HabChildBuilder::new(cmd)).unwrap();
+        while child.status().unwrap().no_status() {}
+
+        let _permit = limit.acquire();
+    }
+
+    #[test]
+    fn raw_exit_state_is_wait_status() {
+        let mut cmd = Command::new("/bin/bash");
+        cmd.arg("-c").arg("exit 5");
+        let mut child = cmd.spawn().unwrap();
+
+        let mut hab_child = HabChild::from(&mut child).unwrap();
+        let exit = wait_for_exit(&mut hab_child);
+
+        assert_eq!(hab_child.raw_exit_state().unwrap(), Some(5 << 8));
+        assert_eq!(exit.code(), Some(5))
+    }
 }
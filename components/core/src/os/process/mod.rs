@@ -13,9 +13,12 @@
 // limitations under the License.
 
 use std::fmt;
-use std::process::Child;
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+use std::sync::{Arc, Condvar, Mutex};
 
-use error::Result;
+use error::{Error, Result};
 
 #[allow(unused_variables)]
 #[cfg(windows)]
@@ -61,15 +64,199 @@ impl HabChild {
         self.inner.id()
     }
 
+    /// Returns the child's exit status, which has no status while the child is still running.
     pub fn status(&mut self) -> Result<HabExitStatus> {
         self.inner.status()
     }
 
+    /// Returns the child's exit state exactly as the operating system reports it. On Windows this
+    /// is the result of `GetExitCodeProcess`, which is `259` (`STILL_ACTIVE`) both while the child
+    /// is running and when it exited with that code. On Linux it is the raw wait status, or `None`
+    /// while the child is running.
+    pub fn raw_exit_state(&mut self) -> Result<Option<u32>> {
+        self.inner.raw_exit_state()
+    }
+
     pub fn kill(&mut self) -> Result<ShutdownMethod> {
         self.inner.kill()
     }
 }
 
+/// Spawns a `HabChild` from a `Command`, optionally redirecting the child's output streams to
+/// files on disk rather than piping them through the parent process.
+pub struct HabChildBuilder {
+    command: Command,
+    stdout: Option<PathBuf>,
+    stderr: Option<PathBuf>,
+    stderr_to_stdout: bool,
+}
+
+impl HabChildBuilder {
+    pub fn new(command: Command) -> Self {
+        HabChildBuilder {
+            command: command,
+            stdout: None,
+            stderr: None,
+            stderr_to_stdout: false,
+        }
+    }
+
+    /// Write the child's standard output to the file at the given path, creating it if needed.
+    pub fn stdout_to_file(&mut self, path: &Path) -> &mut Self {
+        self.stdout = Some(path.to_path_buf());
+        self
+    }
+
+    /// Write the child's standard error to the file at the given path, creating it if needed.
+    pub fn stderr_to_file(&mut self, path: &Path) -> &mut Self {
+        self.stderr = Some(path.to_path_buf());
+        self.stderr_to_stdout = false;
+        self
+    }
+
+    /// Write the child's standard error to the same file as its standard output.
+    pub fn stderr_to_stdout(&mut self) -> &mut Self {
+        self.stderr = None;
+        self.stderr_to_stdout = true;
+        self
+    }
+
+    /// Spawn the command as a child process with the configured redirections.
+    ///
+    /// # Failures
+    ///
+    /// * An output file cannot be opened for writing
+    /// * Standard error is redirected to standard output which was not redirected to a file
+    /// * The child process cannot be spawned
+    pub fn spawn(&mut self) -> Result<HabChild> {
+        let stdout = match self.stdout {
+            Some(ref path) => Some(try!(open_output(path))),
+            None => None,
+        };
+        if self.stderr_to_stdout {
+            match stdout {
+                Some(ref file) => {
+                    self.command.stderr(imp::stdio_from_file(try!(file.try_clone())));
+                }
+                None => {
+                    return Err(Error::GetHabChildFailed("Unable to redirect stderr to stdout, \
+                                                         stdout is not redirected to a file"
+                                                                .to_string()))
+                }
+            }
+        } else if let Some(ref path) = self.stderr {
+            self.command.stderr(imp::stdio_from_file(try!(open_output(path))));
+        }
+        if let Some(file) = stdout {
+            self.command.stdout(imp::stdio_from_file(file));
+        }
+        let mut child = try!(self.command.spawn());
+        HabChild::from(&mut child)
+    }
+}
+
+/// Caps the number of child processes which may run at once. Spawning while the limit is reached
+/// blocks until one of the running children exits. Clones share the same limit.
+#[derive(Clone)]
+pub struct ChildLimit {
+    max: usize,
+    running: Arc<(Mutex<usize>, Condvar)>,
+}
+
+impl ChildLimit {
+    /// Create a new limit allowing `max` children to run at once.
+    ///
+    /// # Panics
+    ///
+    /// * `max` is zero
+    pub fn new(max: usize) -> Self {
+        assert!(max > 0, "child process limit must be at least 1");
+        ChildLimit {
+            max: max,
+            running: Arc::new((Mutex::new(0), Condvar::new())),
+        }
+    }
+
+    /// Block until a slot is free and claim it. The slot is released when the returned permit is
+    /// dropped, which lets children spawned by other means, such as `std::process::Command`, count
+    /// against the limit.
+    pub fn acquire(&self) -> ChildPermit {
+        let &(ref lock, ref cvar) = &*self.running;
+        let mut running = lock.lock().expect("child limit lock is poisoned");
+        while *running >= self.max {
+            running = cvar.wait(running).expect("child limit lock is poisoned");
+        }
+        *running += 1;
+        ChildPermit { limit: self.clone() }
+    }
+
+    /// Spawn a child from the given builder once a slot is free. The slot is held until the
+    /// child is seen to exit or the returned `LimitedChild` is dropped.
+    pub fn spawn(&self, builder: &mut HabChildBuilder) -> Result<LimitedChild> {
+        let permit = self.acquire();
+        let child = try!(builder.spawn());
+        Ok(LimitedChild {
+               child: child,
+               permit: Some(permit),
+           })
+    }
+
+    fn release(&self) {
+        let &(ref lock, ref cvar) = &*self.running;
+        let mut running = lock.lock().expect("child limit lock is poisoned");
+        *running -= 1;
+        cvar.notify_one();
+    }
+}
+
+/// A claimed slot of a `ChildLimit`, released when dropped.
+pub struct ChildPermit {
+    limit: ChildLimit,
+}
+
+impl Drop for ChildPermit {
+    fn drop(&mut self) {
+        self.limit.release();
+    }
+}
+
+/// A `HabChild` spawned through a `ChildLimit`, holding one of its slots while it runs.
+pub struct LimitedChild {
+    child: HabChild,
+    permit: Option<ChildPermit>,
+}
+
+impl LimitedChild {
+    pub fn id(&self) -> u32 {
+        self.child.id()
+    }
+
+    /// Returns the child's exit status, freeing its slot once the child has exited.
+    pub fn status(&mut self) -> Result<HabExitStatus> {
+        let status = try!(self.child.status());
+        if !status.no_status() {
+            self.permit.take();
+        }
+        Ok(status)
+    }
+
+    /// Terminates the child and frees its slot.
+    pub fn kill(&mut self) -> Result<ShutdownMethod> {
+        let method = try!(self.child.kill());
+        self.permit.take();
+        Ok(method)
+    }
+}
+
+fn open_output(path: &Path) -> Result<File> {
+    let file = try!(OpenOptions::new()
+                        .create(true)
+                        .write(true)
+                        .truncate(true)
+                        .open(path));
+    Ok(file)
+}
+
 impl fmt::Debug for HabChild {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "pid: {}", self.id())
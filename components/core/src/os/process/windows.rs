@@ -13,8 +13,10 @@
 // limitations under the License.
 
 use std::ffi::OsString;
+use std::fs::File;
+use std::os::windows::io::{FromRawHandle, IntoRawHandle};
 use std::path::PathBuf;
-use std::process::{self, Command};
+use std::process::{self, Command, Stdio};
 use std::ptr;
 use std::io;
 use time::{Duration, SteadyTime};
@@ -37,15 +39,22 @@ pub fn current_pid() -> u32 {
     unsafe { kernel32::GetCurrentProcessId() as u32 }
 }
 
+/// Converts an open file into a `Stdio` so it can be handed to a child process as one of its
+/// standard streams. The handle ends up as `hStdOutput` or `hStdError` in the child's
+/// `STARTUPINFOW`.
+pub fn stdio_from_file(file: File) -> Stdio {
+    unsafe { Stdio::from_raw_handle(file.into_raw_handle()) }
+}
+
 /// Determines if a process is running with the given process identifier.
 pub fn is_alive(pid: u32) -> bool {
     match handle_from_pid(pid) {
         Some(handle) => {
-            let exit_status = exit_status(handle).expect("Failed to get exit status");
+            let alive = is_running(handle);
             unsafe {
                 let _ = kernel32::CloseHandle(handle);
             }
-            exit_status == STILL_ACTIVE
+            alive
         }
         None => false,
     }
@@ -70,7 +79,8 @@ fn become_child_command(command: PathBuf, args: Vec<OsString>) -> Result<()> {
 fn handle_from_pid(pid: u32) -> Option<winapi::HANDLE> {
     unsafe {
         let proc_handle = kernel32::OpenProcess(winapi::PROCESS_QUERY_LIMITED_INFORMATION |
-                                                winapi::PROCESS_TERMINATE,
+                                                winapi::PROCESS_TERMINATE |
+                                                winapi::SYNCHRONIZE,
                                                 winapi::FALSE,
                                                 pid as winapi::DWORD);
 
@@ -84,6 +94,11 @@ fn handle_from_pid(pid: u32) -> Option<winapi::HANDLE> {
     }
 }
 
+// The process handle becomes signaled once the process exits.
+fn is_running(handle: winapi::HANDLE) -> bool {
+    unsafe { kernel32::WaitForSingleObject(handle, 0) == winapi::WAIT_TIMEOUT }
+}
+
 fn exit_status(handle: winapi::HANDLE) -> Result<u32> {
     let mut exit_status: u32 = 0;
 
@@ -149,15 +164,25 @@ impl Child {
             return Ok(HabExitStatus { status: Some(self.last_status.unwrap()) });
         }
 
-        let exit_status = exit_status(self.handle.unwrap())?;
+        let handle = self.handle.unwrap();
+        let exit_status = exit_status(handle)?;
 
-        if exit_status == STILL_ACTIVE {
+        // A process may exit with the same code that marks a running process, so only a process
+        // whose handle is not yet signaled is considered to be running.
+        if exit_status == STILL_ACTIVE && is_running(handle) {
             return Ok(HabExitStatus { status: None });
         };
 
         Ok(HabExitStatus { status: Some(exit_status) })
     }
 
+    pub fn raw_exit_state(&mut self) -> Result<Option<u32>> {
+        if self.last_status.is_some() {
+            return Ok(self.last_status);
+        }
+        exit_status(self.handle.unwrap()).map(Some)
+    }
+
     pub fn kill(&mut self) -> Result<ShutdownMethod> {
         if self.last_status.is_some() {
             return Ok(ShutdownMethod::AlreadyExited);
@@ -310,4 +335,36 @@ mod tests {
 
         assert_eq!(hab_child.status().unwrap().code(), Some(5000))
     }
+
+    #[test]
+    fn running_process_has_still_active_raw_exit_state() {
+        let mut cmd = Command::new("C:\\Windows\\System32\\WindowsPowerShell\\v1.0\\powershell.\
+                                    exe");
+        cmd.arg("-noprofile")
+            .arg("-command")
+            .arg("while($true) { Start-Sleep 1 }");
+        let mut child = cmd.spawn().unwrap();
+
+        let mut hab_child = HabChild::from(&mut child).unwrap();
+
+        assert_eq!(hab_child.raw_exit_state().unwrap(), Some(259));
+        assert!(hab_child.status().unwrap().no_status());
+        let _ = child.kill();
+    }
+
+    #[test]
+    fn process_that_exits_with_still_active_code_has_exit_status() {
+        let mut cmd = Command::new("C:\\Windows\\System32\\WindowsPowerShell\\v1.0\\powershell.\
+                                    exe");
+        cmd.arg("-noprofile").arg("-command").arg("exit 259");
+        let mut child = cmd.spawn().unwrap();
+
+        let mut hab_child = HabChild::from(&mut child).unwrap();
+        let _ = child.wait();
+
+        assert_eq!(hab_child.raw_exit_state().unwrap(), Some(259));
+        let status = hab_child.status().unwrap();
+        assert!(!status.no_status());
+        assert_eq!(status.code(), Some(259))
+    }
 }
@@ -12,9 +12,9 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::error;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use std::result;
 use std::str::{self, FromStr};
 
@@ -25,12 +25,17 @@ use regex::Regex;
 
 use super::{Identifiable, PackageIdent, PackageTarget};
 use super::metadata::MetaFile;
+use super::sbom::{Sbom, SbomComponent, SbomFormat, SbomScope};
 use error::{Error, Result};
 use crypto::{artifact, hash};
+use crypto::keys::{is_valid_origin_name, parse_name_with_rev};
 
 lazy_static! {
     static ref METAFILE_REGXS: HashMap<MetaFile, Regex> = {
         let mut map = HashMap::new();
+        map.insert(MetaFile::BuildDeps,
+                   Regex::new(&format!(r"^/?hab/pkgs/([^/]+)/([^/]+)/([^/]+)/([^/]+)/{}$",
+                              MetaFile::BuildDeps)).unwrap());
         map.insert(MetaFile::CFlags,
                    Regex::new(&format!(r"^/?hab/pkgs/([^/]+)/([^/]+)/([^/]+)/([^/]+)/{}$",
                               MetaFile::CFlags)).unwrap());
@@ -55,6 +60,9 @@ lazy_static! {
         map.insert(MetaFile::LdFlags,
                    Regex::new(&format!(r"^/?hab/pkgs/([^/]+)/([^/]+)/([^/]+)/([^/]+)/{}$",
                               MetaFile::LdFlags)).unwrap());
+        map.insert(MetaFile::License,
+                   Regex::new(&format!(r"^/?hab/pkgs/([^/]+)/([^/]+)/([^/]+)/([^/]+)/{}$",
+                              MetaFile::License)).unwrap());
         map.insert(MetaFile::Manifest,
                    Regex::new(&format!(r"^/?hab/pkgs/([^/]+)/([^/]+)/([^/]+)/([^/]+)/{}$",
                               MetaFile::Manifest)).unwrap());
@@ -131,6 +139,17 @@ impl PackageArchive {
         self.read_deps(MetaFile::TDeps)
     }
 
+    /// Returns a list of package identifiers representing the build dependencies for this
+    /// archive.
+    ///
+    /// # Failures
+    ///
+    /// * If the archive cannot be read
+    /// * If the archive cannot be verified
+    pub fn build_deps(&mut self) -> Result<Vec<PackageIdent>> {
+        self.read_deps(MetaFile::BuildDeps)
+    }
+
     pub fn exposes(&mut self) -> Result<Vec<u16>> {
         match self.read_metadata(MetaFile::Exposes) {
             Ok(Some(data)) => {
@@ -166,6 +185,16 @@ impl PackageArchive {
         }
     }
 
+    /// Returns the licenses declared by the package's plan. An empty list is returned if the
+    /// archive does not contain a `LICENSE` metafile.
+    pub fn licenses(&mut self) -> Result<Vec<String>> {
+        match self.read_metadata(MetaFile::License) {
+            Ok(Some(data)) => Ok(data.split_whitespace().map(|l| l.to_string()).collect()),
+            Ok(None) => Ok(vec![]),
+            Err(e) => Err(e),
+        }
+    }
+
     pub fn manifest(&mut self) -> Result<String> {
         match self.read_metadata(MetaFile::Manifest) {
             Ok(None) => Err(Error::MetaFileNotFound(MetaFile::Manifest)),
@@ -189,6 +218,39 @@ impl PackageArchive {
         }
     }
 
+    /// Generate a Software Bill of Materials for the archive listing the package itself, its
+    /// direct and transitive runtime dependencies and its build dependencies. A package that is
+    /// both a runtime and a build dependency is listed once, as a runtime dependency.
+    ///
+    /// The returned `Sbom` renders as CycloneDX; set its `format` to render another format.
+    ///
+    /// # Failures
+    ///
+    /// * If the archive cannot be read
+    /// * If the archive's `IDENT` metafile is missing
+    /// * If a dependency metafile contains an invalid package identifier
+    pub fn generate_sbom(&mut self) -> Result<Sbom> {
+        let mut package = SbomComponent::new(self.ident()?, SbomScope::Package);
+        package.licenses = self.licenses()?;
+        let mut components = vec![];
+        let mut seen = HashSet::new();
+        for dep in self.tdeps()? {
+            if seen.insert(dep.clone()) {
+                components.push(SbomComponent::new(dep, SbomScope::Runtime));
+            }
+        }
+        for dep in self.build_deps()? {
+            if seen.insert(dep.clone()) {
+                components.push(SbomComponent::new(dep, SbomScope::Build));
+            }
+        }
+        Ok(Sbom {
+               format: SbomFormat::CycloneDX,
+               package: package,
+               components: components,
+           })
+    }
+
     /// A plain string representation of the archive's file name.
     pub fn file_name(&self) -> String {
         self.path
@@ -198,6 +260,31 @@ impl PackageArchive {
             .into_owned()
     }
 
+    /// Returns the name, with revision, of the origin key which signed the package as recorded in
+    /// the archive's header, such as `core-20160810182414`.
+    ///
+    /// # Failures
+    ///
+    /// * The archive cannot be read
+    /// * The header does not name a key of the form `origin-YYYYMMDDHHMMSS`
+    pub fn signing_key_name(&self) -> Result<String> {
+        let name_with_rev = match artifact::artifact_signer(&self.path) {
+            Ok(name_with_rev) => name_with_rev,
+            Err(Error::CryptoError(e)) => return Err(Error::MissingSigningKeyName(e)),
+            Err(e) => return Err(e),
+        };
+        let (origin, _) = match parse_name_with_rev(&name_with_rev) {
+            Ok(parts) => parts,
+            Err(Error::CryptoError(e)) => return Err(Error::MissingSigningKeyName(e)),
+            Err(e) => return Err(e),
+        };
+        if !is_valid_origin_name(&origin) {
+            return Err(Error::MissingSigningKeyName(format!("invalid origin in key name {}",
+                                                            name_with_rev)));
+        }
+        Ok(name_with_rev)
+    }
+
     /// Given a package name and a path to a file as an `&str`, verify
     /// the files signature.
     ///
@@ -233,6 +320,59 @@ impl PackageArchive {
         Ok(())
     }
 
+    /// Extract the contents of the package archive into the given directory, preserving
+    /// permissions and symlinks. Entries are placed relative to `dest`, so a package's files end
+    /// up under `dest/hab/pkgs/`.
+    ///
+    /// # Failures
+    ///
+    /// * If the archive cannot be read
+    /// * If an entry's path contains a `..` segment, which could place it outside of `dest`
+    /// * If the contents cannot be written to `dest`
+    pub fn extract_to(&self, dest: &Path) -> Result<()> {
+        try!(self.check_entry_paths());
+        let tar_reader = try!(artifact::get_archive_reader(&self.path));
+        let mut builder = reader::Builder::new();
+        try!(builder.support_format(ReadFormat::Gnutar));
+        try!(builder.support_filter(ReadFilter::Xz));
+        let mut reader = try!(builder.open_stream(tar_reader));
+        let writer = writer::Disk::new();
+        let mut extract_options = ExtractOptions::new();
+        extract_options.add(ExtractOption::Time);
+        extract_options.add(ExtractOption::Permissions);
+        // Have libarchive refuse unsafe entries too, including any written through a symlink
+        // extracted earlier in the same archive, which `check_entry_paths` can't see.
+        extract_options.add(ExtractOption::SecureNoAbsolutePaths);
+        extract_options.add(ExtractOption::SecureNoDotDot);
+        extract_options.add(ExtractOption::SecureSymlinks);
+        try!(writer.set_options(&extract_options));
+        try!(writer.set_standard_lookup());
+        try!(writer.write(&mut reader, Some(dest.to_string_lossy().as_ref())));
+        try!(writer.close());
+        Ok(())
+    }
+
+    // Reads every entry header of the archive, failing on the first entry whose path is absolute
+    // or contains a parent directory segment.
+    fn check_entry_paths(&self) -> Result<()> {
+        let tar_reader = try!(artifact::get_archive_reader(&self.path));
+        let mut builder = reader::Builder::new();
+        try!(builder.support_format(ReadFormat::Gnutar));
+        try!(builder.support_filter(ReadFilter::Xz));
+        let mut reader = try!(builder.open_stream(tar_reader));
+        while let Some(entry) = reader.next_header() {
+            let path = entry.pathname();
+            let unsafe_path = Path::new(path).components().any(|c| match c {
+                Component::ParentDir | Component::RootDir | Component::Prefix(_) => true,
+                Component::CurDir | Component::Normal(_) => false,
+            });
+            if unsafe_path {
+                return Err(Error::ArchiveEntryUnsafePath(path.to_string()));
+            }
+        }
+        Ok(())
+    }
+
     fn read_deps(&mut self, file: MetaFile) -> Result<Vec<PackageIdent>> {
         let mut deps: Vec<PackageIdent> = vec![];
         match self.read_metadata(file) {
@@ -326,7 +466,10 @@ pub trait FromArchive: Sized {
 
 #[cfg(test)]
 mod test {
-    use std::path::PathBuf;
+    use std::fs::File;
+    use std::io::Write;
+    use std::path::{Path, PathBuf};
+    use tempdir::TempDir;
     use os::system::{Architecture, Platform};
     use super::*;
 
@@ -349,6 +492,67 @@ mod test {
         root().join("fixtures")
     }
 
+    #[test]
+    fn extract_to_directory() {
+        let dest = TempDir::new("extract-to").unwrap();
+        let hart = PackageArchive::new(fixtures()
+            .join("happyhumans-possums-8.1.4-20160427165340-x86_64-linux.hart"));
+        hart.extract_to(dest.path()).unwrap();
+        let ident = dest.path()
+            .join("hab/pkgs/happyhumans/possums/8.1.4/20160427165340/IDENT");
+        assert!(ident.is_file());
+    }
+
+    #[test]
+    fn extract_to_rejects_path_traversal() {
+        let dest = TempDir::new("extract-to").unwrap();
+        let hart = PackageArchive::new(fixtures()
+            .join("happyhumans-traversal-0.1.0-20170101000000-x86_64-linux.hart"));
+        match hart.extract_to(&dest.path().join("root")) {
+            Err(Error::ArchiveEntryUnsafePath(_)) => (),
+            e => panic!("Unexpected result: {:?}", e),
+        }
+        assert!(!dest.path().join("root").exists());
+        assert!(!dest.path().join("escaped").exists());
+    }
+
+    #[test]
+    fn extract_to_rejects_absolute_paths() {
+        let dest = TempDir::new("extract-to").unwrap();
+        let hart = PackageArchive::new(fixtures()
+            .join("happyhumans-absolute-0.1.0-20170101000000-x86_64-linux.hart"));
+        match hart.extract_to(&dest.path().join("root")) {
+            Err(Error::ArchiveEntryUnsafePath(_)) => (),
+            e => panic!("Unexpected result: {:?}", e),
+        }
+        assert!(!dest.path().join("root").exists());
+        assert!(!Path::new("/tmp/happyhumans-absolute-escaped").exists());
+    }
+
+    #[test]
+    fn reading_signing_key_name() {
+        let hart = PackageArchive::new(fixtures()
+            .join("happyhumans-possums-8.1.4-20160427165340-x86_64-linux.hart"));
+        assert_eq!(hart.signing_key_name().unwrap(),
+                   "happyhumans-20160424223347");
+    }
+
+    #[test]
+    fn reading_malformed_signing_key_name() {
+        let dir = TempDir::new("signing-key-name").unwrap();
+        for header in &["HART-1\nhappyhumans\nBLAKE2b\n", "HART-1\n", "HART-1\n-20160424223347\n"] {
+            let path = dir.path().join("bad.hart");
+            File::create(&path)
+                .unwrap()
+                .write_all(header.as_bytes())
+                .unwrap();
+            match PackageArchive::new(path).signing_key_name() {
+                Err(Error::MissingSigningKeyName(_)) => (),
+                e => panic!("Unexpected result for {:?}: {:?}", header, e),
+            }
+        }
+    }
+
     #[test]
     fn reading_artifact_deps() {
         let mut hart = PackageArchive::new(fixtures()
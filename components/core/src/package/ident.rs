@@ -14,6 +14,7 @@
 
 use std::cmp::{Ordering, PartialOrd};
 use std::fmt;
+use std::path::{Path, PathBuf};
 use std::result;
 use std::str::FromStr;
 
@@ -86,18 +87,65 @@ impl PackageIdent {
 
     pub fn archive_name(&self) -> Option<String> {
         if self.fully_qualified() {
-            let default_target = PackageTarget::default();
-            Some(format!("{}-{}-{}-{}-{}-{}.hart",
-                         self.origin,
-                         self.name,
-                         self.version.as_ref().unwrap(),
-                         self.release.as_ref().unwrap(),
-                         default_target.architecture,
-                         default_target.platform))
+            let path = self.to_path(&PackageTarget::default());
+            Some(path.to_string_lossy().into_owned())
         } else {
             None
         }
     }
+
+    /// Parses a package identifier from the file name of a package archive, such as
+    /// `core-openssl-1.0.2j-20161214010111-x86_64-linux.hart`.
+    ///
+    /// Reading from the end of the name, the segments are the platform, architecture, release
+    /// and version. The origin is taken to be the first segment and everything between it and
+    /// the version is the name, so package names may contain dashes but origins may not.
+    ///
+    /// # Failures
+    ///
+    /// * The file name doesn't end in `.hart`
+    /// * The file name has too few segments or names an unknown target
+    pub fn from_path(path: &Path) -> Result<PackageIdent> {
+        let invalid = || Error::InvalidPackageIdent(path.to_string_lossy().into_owned());
+        let file_name = match path.file_name().and_then(|f| f.to_str()) {
+            Some(file_name) if file_name.ends_with(".hart") => file_name,
+            _ => return Err(invalid()),
+        };
+        let mut items: Vec<&str> = file_name[..file_name.len() - ".hart".len()]
+            .split("-")
+            .collect();
+        if items.len() < 6 || items.iter().any(|i| i.is_empty()) {
+            return Err(invalid());
+        }
+        let platform = items.pop().unwrap();
+        let architecture = items.pop().unwrap();
+        if PackageTarget::from_str(&format!("{}-{}", architecture, platform)).is_err() {
+            return Err(invalid());
+        }
+        let release = items.pop().unwrap();
+        let version = items.pop().unwrap();
+        let origin = items.remove(0);
+        let name = items.join("-");
+        Ok(PackageIdent::new(origin.to_string(),
+                             name,
+                             Some(version.to_string()),
+                             Some(release.to_string())))
+    }
+
+    /// Returns the file name of the package archive built from this identifier for the given
+    /// target. This is the inverse of `from_path()`.
+    ///
+    /// # Panics
+    ///
+    /// * The identifier isn't fully qualified
+    pub fn to_path(&self, target: &PackageTarget) -> PathBuf {
+        PathBuf::from(format!("{}-{}-{}-{}-{}.hart",
+                              self.origin,
+                              self.name,
+                              self.version.as_ref().expect("package ident has no version"),
+                              self.release.as_ref().expect("package ident has no release"),
+                              target))
+    }
 }
 
 impl Identifiable for PackageIdent {
@@ -339,6 +387,18 @@ mod tests {
     use super::split_version;
     use std::cmp::Ordering;
     use std::cmp::PartialOrd;
+    use std::path::{Path, PathBuf};
+    use std::str::FromStr;
+
+    const HART_NAMES: &'static [(&'static str, &'static str)] =
+        &[("core-openssl-1.0.2j-20161214010111-x86_64-linux.hart",
+           "core/openssl/1.0.2j/20161214010111"),
+          ("core-hab-studio-0.22.1-20170425190224-x86_64-linux.hart",
+           "core/hab-studio/0.22.1/20170425190224"),
+          ("core-cacerts-2017.01.17-20170209064045-x86_64-windows.hart",
+           "core/cacerts/2017.01.17/20170209064045"),
+          ("happyhumans-possums-8.1.4-20160427165340-x86_64-linux.hart",
+           "happyhumans/possums/8.1.4/20160427165340")];
 
     #[test]
     fn package_ident_partial_eq() {
@@ -496,4 +556,42 @@ mod tests {
         assert!(!invalid1.valid());
         assert!(!invalid2.valid());
     }
+
+    #[test]
+    fn package_ident_from_path() {
+        for &(file_name, ident) in HART_NAMES {
+            let path = PathBuf::from("/hab/cache/artifacts").join(file_name);
+            assert_eq!(PackageIdent::from_path(&path).unwrap(),
+                       PackageIdent::from_str(ident).unwrap());
+        }
+    }
+
+    #[test]
+    fn package_ident_to_path() {
+        for &(file_name, ident) in HART_NAMES {
+            let target = PackageTarget::from_str(if file_name.contains("windows") {
+                                                     "x86_64-windows"
+                                                 } else {
+                                                     "x86_64-linux"
+                                                 })
+                    .unwrap();
+            assert_eq!(PackageIdent::from_str(ident).unwrap().to_path(&target),
+                       PathBuf::from(file_name));
+        }
+    }
+
+    #[test]
+    fn package_ident_from_invalid_path() {
+        for file_name in &["core-openssl-1.0.2j-20161214010111-x86_64-linux.tar.xz",
+                           "core-openssl-20161214010111-x86_64-linux.hart",
+                           "core-openssl-1.0.2j-20161214010111-sparc-solaris.hart",
+                           "core--openssl-1.0.2j-20161214010111-x86_64-linux.hart",
+                           ".hart",
+                           ""] {
+            match PackageIdent::from_path(Path::new(file_name)) {
+                Err(Error::InvalidPackageIdent(_)) => (),
+                e => panic!("Unexpected result for {}: {:?}", file_name, e),
+            }
+        }
+    }
 }
@@ -47,6 +47,7 @@ impl FromStr for Bind {
 pub enum MetaFile {
     Binds,
     BindsOptional,
+    BuildDeps,
     CFlags,
     Config,
     Deps,
@@ -58,6 +59,7 @@ pub enum MetaFile {
     Ident,
     LdRunPath,
     LdFlags,
+    License,
     Manifest,
     Path,
     SvcUser,
@@ -70,6 +72,7 @@ impl fmt::Display for MetaFile {
         let id = match *self {
             MetaFile::Binds => "BINDS",
             MetaFile::BindsOptional => "BINDS_OPTIONAL",
+            MetaFile::BuildDeps => "BUILD_DEPS",
             MetaFile::CFlags => "CFLAGS",
             MetaFile::Config => "default.toml",
             MetaFile::Deps => "DEPS",
@@ -81,6 +84,7 @@ impl fmt::Display for MetaFile {
             MetaFile::Ident => "IDENT",
             MetaFile::LdRunPath => "LD_RUN_PATH",
             MetaFile::LdFlags => "LDFLAGS",
+            MetaFile::License => "LICENSE",
             MetaFile::Manifest => "MANIFEST",
             MetaFile::Path => "PATH",
             MetaFile::SvcUser => "SVC_USER",
@@ -17,12 +17,14 @@ pub mod ident;
 pub mod install;
 pub mod metadata;
 pub mod plan;
+pub mod sbom;
 pub mod target;
 
 pub use self::archive::{FromArchive, PackageArchive};
 pub use self::ident::{Identifiable, PackageIdent};
 pub use self::install::PackageInstall;
-pub use self::plan::Plan;
+pub use self::plan::{Plan, PlanError};
+pub use self::sbom::{Sbom, SbomComponent, SbomFormat, SbomScope};
 pub use self::target::{Target, PackageTarget};
 
 #[cfg(test)]
@@ -12,13 +12,50 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::io::BufRead;
+use std::fmt;
+use std::str::{self, FromStr};
 
+use regex::Regex;
+
+use crypto::keys::is_valid_origin_name;
 use error::{Error, Result};
+use package::PackageIdent;
+
+lazy_static! {
+    static ref NAME_RE: Regex = Regex::new(r"\A[a-z0-9_-]+\z").unwrap();
+    // Dotted numeric versions with an optional suffix, which covers semantic versions such as
+    // `1.11.10` or `2.0.0-rc1`, looser ones such as `1.0.2k`, and dates such as `2017-01-17`.
+    static ref VERSION_RE: Regex =
+        Regex::new(r"\A\d+([.-]\d+)*([-+.]?[0-9A-Za-z][0-9A-Za-z.+-]*)?\z").unwrap();
+}
 
 pub struct Plan {
     pub name: String,
     pub version: String,
+    pub origin: Option<String>,
+    pub deps: Vec<String>,
+}
+
+/// A problem with one field of a plan, as found by `Plan::validate`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct PlanError {
+    pub field: String,
+    pub message: String,
+}
+
+impl PlanError {
+    fn new<T: Into<String>>(field: &str, message: T) -> Self {
+        PlanError {
+            field: field.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for PlanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
 }
 
 impl Plan {
@@ -26,26 +63,309 @@ impl Plan {
         Plan {
             name: name,
             version: version,
+            origin: None,
+            deps: Vec::new(),
         }
     }
 
+    /// Reads a plan from the contents of a plan file. The contents may be UTF-8, with or without
+    /// a byte order mark, or UTF-16 with a byte order mark as written by some Windows editors.
     pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let content = try!(decode(bytes));
         let mut name: Option<String> = None;
         let mut version: Option<String> = None;
-        for line in bytes.lines() {
-            if let Ok(line) = line {
-                let parts: Vec<&str> = line.splitn(2, "=").collect();
-                match parts[0] {
-                    "pkg_name" => name = Some(parts[1].to_string()),
-                    "pkg_version" => version = Some(parts[1].to_string()),
-                    _ => (),
+        let mut origin: Option<String> = None;
+        let mut deps: Vec<String> = Vec::new();
+        // Set while reading a `pkg_deps` array which continues over several lines.
+        let mut in_deps = false;
+        for line in content.lines() {
+            if in_deps {
+                in_deps = !push_deps(line, &mut deps);
+                continue;
+            }
+            let parts: Vec<&str> = line.splitn(2, "=").collect();
+            match parts[0] {
+                "pkg_name" => name = Some(unquote(try!(assigned_value(&parts)))),
+                "pkg_version" => version = Some(unquote(try!(assigned_value(&parts)))),
+                "pkg_origin" => origin = Some(unquote(try!(assigned_value(&parts)))),
+                "pkg_deps" => {
+                    let value = try!(assigned_value(&parts)).trim();
+                    if value.starts_with("(") {
+                        in_deps = !push_deps(&value[1..], &mut deps);
+                    }
                 }
+                _ => (),
             }
         }
         if name.is_none() || version.is_none() {
             return Err(Error::PlanMalformed);
         }
-        let plan = Plan::new(name.unwrap(), version.unwrap());
+        let mut plan = Plan::new(name.unwrap(), version.unwrap());
+        plan.origin = origin;
+        plan.deps = deps;
         Ok(plan)
     }
+
+    /// Checks the fields of the plan and returns every problem found, or an empty list if the
+    /// plan is valid. Values which expand shell variables, such as `pkg_version=${major}.0`, are
+    /// only known once the plan is built and aren't checked.
+    pub fn validate(&self) -> Vec<PlanError> {
+        let mut errors = Vec::new();
+        if !is_expanded(&self.name) && !NAME_RE.is_match(&self.name) {
+            errors.push(PlanError::new("pkg_name",
+                                       format!("'{}' may only contain lowercase letters, \
+                                                numbers, '_' and '-'",
+                                               self.name)));
+        }
+        if !is_expanded(&self.version) && !VERSION_RE.is_match(&self.version) {
+            errors.push(PlanError::new("pkg_version",
+                                       format!("'{}' is not a version or date", self.version)));
+        }
+        match self.origin {
+            Some(ref origin) if is_expanded(origin) || is_valid_origin_name(origin) => (),
+            Some(ref origin) => {
+                errors.push(PlanError::new("pkg_origin",
+                                           format!("'{}' is not a valid origin name", origin)))
+            }
+            None => errors.push(PlanError::new("pkg_origin", "is not set")),
+        }
+        for dep in self.deps.iter() {
+            if !is_expanded(dep) && !is_valid_dep(dep) {
+                errors.push(PlanError::new("pkg_deps",
+                                           format!("'{}' is not a valid package identifier",
+                                                   dep)));
+            }
+        }
+        errors
+    }
+}
+
+// Adds the entries of one line of a `pkg_deps` array, returning true if the line closes it.
+fn push_deps(line: &str, deps: &mut Vec<String>) -> bool {
+    let (entries, closed) = match line.find(')') {
+        Some(end) => (&line[..end], true),
+        None => (line, false),
+    };
+    for entry in entries.split_whitespace() {
+        deps.push(entry.trim_matches(|c: char| c == '"' || c == '\'').to_string());
+    }
+    closed
+}
+
+// Returns the value of a line split at its `=`, or an error if the line isn't an assignment.
+fn assigned_value<'a>(parts: &[&'a str]) -> Result<&'a str> {
+    parts.get(1).cloned().ok_or(Error::PlanMalformed)
+}
+
+// Returns a shell assignment's value without the quotes around it.
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    for quote in &['"', '\''] {
+        if value.len() >= 2 && value.starts_with(*quote) && value.ends_with(*quote) {
+            return value[1..value.len() - 1].to_string();
+        }
+    }
+    value.to_string()
+}
+
+// Returns true if the value refers to a shell variable, so its real value is unknown until the
+// plan is built.
+fn is_expanded(value: &str) -> bool {
+    value.contains('$')
+}
+
+fn is_valid_dep(dep: &str) -> bool {
+    PackageIdent::from_str(dep).is_ok() && dep.split("/").all(|part| !part.is_empty())
+}
+
+fn decode(bytes: &[u8]) -> Result<String> {
+    // The UTF-32 byte order marks start with the UTF-16 ones, so they must be checked first.
+    if bytes.starts_with(b"\xff\xfe\x00\x00") {
+        return Err(Error::UnsupportedEncoding("UTF-32LE".to_string()));
+    }
+    if bytes.starts_with(b"\x00\x00\xfe\xff") {
+        return Err(Error::UnsupportedEncoding("UTF-32BE".to_string()));
+    }
+    if bytes.starts_with(b"\xff\xfe") {
+        return decode_utf16(&bytes[2..], false);
+    }
+    if bytes.starts_with(b"\xfe\xff") {
+        return decode_utf16(&bytes[2..], true);
+    }
+    let bytes = if bytes.starts_with(b"\xef\xbb\xbf") {
+        &bytes[3..]
+    } else {
+        bytes
+    };
+    match str::from_utf8(bytes) {
+        Ok(content) => Ok(content.to_string()),
+        Err(_) => Err(Error::UnsupportedEncoding("unknown, not valid UTF-8".to_string())),
+    }
+}
+
+fn decode_utf16(bytes: &[u8], big_endian: bool) -> Result<String> {
+    if bytes.len() % 2 != 0 {
+        return Err(Error::PlanMalformed);
+    }
+    let units: Vec<u16> = bytes
+        .chunks(2)
+        .map(|pair| if big_endian {
+                 (pair[0] as u16) << 8 | pair[1] as u16
+             } else {
+                 (pair[1] as u16) << 8 | pair[0] as u16
+             })
+        .collect();
+    String::from_utf16(&units).map_err(|_| Error::PlanMalformed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const PLAN: &'static str = "pkg_origin=core\r\npkg_name=nginx\r\npkg_version=1.11.10\r\n";
+
+    fn utf16(content: &str, big_endian: bool) -> Vec<u8> {
+        let mut bytes = if big_endian {
+            vec![0xfe, 0xff]
+        } else {
+            vec![0xff, 0xfe]
+        };
+        for unit in content.encode_utf16() {
+            let (high, low) = ((unit >> 8) as u8, unit as u8);
+            if big_endian {
+                bytes.push(high);
+                bytes.push(low);
+            } else {
+                bytes.push(low);
+                bytes.push(high);
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn plan_from_utf8() {
+        let plan = Plan::from_bytes(PLAN.as_bytes()).unwrap();
+        assert_eq!(plan.name, "nginx");
+        assert_eq!(plan.version, "1.11.10");
+    }
+
+    #[test]
+    fn plan_from_utf8_with_bom() {
+        let mut bytes = b"\xef\xbb\xbf".to_vec();
+        bytes.extend_from_slice(PLAN.as_bytes());
+        let plan = Plan::from_bytes(&bytes).unwrap();
+        assert_eq!(plan.name, "nginx");
+        assert_eq!(plan.version, "1.11.10");
+    }
+
+    #[test]
+    fn plan_from_utf16le() {
+        let plan = Plan::from_bytes(&utf16(PLAN, false)).unwrap();
+        assert_eq!(plan.origin, Some("core".to_string()));
+        assert_eq!(plan.name, "nginx");
+        assert_eq!(plan.version, "1.11.10");
+    }
+
+    #[test]
+    fn plan_from_utf16be() {
+        let plan = Plan::from_bytes(&utf16(PLAN, true)).unwrap();
+        assert_eq!(plan.origin, Some("core".to_string()));
+        assert_eq!(plan.name, "nginx");
+        assert_eq!(plan.version, "1.11.10");
+    }
+
+    #[test]
+    fn plan_from_unsupported_encoding() {
+        let mut utf32 = b"\xff\xfe\x00\x00".to_vec();
+        utf32.extend_from_slice(b"p\x00\x00\x00");
+        match Plan::from_bytes(&utf32) {
+            Err(Error::UnsupportedEncoding(ref e)) => assert_eq!(e, "UTF-32LE"),
+            _ => panic!("expected an unsupported encoding error"),
+        }
+
+        let latin1 = b"pkg_name=caf\xe9\npkg_version=1.0.0\n";
+        match Plan::from_bytes(latin1) {
+            Err(Error::UnsupportedEncoding(_)) => (),
+            _ => panic!("expected an unsupported encoding error"),
+        }
+    }
+
+    #[test]
+    fn plan_reads_origin_and_deps() {
+        let content = "pkg_origin=core\npkg_name=nginx\npkg_version=1.11.10\n\
+                       pkg_deps=(core/glibc \"core/openssl/1.0.2k\"\n  core/zlib)\n\
+                       pkg_build_deps=(core/gcc)\n";
+        let plan = Plan::from_bytes(content.as_bytes()).unwrap();
+        assert_eq!(plan.origin, Some("core".to_string()));
+        assert_eq!(plan.deps, vec!["core/glibc", "core/openssl/1.0.2k", "core/zlib"]);
+    }
+
+    #[test]
+    fn plan_validate_valid() {
+        let content = "pkg_origin=core\npkg_name=openssl\npkg_version=1.0.2k\n\
+                       pkg_deps=(core/glibc core/zlib/1.2.8/20170513215726)\n";
+        let plan = Plan::from_bytes(content.as_bytes()).unwrap();
+        assert_eq!(plan.validate(), vec![]);
+
+        for version in &["2.0.0-rc1", "1.11", "2017-01-17", "20170117"] {
+            let mut plan = Plan::from_bytes(PLAN.as_bytes()).unwrap();
+            plan.version = version.to_string();
+            assert_eq!(plan.validate(), vec![], "{}", version);
+        }
+    }
+
+    #[test]
+    fn plan_validate_reports_every_error() {
+        let content = "pkg_name=Nginx!\npkg_version=latest\npkg_deps=(core/glibc core/ /x)\n";
+        let plan = Plan::from_bytes(content.as_bytes()).unwrap();
+        let fields: Vec<String> = plan.validate().into_iter().map(|e| e.field).collect();
+        assert_eq!(fields,
+                   vec!["pkg_name", "pkg_version", "pkg_origin", "pkg_deps", "pkg_deps"]);
+
+        let mut plan = Plan::from_bytes(PLAN.as_bytes()).unwrap();
+        plan.origin = Some("-core".to_string());
+        assert_eq!(plan.validate(),
+                   vec![PlanError::new("pkg_origin", "'-core' is not a valid origin name")]);
+    }
+
+    #[test]
+    fn plan_with_quoted_values() {
+        let content = "pkg_origin=\"core\"\npkg_name='nginx'\npkg_version=\"1.11.10\"\n\
+                       pkg_deps=(\"core/glibc\" 'core/zlib')\n";
+        let plan = Plan::from_bytes(content.as_bytes()).unwrap();
+        assert_eq!(plan.origin, Some("core".to_string()));
+        assert_eq!(plan.name, "nginx");
+        assert_eq!(plan.version, "1.11.10");
+        assert_eq!(plan.validate(), vec![]);
+    }
+
+    #[test]
+    fn plan_validate_skips_shell_expansions() {
+        let content = "pkg_origin=core\npkg_name=nginx\npkg_version=\"${major}.${minor}\"\n\
+                       pkg_deps=(core/glibc \"${pkg_origin}/zlib\")\n";
+        let plan = Plan::from_bytes(content.as_bytes()).unwrap();
+        assert_eq!(plan.version, "${major}.${minor}");
+        assert_eq!(plan.validate(), vec![]);
+    }
+
+    #[test]
+    fn plan_missing_version_is_malformed() {
+        match Plan::from_bytes(b"pkg_name=nginx\n") {
+            Err(Error::PlanMalformed) => (),
+            _ => panic!("expected a malformed plan error"),
+        }
+    }
+
+    #[test]
+    fn plan_field_without_value_is_malformed() {
+        for content in &["pkg_name\npkg_version=1.0.0\n",
+                         "pkg_name=nginx\npkg_version=1.0.0\npkg_origin\n",
+                         "pkg_name=nginx\npkg_version=1.0.0\npkg_deps\n"] {
+            match Plan::from_bytes(content.as_bytes()) {
+                Err(Error::PlanMalformed) => (),
+                _ => panic!("expected a malformed plan error for {:?}", content),
+            }
+        }
+    }
 }
@@ -0,0 +1,532 @@
+// Copyright (c) 2016-2017 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Software Bill of Materials generation for package archives.
+//!
+//! An `Sbom` is built from the metadata of a `PackageArchive` and can be rendered as either a
+//! CycloneDX 1.4 or an SPDX 2.3 JSON document.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde_json;
+use time;
+
+use error::{Error, Result};
+use super::{Identifiable, PackageIdent};
+
+const CYCLONEDX_SPEC_VERSION: &'static str = "1.4";
+const SPDX_VERSION: &'static str = "SPDX-2.3";
+const SPDX_DOCUMENT_ID: &'static str = "SPDXRef-DOCUMENT";
+const SPDX_NAMESPACE: &'static str = "https://habitat.sh/spdx";
+const SPDX_NOASSERTION: &'static str = "NOASSERTION";
+const TOOL_VENDOR: &'static str = "Habitat";
+const TOOL_NAME: &'static str = "habitat";
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SbomFormat {
+    CycloneDX,
+    Spdx,
+}
+
+impl Default for SbomFormat {
+    fn default() -> Self {
+        SbomFormat::CycloneDX
+    }
+}
+
+impl fmt::Display for SbomFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let format = match *self {
+            SbomFormat::CycloneDX => "cyclonedx",
+            SbomFormat::Spdx => "spdx",
+        };
+        write!(f, "{}", format)
+    }
+}
+
+impl FromStr for SbomFormat {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.to_lowercase().as_ref() {
+            "cyclonedx" => Ok(SbomFormat::CycloneDX),
+            "spdx" => Ok(SbomFormat::Spdx),
+            _ => Err(Error::InvalidSbomFormat(value.to_string())),
+        }
+    }
+}
+
+/// Relationship of a component to the package an `Sbom` describes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SbomScope {
+    /// The package itself
+    Package,
+    /// A direct or transitive runtime dependency of the package
+    Runtime,
+    /// A dependency only required to build the package
+    Build,
+}
+
+#[derive(Clone, Debug)]
+pub struct SbomComponent {
+    pub ident: PackageIdent,
+    pub scope: SbomScope,
+    pub licenses: Vec<String>,
+}
+
+impl SbomComponent {
+    pub fn new(ident: PackageIdent, scope: SbomScope) -> Self {
+        SbomComponent {
+            ident: ident,
+            scope: scope,
+            licenses: vec![],
+        }
+    }
+
+    /// Package URL uniquely identifying the component.
+    pub fn purl(&self) -> String {
+        match (self.ident.version(), self.ident.release()) {
+            (Some(version), Some(release)) => {
+                format!("pkg:habitat/{}/{}@{}-{}",
+                        self.ident.origin(),
+                        self.ident.name(),
+                        version,
+                        release)
+            }
+            (Some(version), None) => {
+                format!("pkg:habitat/{}/{}@{}",
+                        self.ident.origin(),
+                        self.ident.name(),
+                        version)
+            }
+            _ => format!("pkg:habitat/{}/{}", self.ident.origin(), self.ident.name()),
+        }
+    }
+
+    fn version(&self) -> String {
+        match (self.ident.version(), self.ident.release()) {
+            (Some(version), Some(release)) => format!("{}-{}", version, release),
+            (Some(version), None) => version.to_string(),
+            _ => String::new(),
+        }
+    }
+
+    fn spdx_id(&self) -> String {
+        let id: String = self.ident
+            .to_string()
+            .chars()
+            .map(|c| match c {
+                     'a'...'z' | 'A'...'Z' | '0'...'9' | '.' => c,
+                     _ => '-',
+                 })
+            .collect();
+        format!("SPDXRef-Package-{}", id)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Sbom {
+    pub format: SbomFormat,
+    /// The package described by this bill of materials
+    pub package: SbomComponent,
+    /// Dependencies of the package
+    pub components: Vec<SbomComponent>,
+}
+
+impl Sbom {
+    /// Render the bill of materials as a JSON document in the `Sbom`'s format.
+    pub fn to_json(&self) -> String {
+        let timestamp = format!("{}", time::now_utc().rfc3339());
+        match self.format {
+            SbomFormat::CycloneDX => {
+                serde_json::to_string(&CycloneDxBom::new(self, timestamp)).unwrap()
+            }
+            SbomFormat::Spdx => serde_json::to_string(&SpdxDocument::new(self, timestamp)).unwrap(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct CycloneDxBom {
+    #[serde(rename = "bomFormat")]
+    bom_format: &'static str,
+    #[serde(rename = "specVersion")]
+    spec_version: &'static str,
+    version: u32,
+    metadata: CycloneDxMetadata,
+    components: Vec<CycloneDxComponent>,
+    dependencies: Vec<CycloneDxDependency>,
+}
+
+impl CycloneDxBom {
+    fn new(sbom: &Sbom, timestamp: String) -> Self {
+        let runtime_refs = sbom.components
+            .iter()
+            .filter(|c| c.scope == SbomScope::Runtime)
+            .map(|c| c.purl())
+            .collect();
+        CycloneDxBom {
+            bom_format: "CycloneDX",
+            spec_version: CYCLONEDX_SPEC_VERSION,
+            version: 1,
+            metadata: CycloneDxMetadata {
+                timestamp: timestamp,
+                tools: vec![CycloneDxTool {
+                                vendor: TOOL_VENDOR,
+                                name: TOOL_NAME,
+                            }],
+                component: CycloneDxComponent::from(&sbom.package),
+            },
+            components: sbom.components.iter().map(CycloneDxComponent::from).collect(),
+            dependencies: vec![CycloneDxDependency {
+                                   dep_ref: sbom.package.purl(),
+                                   depends_on: runtime_refs,
+                               }],
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct CycloneDxMetadata {
+    timestamp: String,
+    tools: Vec<CycloneDxTool>,
+    component: CycloneDxComponent,
+}
+
+#[derive(Serialize)]
+struct CycloneDxTool {
+    vendor: &'static str,
+    name: &'static str,
+}
+
+#[derive(Serialize)]
+struct CycloneDxComponent {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    #[serde(rename = "bom-ref")]
+    bom_ref: String,
+    group: String,
+    name: String,
+    version: String,
+    purl: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope: Option<&'static str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    licenses: Vec<CycloneDxLicenseChoice>,
+}
+
+impl<'a> From<&'a SbomComponent> for CycloneDxComponent {
+    fn from(component: &'a SbomComponent) -> Self {
+        let (kind, scope) = match component.scope {
+            SbomScope::Package => ("application", None),
+            SbomScope::Runtime => ("library", Some("required")),
+            SbomScope::Build => ("library", Some("excluded")),
+        };
+        CycloneDxComponent {
+            kind: kind,
+            bom_ref: component.purl(),
+            group: component.ident.origin().to_string(),
+            name: component.ident.name().to_string(),
+            version: component.version(),
+            purl: component.purl(),
+            scope: scope,
+            licenses: component
+                .licenses
+                .iter()
+                .map(|l| CycloneDxLicenseChoice { license: CycloneDxLicense { name: l.clone() } })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct CycloneDxLicenseChoice {
+    license: CycloneDxLicense,
+}
+
+#[derive(Serialize)]
+struct CycloneDxLicense {
+    name: String,
+}
+
+#[derive(Serialize)]
+struct CycloneDxDependency {
+    #[serde(rename = "ref")]
+    dep_ref: String,
+    #[serde(rename = "dependsOn")]
+    depends_on: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct SpdxDocument {
+    #[serde(rename = "spdxVersion")]
+    spdx_version: &'static str,
+    #[serde(rename = "dataLicense")]
+    data_license: &'static str,
+    #[serde(rename = "SPDXID")]
+    spdx_id: &'static str,
+    name: String,
+    #[serde(rename = "documentNamespace")]
+    document_namespace: String,
+    #[serde(rename = "creationInfo")]
+    creation_info: SpdxCreationInfo,
+    packages: Vec<SpdxPackage>,
+    relationships: Vec<SpdxRelationship>,
+}
+
+impl SpdxDocument {
+    fn new(sbom: &Sbom, timestamp: String) -> Self {
+        let package_id = sbom.package.spdx_id();
+        let mut packages = vec![SpdxPackage::from(&sbom.package)];
+        let mut relationships = vec![SpdxRelationship {
+                                         element: SPDX_DOCUMENT_ID.to_string(),
+                                         kind: "DESCRIBES",
+                                         related: package_id.clone(),
+                                     }];
+        for component in sbom.components.iter() {
+            packages.push(SpdxPackage::from(component));
+            let relationship = match component.scope {
+                SbomScope::Build => {
+                    SpdxRelationship {
+                        element: component.spdx_id(),
+                        kind: "BUILD_DEPENDENCY_OF",
+                        related: package_id.clone(),
+                    }
+                }
+                _ => {
+                    SpdxRelationship {
+                        element: package_id.clone(),
+                        kind: "DEPENDS_ON",
+                        related: component.spdx_id(),
+                    }
+                }
+            };
+            relationships.push(relationship);
+        }
+        SpdxDocument {
+            spdx_version: SPDX_VERSION,
+            data_license: "CC0-1.0",
+            spdx_id: SPDX_DOCUMENT_ID,
+            name: sbom.package.ident.to_string(),
+            document_namespace: format!("{}/{}", SPDX_NAMESPACE, sbom.package.ident),
+            creation_info: SpdxCreationInfo {
+                created: timestamp,
+                creators: vec![format!("Tool: {}", TOOL_NAME)],
+            },
+            packages: packages,
+            relationships: relationships,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct SpdxCreationInfo {
+    created: String,
+    creators: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct SpdxPackage {
+    #[serde(rename = "SPDXID")]
+    spdx_id: String,
+    name: String,
+    #[serde(rename = "versionInfo")]
+    version_info: String,
+    supplier: String,
+    #[serde(rename = "downloadLocation")]
+    download_location: &'static str,
+    #[serde(rename = "filesAnalyzed")]
+    files_analyzed: bool,
+    #[serde(rename = "licenseConcluded")]
+    license_concluded: &'static str,
+    #[serde(rename = "licenseDeclared")]
+    license_declared: String,
+    #[serde(rename = "externalRefs")]
+    external_refs: Vec<SpdxExternalRef>,
+}
+
+impl<'a> From<&'a SbomComponent> for SpdxPackage {
+    fn from(component: &'a SbomComponent) -> Self {
+        let license_declared = if component.licenses.is_empty() {
+            SPDX_NOASSERTION.to_string()
+        } else {
+            component.licenses.join(" AND ")
+        };
+        SpdxPackage {
+            spdx_id: component.spdx_id(),
+            name: component.ident.name().to_string(),
+            version_info: component.version(),
+            supplier: format!("Organization: {}", component.ident.origin()),
+            download_location: SPDX_NOASSERTION,
+            files_analyzed: false,
+            license_concluded: SPDX_NOASSERTION,
+            license_declared: license_declared,
+            external_refs: vec![SpdxExternalRef {
+                                    category: "PACKAGE-MANAGER",
+                                    kind: "purl",
+                                    locator: component.purl(),
+                                }],
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct SpdxExternalRef {
+    #[serde(rename = "referenceCategory")]
+    category: &'static str,
+    #[serde(rename = "referenceType")]
+    kind: &'static str,
+    #[serde(rename = "referenceLocator")]
+    locator: String,
+}
+
+#[derive(Serialize)]
+struct SpdxRelationship {
+    #[serde(rename = "spdxElementId")]
+    element: String,
+    #[serde(rename = "relationshipType")]
+    kind: &'static str,
+    #[serde(rename = "relatedSpdxElement")]
+    related: String,
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use serde_json::{self, Value};
+
+    use super::*;
+    use package::PackageArchive;
+    use package::test_support::fixture_path;
+
+    fn component(ident: &str, scope: SbomScope) -> SbomComponent {
+        SbomComponent::new(PackageIdent::from_str(ident).unwrap(), scope)
+    }
+
+    fn sbom() -> Sbom {
+        let mut package = component("core/redis/3.2.4/20170514150022", SbomScope::Package);
+        package.licenses = vec!["BSD-3-Clause".to_string()];
+        Sbom {
+            format: SbomFormat::CycloneDX,
+            package: package,
+            components: vec![component("core/glibc/2.22/20170513201042", SbomScope::Runtime),
+                             component("core/make/4.2.1/20170513214620", SbomScope::Build)],
+        }
+    }
+
+    #[test]
+    fn sbom_format_from_str() {
+        assert_eq!(SbomFormat::from_str("cyclonedx").unwrap(),
+                   SbomFormat::CycloneDX);
+        assert_eq!(SbomFormat::from_str("SPDX").unwrap(), SbomFormat::Spdx);
+        assert!(SbomFormat::from_str("swid").is_err());
+    }
+
+    #[test]
+    fn cyclonedx_json() {
+        let doc: Value = serde_json::from_str(&sbom().to_json()).unwrap();
+        assert_eq!(doc["bomFormat"], "CycloneDX");
+        assert_eq!(doc["specVersion"], "1.4");
+        assert_eq!(doc["version"], 1);
+        assert!(doc["metadata"]["timestamp"].is_string());
+        let package = &doc["metadata"]["component"];
+        assert_eq!(package["type"], "application");
+        assert_eq!(package["group"], "core");
+        assert_eq!(package["name"], "redis");
+        assert_eq!(package["version"], "3.2.4-20170514150022");
+        assert_eq!(package["purl"], "pkg:habitat/core/redis@3.2.4-20170514150022");
+        assert_eq!(package["licenses"][0]["license"]["name"], "BSD-3-Clause");
+        let components = doc["components"].as_array().unwrap();
+        assert_eq!(components.len(), 2);
+        assert_eq!(components[0]["type"], "library");
+        assert_eq!(components[0]["scope"], "required");
+        assert_eq!(components[0]["bom-ref"],
+                   "pkg:habitat/core/glibc@2.22-20170513201042");
+        assert_eq!(components[0]["purl"],
+                   "pkg:habitat/core/glibc@2.22-20170513201042");
+        assert!(components[0].get("licenses").is_none());
+        assert_eq!(components[1]["scope"], "excluded");
+        assert_eq!(components[1]["purl"], "pkg:habitat/core/make@4.2.1-20170513214620");
+        assert_eq!(doc["dependencies"][0]["ref"], package["bom-ref"]);
+        assert_eq!(doc["dependencies"][0]["dependsOn"],
+                   json_array(&["pkg:habitat/core/glibc@2.22-20170513201042"]));
+    }
+
+    #[test]
+    fn spdx_json() {
+        let mut sbom = sbom();
+        sbom.format = SbomFormat::Spdx;
+        let doc: Value = serde_json::from_str(&sbom.to_json()).unwrap();
+        assert_eq!(doc["spdxVersion"], "SPDX-2.3");
+        assert_eq!(doc["SPDXID"], "SPDXRef-DOCUMENT");
+        let packages = doc["packages"].as_array().unwrap();
+        assert_eq!(packages.len(), 3);
+        assert_eq!(packages[0]["SPDXID"],
+                   "SPDXRef-Package-core-redis-3.2.4-20170514150022");
+        assert_eq!(packages[0]["licenseDeclared"], "BSD-3-Clause");
+        assert_eq!(packages[1]["licenseDeclared"], "NOASSERTION");
+        let relationships = doc["relationships"].as_array().unwrap();
+        assert_eq!(relationships[0]["relationshipType"], "DESCRIBES");
+        assert_eq!(relationships[1]["relationshipType"], "DEPENDS_ON");
+        assert_eq!(relationships[2]["relationshipType"], "BUILD_DEPENDENCY_OF");
+    }
+
+    #[test]
+    fn generate_sbom_from_archive() {
+        let path = fixture_path("happyhumans-possums-8.1.4-20160427165340-x86_64-linux.hart");
+        let mut hart = PackageArchive::new(path);
+        let sbom = hart.generate_sbom().unwrap();
+        assert_eq!(sbom.format, SbomFormat::CycloneDX);
+        assert_eq!(sbom.package.ident.to_string(),
+                   "happyhumans/possums/8.1.4/20160427165340");
+        assert_eq!(sbom.package.scope, SbomScope::Package);
+        let doc: Value = serde_json::from_str(&sbom.to_json()).unwrap();
+        assert_eq!(doc["bomFormat"], "CycloneDX");
+        assert_eq!(doc["specVersion"], "1.4");
+        assert_eq!(doc["metadata"]["component"]["purl"],
+                   "pkg:habitat/happyhumans/possums@8.1.4-20160427165340");
+        let purls: Vec<Value> = sbom.components
+            .iter()
+            .map(|c| Value::String(c.purl()))
+            .collect();
+        let doc_purls: Vec<Value> = doc["components"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| c["purl"].clone())
+            .collect();
+        assert_eq!(doc_purls, purls);
+
+        let runtime: Vec<PackageIdent> = sbom.components
+            .iter()
+            .filter(|c| c.scope == SbomScope::Runtime)
+            .map(|c| c.ident.clone())
+            .collect();
+        assert_eq!(runtime, hart.tdeps().unwrap());
+        for dep in hart.deps().unwrap() {
+            assert!(runtime.contains(&dep), "{} is missing", dep);
+        }
+        let mut idents: Vec<String> = sbom.components.iter().map(|c| c.ident.to_string()).collect();
+        let count = idents.len();
+        idents.sort();
+        idents.dedup();
+        assert_eq!(idents.len(), count);
+    }
+
+    fn json_array(values: &[&str]) -> Value {
+        Value::Array(values.iter().map(|v| Value::String(v.to_string())).collect())
+    }
+}
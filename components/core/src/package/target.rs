@@ -12,14 +12,63 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::cmp::Ordering;
 use std::fmt;
+use std::iter::FromIterator;
 use std::result;
 use std::str::FromStr;
 
+use serde::{Deserialize, Deserializer};
+use serde::de::Error as DeError;
+
 use error::{Error, Result};
 use os::system::{Architecture, Platform};
 use util::{deserialize_using_from_str, serialize_using_to_string};
 
+/// Other spellings of package targets which users write in configuration, paired with the
+/// canonical target each one stands for.
+pub const TARGET_ALIASES: &'static [(&'static str, &'static str)] =
+    &[("amd64-linux", "x86_64-linux"),
+      ("x86_64-unknown-linux-gnu", "x86_64-linux"),
+      ("x86_64-unknown-linux-musl", "x86_64-linux"),
+      ("amd64-windows", "x86_64-windows"),
+      ("x86_64-pc-windows-msvc", "x86_64-windows"),
+      ("amd64-darwin", "x86_64-darwin"),
+      ("x86_64-apple-darwin", "x86_64-darwin")];
+
+/// Deserializes a list of package targets from configuration, accepting any spelling of
+/// `PackageTarget::canonicalize`. Each target may be written as a string, such as
+/// `"x86_64-linux"`, or as a table with `architecture` and `platform` fields.
+pub fn deserialize_targets<'de, D, T>(d: D) -> result::Result<T, D::Error>
+    where D: Deserializer<'de>,
+          T: FromIterator<PackageTarget>
+{
+    let values: Vec<TargetValue> = try!(Deserialize::deserialize(d));
+    values
+        .iter()
+        .map(|value| {
+                 let name = match *value {
+                     TargetValue::Name(ref name) => name.clone(),
+                     TargetValue::Table {
+                         ref architecture,
+                         ref platform,
+                     } => format!("{}-{}", architecture, platform),
+                 };
+                 PackageTarget::canonicalize(&name).map_err(|e| D::Error::custom(e))
+             })
+        .collect()
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TargetValue {
+    Name(String),
+    Table {
+        architecture: String,
+        platform: String,
+    },
+}
+
 pub trait Target: fmt::Display + Into<PackageTarget> {
     fn validate(&self) -> Result<()>;
 }
@@ -53,6 +102,21 @@ impl PackageTarget {
         }
     }
 
+    /// Parses a target as a user may have written it, ignoring case and surrounding whitespace
+    /// and mapping the aliases in `TARGET_ALIASES` to their canonical target.
+    ///
+    /// Errors:
+    /// * ConfigInvalidTargetString, naming the value as it was given
+    pub fn canonicalize(value: &str) -> Result<Self> {
+        let name = value.trim().to_lowercase();
+        let canonical: &str = match TARGET_ALIASES.iter().find(|&&(alias, _)| alias == name) {
+            Some(&(_, target)) => target,
+            None => &name,
+        };
+        PackageTarget::from_str(canonical)
+            .map_err(|_| Error::ConfigInvalidTargetString(value.to_string()))
+    }
+
     pub fn current_platform() -> Platform {
         if cfg!(target_os = "windows") {
             Platform::Windows
@@ -90,6 +154,20 @@ impl Target for PackageTarget {
     }
 }
 
+/// Targets are ordered by their string representation, for example `x86_64-linux` sorts before
+/// `x86_64-windows`.
+impl Ord for PackageTarget {
+    fn cmp(&self, other: &PackageTarget) -> Ordering {
+        self.to_string().cmp(&other.to_string())
+    }
+}
+
+impl PartialOrd for PackageTarget {
+    fn partial_cmp(&self, other: &PackageTarget) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl Default for PackageTarget {
     fn default() -> PackageTarget {
         PackageTarget {
@@ -124,8 +202,10 @@ impl FromStr for PackageTarget {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashSet;
     use std::str::FromStr;
     use os::system::{Architecture, Platform};
+    use toml;
 
     #[test]
     fn package_target_matches_current_operating_system() {
@@ -150,6 +230,92 @@ mod tests {
         assert_eq!(target.architecture, Architecture::X86_64);
     }
 
+    #[test]
+    fn package_target_canonicalize() {
+        let linux = PackageTarget::from_str("x86_64-linux").unwrap();
+        let windows = PackageTarget::from_str("x86_64-windows").unwrap();
+        for name in &["x86_64-linux", " X86_64-Linux ", "amd64-linux", "x86_64-unknown-linux-gnu"] {
+            assert_eq!(PackageTarget::canonicalize(name).unwrap(), linux, "{:?}", name);
+        }
+        for name in &["x86_64-windows", "x86_64-pc-windows-msvc"] {
+            assert_eq!(PackageTarget::canonicalize(name).unwrap(), windows, "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn package_target_canonicalize_unknown() {
+        for name in &["", "arm-linux", "x86_64-beos", "x86_64-linux-gnu-extra"] {
+            match PackageTarget::canonicalize(name) {
+                Err(Error::ConfigInvalidTargetString(ref value)) => assert_eq!(value, name),
+                e => panic!("Unexpected result for {:?}: {:?}", name, e),
+            }
+        }
+    }
+
+    #[derive(Deserialize)]
+    struct TargetsCfg {
+        #[serde(deserialize_with = "deserialize_targets")]
+        targets: Vec<PackageTarget>,
+    }
+
+    #[test]
+    fn deserialize_targets_canonicalizes() {
+        let linux = PackageTarget::from_str("x86_64-linux").unwrap();
+        let windows = PackageTarget::from_str("x86_64-windows").unwrap();
+
+        let cfg: TargetsCfg = toml::from_str(r#"targets = ["amd64-linux", "X86_64-Windows"]"#)
+            .unwrap();
+        assert_eq!(cfg.targets, vec![linux.clone(), windows.clone()]);
+
+        let content = r#"
+        [[targets]]
+        architecture = "amd64"
+        platform = "linux"
+
+        [[targets]]
+        architecture = "x86_64"
+        platform = "Windows"
+        "#;
+        let cfg: TargetsCfg = toml::from_str(content).unwrap();
+        assert_eq!(cfg.targets, vec![linux, windows]);
+    }
+
+    #[test]
+    fn deserialize_targets_unknown() {
+        assert!(toml::from_str::<TargetsCfg>(r#"targets = ["x86_64-beos"]"#).is_err());
+    }
+
+    #[test]
+    fn package_target_set_operations() {
+        let linux = PackageTarget::from_str("x86_64-linux").unwrap();
+        let windows = PackageTarget::from_str("x86_64-windows").unwrap();
+        let darwin = PackageTarget::from_str("x86_64-darwin").unwrap();
+        let mut targets = HashSet::new();
+        assert!(targets.insert(linux.clone()));
+        assert!(targets.insert(windows.clone()));
+        assert!(!targets.insert(PackageTarget::from_str("x86_64-linux").unwrap()));
+        assert_eq!(targets.len(), 2);
+        assert!(targets.contains(&linux));
+        assert!(!targets.contains(&darwin));
+
+        let others: HashSet<PackageTarget> = vec![windows.clone(), darwin.clone()]
+            .into_iter()
+            .collect();
+        let common: Vec<&PackageTarget> = targets.intersection(&others).collect();
+        assert_eq!(common, vec![&windows]);
+    }
+
+    #[test]
+    fn package_target_ordering() {
+        let mut targets: Vec<PackageTarget> = ["x86_64-windows", "x86_64-linux", "x86_64-darwin"]
+            .iter()
+            .map(|t| PackageTarget::from_str(t).unwrap())
+            .collect();
+        targets.sort();
+        let names: Vec<String> = targets.iter().map(|t| t.to_string()).collect();
+        assert_eq!(names, vec!["x86_64-darwin", "x86_64-linux", "x86_64-windows"]);
+    }
+
     #[test]
     #[should_panic]
     fn package_target_with_reversed_target_string() {
@@ -54,7 +54,7 @@ pub fn deserialize_using_from_str<'de, T, E, D>(d: D) -> result::Result<T, D::Er
         }
     }
 
-    d.deserialize_any(FromStringable(PhantomData, PhantomData))
+    d.deserialize_str(FromStringable(PhantomData, PhantomData))
 }
 
 pub fn serialize_using_to_string<T, S>(t: &T, s: S) -> result::Result<S::Ok, S::Error>
@@ -36,6 +36,7 @@ impl Default for Config {
 
 impl ConfigFile for Config {
     type Error = Error;
+    const ENV_PREFIX: &'static str = "HAB_EVENTSRV";
 }
 
 #[cfg(test)]
@@ -35,6 +35,7 @@ pub struct Config {
 
 impl ConfigFile for Config {
     type Error = Error;
+    const ENV_PREFIX: &'static str = "HAB_CLI";
 }
 
 impl Default for Config {
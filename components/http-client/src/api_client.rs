@@ -51,6 +51,8 @@ pub struct ApiClient {
     inner: HyperClient,
     /// Proxy information, if a proxy is being used.
     proxy: Option<ProxyInfo>,
+    /// The read and write timeout of the client's connections.
+    timeout: Duration,
     /// The URL scheme of the endpoint.
     target_scheme: String,
     /// The `User-Agent` header string to use for HTTP calls.
@@ -75,11 +77,29 @@ impl ApiClient {
                endpoint: endpoint.clone(),
                inner: try!(new_hyper_client(Some(endpoint), fs_root_path)),
                proxy: try!(proxy_unless_domain_exempted(Some(endpoint))),
+               timeout: Duration::from_secs(CLIENT_SOCKET_RW_TIMEOUT),
                target_scheme: endpoint.scheme().to_string(),
                user_agent_header: try!(user_agent(product, version)),
            })
     }
 
+    /// Returns the base URL of the client.
+    pub fn endpoint(&self) -> &Url {
+        &self.endpoint
+    }
+
+    /// Returns the read and write timeout of the client's connections.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Sets the read and write timeout of the client's connections.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.inner.set_read_timeout(Some(timeout));
+        self.inner.set_write_timeout(Some(timeout));
+        self.timeout = timeout;
+    }
+
     /// Builds an HTTP GET request for a given path.
     pub fn get(&self, path: &str) -> RequestBuilder {
         self.get_with_custom_url(path, |_| {})
@@ -0,0 +1,288 @@
+// Copyright (c) 2016-2017 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A stand-in HTTP server for tests of clients of the Depot, GitHub and other HTTP services.
+//!
+//! The server reads one request per connection, answers it with the response its handler
+//! returns and closes the connection, recording every request it received.
+
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// A request received by a `MockServer`.
+#[derive(Clone, Debug)]
+pub struct Request {
+    pub method: String,
+    /// Request target, including any query string.
+    pub target: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// Path of the request target, without its query string.
+    pub fn path(&self) -> &str {
+        self.target.split('?').next().unwrap()
+    }
+
+    /// Request line, such as `GET /v1/depot/pkgs HTTP/1.1`.
+    pub fn line(&self) -> String {
+        format!("{} {} HTTP/1.1", self.method, self.target)
+    }
+
+    /// Value of the named header, compared without regard to case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|&&(ref key, _)| key.to_lowercase() == name.to_lowercase())
+            .map(|&(_, ref value)| value.as_str())
+    }
+}
+
+/// A response for a `MockServer` to answer with.
+#[derive(Clone, Debug)]
+pub struct Response {
+    status: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+    stall: bool,
+}
+
+impl Response {
+    /// A response with the given status line, such as `404 Not Found`, and an empty body.
+    pub fn new(status: &str) -> Self {
+        Response {
+            status: status.to_string(),
+            headers: vec![],
+            body: vec![],
+            stall: false,
+        }
+    }
+
+    /// A response with the given status line and JSON body.
+    pub fn json(status: &str, body: &str) -> Self {
+        Response::new(status)
+            .header("Content-Type", "application/json")
+            .body(body)
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Sets the body. A `Content-Length` matching the body is sent unless one has been set with
+    /// `header()`.
+    pub fn body<B: AsRef<[u8]>>(mut self, body: B) -> Self {
+        self.body = body.as_ref().to_vec();
+        self
+    }
+
+    /// Keeps the connection open after the response is written instead of closing it, such as to
+    /// leave a client waiting on a body shorter than its `Content-Length`.
+    pub fn stall(mut self) -> Self {
+        self.stall = true;
+        self
+    }
+
+    fn write_to(&self, stream: &mut TcpStream) {
+        let mut head = format!("HTTP/1.1 {}\r\n", self.status);
+        for &(ref name, ref value) in self.headers.iter() {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        if !self.headers
+                .iter()
+                .any(|&(ref name, _)| name.to_lowercase() == "content-length") {
+            head.push_str(&format!("Content-Length: {}\r\n", self.body.len()));
+        }
+        if !self.stall {
+            head.push_str("Connection: close\r\n");
+        }
+        head.push_str("\r\n");
+        let _ = stream.write_all(head.as_bytes());
+        let _ = stream.write_all(&self.body);
+        let _ = stream.flush();
+    }
+}
+
+pub struct MockServer {
+    url: String,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl MockServer {
+    /// Listen on a free local port and answer every request with the response returned by the
+    /// handler, in a separate thread.
+    pub fn start<F>(handler: F) -> Self
+        where F: Fn(&Request) -> Response + Send + 'static
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(vec![]));
+        let received = requests.clone();
+        thread::Builder::new()
+            .name("mock-http".to_string())
+            .spawn(move || {
+                // Stalled connections are kept open for as long as the server thread lives.
+                let mut stalled = vec![];
+                for stream in listener.incoming() {
+                    let mut stream = match stream {
+                        Ok(stream) => stream,
+                        Err(_) => continue,
+                    };
+                    let request = match read_request(&stream) {
+                        Some(request) => request,
+                        None => continue,
+                    };
+                    let response = handler(&request);
+                    received.lock().unwrap().push(request);
+                    response.write_to(&mut stream);
+                    if response.stall {
+                        stalled.push(stream);
+                    }
+                }
+            })
+            .unwrap();
+        MockServer {
+            url: url,
+            requests: requests,
+        }
+    }
+
+    /// Answer requests with each of the given responses in turn. Requests after the last one are
+    /// answered with a `500 Internal Server Error`.
+    pub fn respond_with(responses: Vec<Response>) -> Self {
+        let responses = Mutex::new(responses.into_iter().collect::<VecDeque<_>>());
+        MockServer::start(move |_| match responses.lock().unwrap().pop_front() {
+                              Some(response) => response,
+                              None => Response::new("500 Internal Server Error"),
+                          })
+    }
+
+    /// Root URL of the server, such as `http://127.0.0.1:34567`.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Requests received so far, in the order they arrived.
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Request lines of the requests received so far, in the order they arrived.
+    pub fn request_lines(&self) -> Vec<String> {
+        self.requests().iter().map(|r| r.line()).collect()
+    }
+}
+
+/// Returns a local port which nothing listens on.
+pub fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+/// Returns the URL of a local port which nothing listens on, so connecting to it fails.
+pub fn unreachable_url() -> String {
+    format!("http://127.0.0.1:{}", free_port())
+}
+
+fn read_request(stream: &TcpStream) -> Option<Request> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    if reader.read_line(&mut line).ok().map_or(true, |n| n == 0) {
+        return None;
+    }
+    let mut parts = line.trim_right().split(' ');
+    let method = parts.next().unwrap_or("").to_string();
+    let target = parts.next().unwrap_or("").to_string();
+    let mut headers = vec![];
+    loop {
+        let mut header = String::new();
+        match reader.read_line(&mut header) {
+            Ok(0) | Err(_) => return None,
+            Ok(_) => (),
+        }
+        if header.trim().is_empty() {
+            break;
+        }
+        let mut parts = header.splitn(2, ':');
+        let name = parts.next().unwrap().trim().to_string();
+        let value = parts.next().unwrap_or("").trim().to_string();
+        headers.push((name, value));
+    }
+    let mut request = Request {
+        method: method,
+        target: target,
+        headers: headers,
+        body: vec![],
+    };
+    let length = request
+        .header("Content-Length")
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; length];
+    if reader.read_exact(&mut body).is_err() {
+        return None;
+    }
+    request.body = body;
+    Some(request)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    use super::*;
+
+    fn send(url: &str, request: &str) -> String {
+        let mut stream = TcpStream::connect(&url["http://".len()..]).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn answers_and_records_requests() {
+        let server = MockServer::start(|req| Response::json("200 OK", req.path()));
+        let response = send(server.url(),
+                            "POST /repos?page=2 HTTP/1.1\r\nContent-Length: 4\r\n\r\nbody");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Content-Length: 6\r\n"));
+        assert!(response.ends_with("\r\n\r\n/repos"));
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].line(), "POST /repos?page=2 HTTP/1.1");
+        assert_eq!(requests[0].header("content-length"), Some("4"));
+        assert_eq!(requests[0].body, b"body");
+    }
+
+    #[test]
+    fn responds_in_turn() {
+        let server = MockServer::respond_with(vec![Response::new("201 Created")]);
+        let first = send(server.url(), "GET / HTTP/1.1\r\n\r\n");
+        let second = send(server.url(), "GET / HTTP/1.1\r\n\r\n");
+        assert!(first.starts_with("HTTP/1.1 201 Created\r\n"));
+        assert!(second.starts_with("HTTP/1.1 500 Internal Server Error\r\n"));
+        assert_eq!(server.request_lines(), vec!["GET / HTTP/1.1", "GET / HTTP/1.1"]);
+    }
+}
@@ -14,14 +14,22 @@
 
 use std::fmt;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use std::result;
+use std::str::FromStr;
 
+use hyper::Url;
 use num_cpus;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use error::{Error, Result};
 
 pub const DEFAULT_ROUTER_LISTEN_PORT: u16 = 5562;
 pub const DEFAULT_ROUTER_HEARTBEAT_PORT: u16 = 5563;
 
 /// URL to GitHub API endpoint
 pub const DEFAULT_GITHUB_URL: &'static str = "https://api.github.com";
+/// Default time, in milliseconds, a request to the GitHub API may go without receiving any data
+pub const DEFAULT_GITHUB_IDLE_TIMEOUT: u64 = 3_000;
 /// Default Client ID for providing a default value in development environments only. This is
 /// associated to the habitat-sh GitHub account and is configured to re-direct and point to a local
 /// builder-api.
@@ -31,6 +39,8 @@ pub const DEV_GITHUB_CLIENT_ID: &'static str = "0c2f738a7d0bd300de10";
 /// Default Client Secret for development purposes only. See the `DEV_GITHUB_CLIENT_ID` for
 /// additional comments.
 pub const DEV_GITHUB_CLIENT_SECRET: &'static str = "438223113eeb6e7edf2d2f91a232b72de72b9bdf";
+/// Placeholder written in place of secret configuration values when formatting for logs.
+pub const REDACTED: &'static str = "<redacted>";
 
 pub trait DispatcherCfg {
     fn default_worker_count() -> usize {
@@ -44,28 +54,123 @@ pub trait DispatcherCfg {
 }
 
 pub trait GitHubOAuth {
-    fn github_url(&self) -> &str;
+    fn github_url(&self) -> &GitHubEndpoint;
     fn github_client_id(&self) -> &str;
     fn github_client_secret(&self) -> &str;
+    fn github_idle_timeout(&self) -> u64;
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(default)]
 pub struct GitHubCfg {
-    /// URL to GitHub API
-    pub url: String,
+    /// URL to GitHub API, or to the root of a GitHub Enterprise instance
+    pub url: GitHubEndpoint,
     /// Client identifier used for GitHub API requests
     pub client_id: String,
     /// Client secret used for GitHub API requests
     pub client_secret: String,
+    /// How long, in milliseconds, a request to the GitHub API may wait without receiving any
+    /// data before it is abandoned
+    pub idle_timeout: u64,
+}
+
+impl fmt::Debug for GitHubCfg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("GitHubCfg")
+            .field("url", &self.url)
+            .field("client_id", &self.client_id)
+            .field("client_secret", &REDACTED)
+            .field("idle_timeout", &self.idle_timeout)
+            .finish()
+    }
 }
 
 impl Default for GitHubCfg {
     fn default() -> Self {
         GitHubCfg {
-            url: DEFAULT_GITHUB_URL.to_string(),
+            url: DEFAULT_GITHUB_URL.parse().unwrap(),
             client_id: DEV_GITHUB_CLIENT_ID.to_string(),
             client_secret: DEV_GITHUB_CLIENT_SECRET.to_string(),
+            idle_timeout: DEFAULT_GITHUB_IDLE_TIMEOUT,
+        }
+    }
+}
+
+/// A validated location of the GitHub API, given either as the API's own URL or as the root of a
+/// GitHub Enterprise instance, which serves its API under `/api/v3`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GitHubEndpoint {
+    url: Url,
+}
+
+impl GitHubEndpoint {
+    /// Returns the base URL, without a trailing slash, which API paths are appended to.
+    pub fn api_base(&self) -> String {
+        let host = self.host();
+        if host == "github.com" || host == "api.github.com" {
+            return "https://api.github.com".to_string();
+        }
+        let authority = match self.url.port() {
+            Some(port) => format!("{}:{}", host, port),
+            None => host.to_string(),
+        };
+        let path = self.url.path().trim_right_matches('/');
+        if path.is_empty() {
+            format!("{}://{}/api/v3", self.url.scheme(), authority)
+        } else {
+            format!("{}://{}{}", self.url.scheme(), authority, path)
+        }
+    }
+
+    /// Returns the host name of the GitHub instance.
+    pub fn host(&self) -> &str {
+        self.url.host_str().unwrap()
+    }
+}
+
+impl FromStr for GitHubEndpoint {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let url = match Url::parse(value) {
+            Ok(url) => url,
+            Err(e) => return Err(Error::InvalidGitHubUrl(format!("{}, {}", value, e))),
+        };
+        if url.scheme() != "http" && url.scheme() != "https" {
+            let msg = format!("{}, expected an http or https URL", value);
+            return Err(Error::InvalidGitHubUrl(msg));
+        }
+        if url.host_str().map_or(true, |host| host.is_empty()) {
+            return Err(Error::InvalidGitHubUrl(format!("{}, missing a host", value)));
         }
+        if url.query().is_some() || url.fragment().is_some() {
+            let msg = format!("{}, a base URL can't have a query or fragment", value);
+            return Err(Error::InvalidGitHubUrl(msg));
+        }
+        Ok(GitHubEndpoint { url: url })
+    }
+}
+
+impl fmt::Display for GitHubEndpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.url.as_str().trim_right_matches('/'))
+    }
+}
+
+impl<'de> Deserialize<'de> for GitHubEndpoint {
+    fn deserialize<D>(deserializer: D) -> result::Result<Self, D::Error>
+        where D: Deserializer<'de>
+    {
+        let value = try!(String::deserialize(deserializer));
+        value.parse().map_err(de::Error::custom)
+    }
+}
+
+impl Serialize for GitHubEndpoint {
+    fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        serializer.serialize_str(&self.to_string())
     }
 }
 
@@ -97,10 +202,105 @@ impl fmt::Display for RouterAddr {
     }
 }
 
+/// A range of IP addresses in CIDR notation, such as `10.0.0.0/8` or `fd00::/8`. A lone address is
+/// a range containing only that address.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IpNet {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpNet {
+    /// Returns true if the given address falls within this range.
+    pub fn contains(&self, addr: &IpAddr) -> bool {
+        match (self.addr, *addr) {
+            (IpAddr::V4(net), IpAddr::V4(addr)) => {
+                prefix_matches(&net.octets(), &addr.octets(), self.prefix)
+            }
+            (IpAddr::V6(net), IpAddr::V6(addr)) => {
+                prefix_matches(&net.octets(), &addr.octets(), self.prefix)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpNet {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let mut parts = value.splitn(2, '/');
+        let addr: IpAddr = match parts.next().unwrap().parse() {
+            Ok(addr) => addr,
+            Err(_) => return Err(Error::InvalidIpNet(value.to_string())),
+        };
+        let max = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        let prefix = match parts.next() {
+            Some(prefix) => {
+                match prefix.parse::<u8>() {
+                    Ok(prefix) if prefix <= max => prefix,
+                    _ => return Err(Error::InvalidIpNet(value.to_string())),
+                }
+            }
+            None => max,
+        };
+        Ok(IpNet {
+               addr: addr,
+               prefix: prefix,
+           })
+    }
+}
+
+impl fmt::Display for IpNet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+impl<'de> Deserialize<'de> for IpNet {
+    fn deserialize<D>(deserializer: D) -> result::Result<Self, D::Error>
+        where D: Deserializer<'de>
+    {
+        let value = try!(String::deserialize(deserializer));
+        value.parse().map_err(de::Error::custom)
+    }
+}
+
+fn prefix_matches(net: &[u8], addr: &[u8], prefix: u8) -> bool {
+    let whole = (prefix / 8) as usize;
+    if net[..whole] != addr[..whole] {
+        return false;
+    }
+    let bits = prefix % 8;
+    if bits == 0 {
+        return true;
+    }
+    let mask = !0u8 << (8 - bits);
+    net[whole] & mask == addr[whole] & mask
+}
+
 /// Apply to server configurations which connect to a cluster of Routers
 pub trait RouterCfg {
     /// Return a list of router addresses
     fn route_addrs(&self) -> &Vec<RouterAddr>;
+
+    /// Whether the server may start without any configured routers. Override this for servers
+    /// which are able to operate without routing messages.
+    fn allow_empty_routers(&self) -> bool {
+        false
+    }
+
+    /// Validate the list of router addresses, returning an error if it is empty and the server
+    /// does not allow it.
+    fn validate_routers(&self) -> Result<()> {
+        if self.route_addrs().is_empty() && !self.allow_empty_routers() {
+            return Err(Error::RequiredConfigField("routers"));
+        }
+        Ok(())
+    }
 }
 
 /// Apply to a server configuration which belongs to a sharded service
@@ -136,3 +336,90 @@ impl ToAddrString for RouterAddr {
         format!("tcp://{}:{}", self.host, self.port)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+
+    use super::*;
+
+    fn addr(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn config_debug_redacts_secrets() {
+        let mut cfg = GitHubCfg::default();
+        cfg.client_id = "0c2f738a7d0bd300de10".to_string();
+        cfg.client_secret = "438223113eeb6e7edf2d2f91a232b72de72b9bdf".to_string();
+        let output = format!("{:?}", cfg);
+        assert!(!output.contains("438223113eeb6e7edf2d2f91a232b72de72b9bdf"));
+        assert!(output.contains("0c2f738a7d0bd300de10"));
+        assert!(output.contains(REDACTED));
+    }
+
+    #[test]
+    fn github_endpoint_github_com() {
+        for url in &["https://api.github.com", "https://api.github.com/", "https://github.com"] {
+            let endpoint: GitHubEndpoint = url.parse().unwrap();
+            assert_eq!(endpoint.api_base(), "https://api.github.com");
+            assert!(endpoint.host().ends_with("github.com"));
+        }
+    }
+
+    #[test]
+    fn github_endpoint_enterprise() {
+        let endpoint: GitHubEndpoint = "https://github.example.com".parse().unwrap();
+        assert_eq!(endpoint.host(), "github.example.com");
+        assert_eq!(endpoint.api_base(), "https://github.example.com/api/v3");
+
+        let endpoint: GitHubEndpoint = "http://github.example.com:8080/api/v3/".parse().unwrap();
+        assert_eq!(endpoint.api_base(), "http://github.example.com:8080/api/v3");
+        assert_eq!(endpoint.to_string(), "http://github.example.com:8080/api/v3");
+    }
+
+    #[test]
+    fn github_endpoint_malformed() {
+        for url in &["", "api.github.com", "ftp://github.example.com", "https://", "file:///tmp",
+                     "https://github.example.com/?token=1"] {
+            match url.parse::<GitHubEndpoint>() {
+                Err(Error::InvalidGitHubUrl(_)) => (),
+                e => panic!("Unexpected result for {:?}: {:?}", url, e),
+            }
+        }
+    }
+
+    #[test]
+    fn ip_net_contains() {
+        let net: IpNet = "10.0.0.0/8".parse().unwrap();
+        assert!(net.contains(&addr("10.1.2.3")));
+        assert!(!net.contains(&addr("11.0.0.1")));
+        assert!(!net.contains(&addr("::ffff:10.1.2.3")));
+
+        let net: IpNet = "172.16.0.0/12".parse().unwrap();
+        assert!(net.contains(&addr("172.31.255.255")));
+        assert!(!net.contains(&addr("172.32.0.0")));
+
+        let net: IpNet = "fd00::/8".parse().unwrap();
+        assert!(net.contains(&addr("fd12:3456::1")));
+        assert!(!net.contains(&addr("fe80::1")));
+
+        let net: IpNet = "0.0.0.0/0".parse().unwrap();
+        assert!(net.contains(&addr("192.0.2.1")));
+    }
+
+    #[test]
+    fn ip_net_single_address() {
+        let net: IpNet = "192.0.2.1".parse().unwrap();
+        assert_eq!(net.to_string(), "192.0.2.1/32");
+        assert!(net.contains(&addr("192.0.2.1")));
+        assert!(!net.contains(&addr("192.0.2.2")));
+    }
+
+    #[test]
+    fn ip_net_invalid() {
+        for value in &["", "10.0.0.0/33", "::/129", "10.0.0/8", "10.0.0.0/", "localhost"] {
+            assert!(value.parse::<IpNet>().is_err(), "{:?}", value);
+        }
+    }
+}
@@ -19,45 +19,118 @@ use std::io;
 use std::result;
 
 use hyper;
+use hyper::status::StatusCode;
 use protobuf;
 use protocol::net;
 use serde_json;
 use zmq;
 
+use http::net_err_to_http;
 use oauth;
 
 #[derive(Debug)]
 pub enum Error {
     Auth(oauth::github::AuthErr),
+    ContentDecode(String),
     GitHubAPI(hyper::status::StatusCode, HashMap<String, String>),
+    InvalidGitHubUrl(String),
+    InvalidIpNet(String),
     IO(io::Error),
     Json(serde_json::Error),
     MaxHops,
     Net(net::NetError),
     HTTP(hyper::status::StatusCode),
+    PageOutOfRange(usize, usize),
     Protobuf(protobuf::ProtobufError),
     RequiredConfigField(&'static str),
     Sys,
+    Timeout(String),
     Zmq(zmq::Error),
 }
 
 pub type Result<T> = result::Result<T, Error>;
 
+impl Error {
+    /// Returns true if the operation which failed with this error may succeed if retried, such as
+    /// after a timeout or a refused connection. Authentication failures, missing entities, and
+    /// logic errors are never transient.
+    pub fn is_transient(&self) -> bool {
+        match *self {
+            Error::Timeout(_) => true,
+            Error::IO(ref e) => {
+                match e.kind() {
+                    io::ErrorKind::ConnectionRefused |
+                    io::ErrorKind::ConnectionReset |
+                    io::ErrorKind::ConnectionAborted |
+                    io::ErrorKind::NotConnected |
+                    io::ErrorKind::BrokenPipe |
+                    io::ErrorKind::TimedOut |
+                    io::ErrorKind::Interrupted |
+                    io::ErrorKind::WouldBlock => true,
+                    _ => false,
+                }
+            }
+            Error::Zmq(ref e) => {
+                match *e {
+                    zmq::Error::EAGAIN |
+                    zmq::Error::EINTR |
+                    zmq::Error::ECONNREFUSED |
+                    zmq::Error::EHOSTUNREACH => true,
+                    _ => false,
+                }
+            }
+            Error::Net(ref e) => is_transient_status(net_err_to_http(e.get_code())),
+            Error::HTTP(status) |
+            Error::GitHubAPI(status, _) => is_transient_status(status),
+            Error::Auth(_) |
+            Error::ContentDecode(_) |
+            Error::InvalidGitHubUrl(_) |
+            Error::InvalidIpNet(_) |
+            Error::Json(_) |
+            Error::MaxHops |
+            Error::PageOutOfRange(_, _) |
+            Error::Protobuf(_) |
+            Error::RequiredConfigField(_) |
+            Error::Sys => false,
+        }
+    }
+}
+
+// Upstream failures and unavailable services are worth retrying, other server errors are bugs.
+fn is_transient_status(status: StatusCode) -> bool {
+    match status {
+        StatusCode::BadGateway |
+        StatusCode::ServiceUnavailable |
+        StatusCode::GatewayTimeout => true,
+        _ => false,
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let msg = match *self {
             Error::Auth(ref e) => format!("GitHub Authentication error, {}", e),
+            Error::ContentDecode(ref e) => format!("Unable to decode GitHub contents, {}", e),
             Error::GitHubAPI(ref c, ref m) => format!("[{}] {:?}", c, m),
             Error::HTTP(ref e) => format!("{}", e),
+            Error::InvalidGitHubUrl(ref e) => format!("Invalid GitHub URL {}", e),
+            Error::InvalidIpNet(ref e) => {
+                format!("Invalid IP address range '{}', expected an address or CIDR notation",
+                        e)
+            }
             Error::IO(ref e) => format!("{}", e),
             Error::Json(ref e) => format!("{}", e),
             Error::MaxHops => format!("Received a message containing too many network hops"),
             Error::Net(ref e) => format!("{}", e),
+            Error::PageOutOfRange(page, per_page) => {
+                format!("Page {} of {} items is out of range", page, per_page)
+            }
             Error::Protobuf(ref e) => format!("{}", e),
             Error::RequiredConfigField(ref e) => {
                 format!("Missing required field in configuration, {}", e)
             }
             Error::Sys => format!("Internal system error"),
+            Error::Timeout(ref e) => format!("Timed out waiting for a response from {}", e),
             Error::Zmq(ref e) => format!("{}", e),
         };
         write!(f, "{}", msg)
@@ -68,15 +141,20 @@ impl error::Error for Error {
     fn description(&self) -> &str {
         match *self {
             Error::Auth(_) => "GitHub authorization error.",
+            Error::ContentDecode(_) => "Unable to decode GitHub contents.",
             Error::GitHubAPI(_, _) => "GitHub API error.",
             Error::IO(ref err) => err.description(),
             Error::HTTP(_) => "Non-200 HTTP response.",
+            Error::InvalidGitHubUrl(_) => "Invalid GitHub URL.",
+            Error::InvalidIpNet(_) => "Invalid IP address range.",
             Error::Json(ref err) => err.description(),
             Error::MaxHops => "Received a message containing too many network hops",
             Error::Net(ref err) => err.description(),
+            Error::PageOutOfRange(_, _) => "Page out of range.",
             Error::Protobuf(ref err) => err.description(),
             Error::RequiredConfigField(_) => "Missing required field in configuration.",
             Error::Sys => "Internal system error",
+            Error::Timeout(_) => "Timed out waiting for a response.",
             Error::Zmq(ref err) => err.description(),
         }
     }
@@ -117,3 +195,65 @@ impl From<zmq::Error> for Error {
         Error::Zmq(err)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::io;
+
+    use hyper::status::StatusCode;
+    use protocol::net::{self, ErrCode};
+    use zmq;
+
+    use super::*;
+
+    #[test]
+    fn transient_errors() {
+        let errors = vec![Error::Timeout("tcp://127.0.0.1:5562".to_string()),
+                          Error::IO(io::Error::new(io::ErrorKind::ConnectionRefused, "refused")),
+                          Error::IO(io::Error::new(io::ErrorKind::TimedOut, "timed out")),
+                          Error::Zmq(zmq::Error::EAGAIN),
+                          Error::Net(net::err(ErrCode::TIMEOUT, "rt:route:1")),
+                          Error::Net(net::err(ErrCode::NO_SHARD, "rt:route:2")),
+                          Error::Net(net::err(ErrCode::ZMQ, "rt:route:3")),
+                          Error::Net(net::err(ErrCode::DATA_STORE, "vt:origin-get:1")),
+                          Error::HTTP(StatusCode::ServiceUnavailable),
+                          Error::GitHubAPI(StatusCode::BadGateway, HashMap::new())];
+        for err in errors {
+            assert!(err.is_transient(), "{:?} should be transient", err);
+        }
+    }
+
+    #[test]
+    fn permanent_errors() {
+        let errors = vec![Error::IO(io::Error::new(io::ErrorKind::NotFound, "not found")),
+                          Error::IO(io::Error::new(io::ErrorKind::PermissionDenied, "denied")),
+                          Error::Zmq(zmq::Error::ETERM),
+                          Error::Net(net::err(ErrCode::BUG, "rt:route:4")),
+                          Error::Net(net::err(ErrCode::ACCESS_DENIED, "rt:route:5")),
+                          Error::Net(net::err(ErrCode::ENTITY_NOT_FOUND, "vt:origin-get:0")),
+                          Error::Net(net::err(ErrCode::ENTITY_CONFLICT, "vt:origin-create:0")),
+                          Error::HTTP(StatusCode::NotFound),
+                          Error::HTTP(StatusCode::InternalServerError),
+                          Error::GitHubAPI(StatusCode::Unauthorized, HashMap::new()),
+                          Error::ContentDecode("unsupported encoding".to_string()),
+                          Error::MaxHops,
+                          Error::RequiredConfigField("routers"),
+                          Error::Sys];
+        for err in errors {
+            assert!(!err.is_transient(), "{:?} should not be transient", err);
+        }
+    }
+
+    #[test]
+    fn io_error_conversion() {
+        let io_err = io::Error::new(io::ErrorKind::AddrInUse, "address in use");
+        let display = format!("{}", io_err);
+        let err = Error::from(io_err);
+        match err {
+            Error::IO(ref e) => assert_eq!(e.kind(), io::ErrorKind::AddrInUse),
+            ref e => panic!("Unexpected error: {:?}", e),
+        }
+        assert_eq!(format!("{}", err), display);
+    }
+}
@@ -15,4 +15,7 @@
 pub use super::net_err_to_http;
 pub use super::headers::*;
 pub use super::middleware::*;
-pub use super::rendering::{render_json, render_net_error};
+pub use super::pagination::{page_range, parse_pagination, render_paginated,
+                            render_paginated_range};
+pub use super::rendering::{render_json, render_message, render_net_error,
+                           render_net_error_message, render_proto, ResponseFormat};
@@ -13,6 +13,8 @@
 // limitations under the License.
 
 use std::env;
+use std::net::{IpAddr, SocketAddr};
+use std::str;
 
 use hyper;
 use iron::Handler;
@@ -28,10 +30,11 @@ use protocol::net::{self, ErrCode};
 use serde_json;
 
 use super::net_err_to_http;
+use super::rendering::ResponseFormat;
 use super::super::error::Error;
 use super::super::routing::{Broker, BrokerConn};
 use super::super::oauth::github::GitHubClient;
-use config;
+use config::{self, IpNet};
 use privilege::FeatureFlags;
 
 /// Wrapper around the standard `iron::Chain` to assist in adding middleware on a per-handler basis
@@ -82,9 +85,16 @@ impl Key for RouteBroker {
 
 impl BeforeMiddleware for RouteBroker {
     fn before(&self, req: &mut Request) -> IronResult<()> {
-        let conn = Broker::connect().unwrap();
-        req.extensions.insert::<RouteBroker>(conn);
-        Ok(())
+        match Broker::try_connect() {
+            Some(conn) => {
+                req.extensions.insert::<RouteBroker>(conn);
+                Ok(())
+            }
+            None => {
+                let err = net::err(ErrCode::ZMQ, "net:route-broker:0");
+                Err(IronError::new(err, Status::ServiceUnavailable))
+            }
+        }
     }
 }
 
@@ -144,7 +154,13 @@ impl BeforeMiddleware for Authenticated {
                     match req.extensions.get_mut::<RouteBroker>() {
                         Some(broker) => try!(self.authenticate(broker, token)),
                         None => {
-                            let mut broker = Broker::connect().unwrap();
+                            let mut broker = match Broker::try_connect() {
+                                Some(broker) => broker,
+                                None => {
+                                    let err = net::err(ErrCode::ZMQ, "net:auth:2");
+                                    return Err(IronError::new(err, Status::ServiceUnavailable));
+                                }
+                            };
                             try!(self.authenticate(&mut broker, token))
                         }
                     }
@@ -160,6 +176,118 @@ impl BeforeMiddleware for Authenticated {
     }
 }
 
+/// Resolves the address of the client which made a request. When a request is relayed by one of
+/// the trusted proxies the client's address is read from the `X-Forwarded-For` or `X-Real-IP`
+/// header, and the request's `remote_addr` is rewritten to it so that later middleware and
+/// handlers see the client rather than the proxy.
+pub struct ClientAddr {
+    trusted_proxies: Vec<IpNet>,
+}
+
+impl ClientAddr {
+    pub fn new(trusted_proxies: Vec<IpNet>) -> Self {
+        ClientAddr { trusted_proxies: trusted_proxies }
+    }
+}
+
+impl BeforeMiddleware for ClientAddr {
+    fn before(&self, req: &mut Request) -> IronResult<()> {
+        let addr = client_addr(req.remote_addr.ip(), &req.headers, &self.trusted_proxies);
+        req.remote_addr = SocketAddr::new(addr, req.remote_addr.port());
+        Ok(())
+    }
+}
+
+/// Returns the address of the client for a request received from `peer`. Forwarding headers are
+/// only honoured when `peer` is a trusted proxy, and are ignored if they are malformed.
+///
+/// Each proxy appends the address it received the request from to `X-Forwarded-For`, so only the
+/// addresses added by trusted proxies can be believed. The chain is walked from the right, past
+/// the trusted proxies, and the first untrusted address is the client's. Anything to the left of
+/// it was sent by the client and may be forged.
+pub fn client_addr(peer: IpAddr, headers: &headers::Headers, trusted_proxies: &[IpNet]) -> IpAddr {
+    let is_trusted = |addr: &IpAddr| trusted_proxies.iter().any(|net| net.contains(addr));
+    if !is_trusted(&peer) {
+        return peer;
+    }
+    let forwarded = match headers.get_raw("X-Forwarded-For") {
+        Some(values) => forwarded_addrs(values),
+        None => {
+            match headers.get_raw("X-Real-IP") {
+                Some(values) => {
+                    match forwarded_addrs(values) {
+                        Some(ref addrs) if addrs.len() > 1 => None,
+                        addrs => addrs,
+                    }
+                }
+                None => return peer,
+            }
+        }
+    };
+    match forwarded {
+        Some(addrs) => {
+            // When every address is a trusted proxy the leftmost one is the furthest from us.
+            *addrs
+                 .iter()
+                 .rev()
+                 .find(|addr| !is_trusted(*addr))
+                 .unwrap_or(&addrs[0])
+        }
+        None => {
+            warn!("Ignoring malformed forwarding header from proxy {}", peer);
+            peer
+        }
+    }
+}
+
+fn forwarded_addrs(values: &[Vec<u8>]) -> Option<Vec<IpAddr>> {
+    let mut addrs = vec![];
+    for value in values {
+        let value = match str::from_utf8(value) {
+            Ok(value) => value,
+            Err(_) => return None,
+        };
+        for addr in value.split(',') {
+            match addr.trim().parse() {
+                Ok(addr) => addrs.push(addr),
+                Err(_) => return None,
+            }
+        }
+    }
+    if addrs.is_empty() { None } else { Some(addrs) }
+}
+
+/// Inspects the `Accept` header of a request and records the `ResponseFormat` the client prefers
+/// so handlers can render their responses with `render_message`.
+pub struct AcceptHeader;
+
+impl Key for AcceptHeader {
+    type Value = ResponseFormat;
+}
+
+impl BeforeMiddleware for AcceptHeader {
+    fn before(&self, req: &mut Request) -> IronResult<()> {
+        let format = match req.headers.get::<headers::Accept>() {
+            Some(&headers::Accept(ref items)) => {
+                let mut best: Option<(headers::Quality, ResponseFormat)> = None;
+                // A quality of zero marks a media type as not acceptable.
+                for item in items.iter().filter(|item| item.quality != headers::Quality(0)) {
+                    if let Some(format) = ResponseFormat::from_mime(&item.item) {
+                        match best {
+                            Some((quality, _)) if quality >= item.quality => continue,
+                            _ => best = Some((item.quality, format)),
+                        }
+                    }
+                }
+                best.map(|(_, format)| format).unwrap_or_default()
+            }
+            None => ResponseFormat::default(),
+        };
+        req.extensions.insert::<AcceptHeader>(format);
+        Ok(())
+    }
+}
+
 pub struct Cors;
 
 impl AfterMiddleware for Cors {
@@ -200,7 +328,13 @@ pub fn session_create(github: &GitHubClient, token: &str) -> IronResult<Session>
                        user)
             }
         };
-        let mut conn = Broker::connect().unwrap();
+        let mut conn = match Broker::try_connect() {
+            Some(conn) => conn,
+            None => {
+                let err = net::err(ErrCode::ZMQ, "net:session-create:5");
+                return Err(IronError::new(err, Status::ServiceUnavailable));
+            }
+        };
         match conn.route::<SessionCreate, Session>(&request) {
             Ok(session) => return Ok(session),
             Err(err) => {
@@ -230,7 +364,13 @@ pub fn session_create(github: &GitHubClient, token: &str) -> IronResult<Session>
                     return Err(IronError::new(err, (body, status)));
                 }
             };
-            let mut conn = Broker::connect().unwrap();
+            let mut conn = match Broker::try_connect() {
+                Some(conn) => conn,
+                None => {
+                    let err = net::err(ErrCode::ZMQ, "net:session-create:6");
+                    return Err(IronError::new(err, Status::ServiceUnavailable));
+                }
+            };
             let mut request = SessionCreate::new();
             request.set_token(token.to_string());
             request.set_extern_id(user.id);
@@ -275,3 +415,160 @@ pub fn session_create(github: &GitHubClient, token: &str) -> IronResult<Session>
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+
+    use iron::headers::{ContentType, Headers};
+    use iron::status;
+    use iron_test::request;
+    use protocol::net::NetError;
+
+    use super::*;
+    use super::super::rendering::render_message;
+
+    fn addr(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    fn trusted() -> Vec<IpNet> {
+        vec!["10.0.0.0/8".parse().unwrap()]
+    }
+
+    fn forwarded(name: &str, value: &str) -> Headers {
+        let mut headers = Headers::new();
+        headers.set_raw(name.to_string(), vec![value.as_bytes().to_vec()]);
+        headers
+    }
+
+    #[test]
+    fn client_addr_from_trusted_proxy() {
+        let proxy = addr("10.0.0.5");
+        let headers = forwarded("X-Forwarded-For", "203.0.113.7");
+        assert_eq!(client_addr(proxy, &headers, &trusted()), addr("203.0.113.7"));
+
+        let headers = forwarded("X-Real-IP", "2001:db8::7");
+        assert_eq!(client_addr(proxy, &headers, &trusted()), addr("2001:db8::7"));
+
+        assert_eq!(client_addr(proxy, &Headers::new(), &trusted()), proxy);
+    }
+
+    #[test]
+    fn client_addr_skips_trusted_proxies_from_the_right() {
+        let proxy = addr("10.0.0.5");
+        let headers = forwarded("X-Forwarded-For", "203.0.113.7, 10.1.1.1, 10.2.2.2");
+        assert_eq!(client_addr(proxy, &headers, &trusted()), addr("203.0.113.7"));
+
+        // Addresses left of the first untrusted one were sent by the client and may be forged
+        for value in &["203.0.113.7, 198.51.100.2",
+                       "192.168.1.1, 198.51.100.2",
+                       "127.0.0.1, 198.51.100.2, 10.1.1.1"] {
+            let headers = forwarded("X-Forwarded-For", value);
+            assert_eq!(client_addr(proxy, &headers, &trusted()),
+                       addr("198.51.100.2"),
+                       "{}",
+                       value);
+        }
+
+        let headers = forwarded("X-Forwarded-For", "10.3.3.3, 10.1.1.1");
+        assert_eq!(client_addr(proxy, &headers, &trusted()), addr("10.3.3.3"));
+    }
+
+    #[test]
+    fn client_addr_from_untrusted_proxy() {
+        let peer = addr("198.51.100.9");
+        let headers = forwarded("X-Forwarded-For", "203.0.113.7");
+        assert_eq!(client_addr(peer, &headers, &trusted()), peer);
+
+        let headers = forwarded("X-Real-IP", "203.0.113.7");
+        assert_eq!(client_addr(peer, &headers, &trusted()), peer);
+        assert_eq!(client_addr(peer, &headers, &[]), peer);
+    }
+
+    #[test]
+    fn client_addr_rejects_malformed_headers() {
+        let proxy = addr("10.0.0.5");
+        for value in &["", "203.0.113.7; rm -rf /", "203.0.113.7,,", "not-an-ip"] {
+            let headers = forwarded("X-Forwarded-For", value);
+            assert_eq!(client_addr(proxy, &headers, &trusted()), proxy, "{}", value);
+        }
+        let headers = forwarded("X-Real-IP", "203.0.113.7, 198.51.100.2");
+        assert_eq!(client_addr(proxy, &headers, &trusted()), proxy);
+    }
+
+    fn not_found(req: &mut Request) -> IronResult<Response> {
+        let err = net::err(ErrCode::ENTITY_NOT_FOUND, "test:1");
+        Ok(render_message(req, status::NotFound, &err))
+    }
+
+    fn negotiate(accept: Option<&str>) -> (Option<ResponseFormat>, Vec<u8>) {
+        let mut chain = Chain::new(not_found);
+        chain.link_before(AcceptHeader);
+        let headers = match accept {
+            Some(value) => forwarded("Accept", value),
+            None => Headers::new(),
+        };
+        let res = match request::get("http://localhost:9636/", headers, &chain) {
+            Ok(res) => res,
+            Err(err) => err.response,
+        };
+        let format = res.headers
+            .get::<ContentType>()
+            .and_then(|&ContentType(ref mime)| ResponseFormat::from_mime(mime));
+        (format, ::iron_test::response::extract_body_to_bytes(res))
+    }
+
+    #[test]
+    fn accept_header_prefers_highest_quality() {
+        for value in &["application/x-protobuf",
+                       "application/json;q=0.5, application/x-protobuf",
+                       "*/*;q=0.1, application/x-protobuf;q=0.9",
+                       "application/x-protobuf, application/json"] {
+            let (format, body) = negotiate(Some(value));
+            assert_eq!(format, Some(ResponseFormat::Protobuf), "{}", value);
+            let err: NetError = ::protobuf::parse_from_bytes(&body).unwrap();
+            assert_eq!(err.get_code(), ErrCode::ENTITY_NOT_FOUND);
+        }
+        for value in &["application/json",
+                       "application/x-protobuf;q=0.1, application/json",
+                       "application/x-protobuf;q=0.5, */*"] {
+            let (format, body) = negotiate(Some(value));
+            assert_eq!(format, Some(ResponseFormat::Json), "{}", value);
+            assert!(serde_json::from_slice::<serde_json::Value>(&body).is_ok());
+        }
+    }
+
+    #[test]
+    fn accept_header_ignores_unacceptable_types() {
+        for value in &["application/x-protobuf;q=0",
+                       "application/x-protobuf;q=0, application/json;q=0.1"] {
+            let (format, body) = negotiate(Some(value));
+            assert_eq!(format, Some(ResponseFormat::Json), "{}", value);
+            assert!(serde_json::from_slice::<serde_json::Value>(&body).is_ok());
+        }
+        let (format, _) = negotiate(Some("application/json;q=0, application/x-protobuf;q=0.1"));
+        assert_eq!(format, Some(ResponseFormat::Protobuf));
+    }
+
+    #[test]
+    fn accept_header_falls_back_to_json() {
+        for value in &["text/html", "text/html, image/png;q=0.5", "application/xml"] {
+            let (format, body) = negotiate(Some(value));
+            assert_eq!(format, Some(ResponseFormat::Json), "{}", value);
+            assert!(serde_json::from_slice::<serde_json::Value>(&body).is_ok());
+        }
+        assert_eq!(negotiate(None).0, Some(ResponseFormat::Json));
+    }
+
+    #[test]
+    fn render_message_without_accept_header_middleware() {
+        let res = match request::get("http://localhost:9636/", Headers::new(), &not_found) {
+            Ok(res) => res,
+            Err(err) => err.response,
+        };
+        assert_eq!(res.status, Some(status::NotFound));
+        assert_eq!(res.headers.get::<ContentType>(),
+                   Some(&ContentType::json()));
+    }
+}
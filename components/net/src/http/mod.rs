@@ -15,11 +15,15 @@
 pub mod controller;
 pub mod headers;
 pub mod middleware;
+pub mod pagination;
 pub mod rendering;
 
 use iron::status::Status;
 use protocol::net::ErrCode;
 
+/// Returns the HTTP status to answer a request with when routing a message for it failed with
+/// the given error code. Handlers should use this rather than picking a status themselves so that
+/// clients can tell a missing entity from a conflict or an unavailable service.
 pub fn net_err_to_http(err: ErrCode) -> Status {
     match err {
         ErrCode::BUG => Status::InternalServerError,
@@ -42,3 +46,30 @@ pub fn net_err_to_http(err: ErrCode) -> Status {
         ErrCode::POST_PROCESSOR => Status::InternalServerError,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn net_err_to_http_not_found() {
+        assert_eq!(net_err_to_http(ErrCode::ENTITY_NOT_FOUND), Status::NotFound);
+    }
+
+    #[test]
+    fn net_err_to_http_forbidden() {
+        assert_eq!(net_err_to_http(ErrCode::AUTH_SCOPE), Status::Forbidden);
+    }
+
+    #[test]
+    fn net_err_to_http_conflict() {
+        assert_eq!(net_err_to_http(ErrCode::ENTITY_CONFLICT), Status::Conflict);
+    }
+
+    #[test]
+    fn net_err_to_http_unavailable() {
+        for code in vec![ErrCode::NO_SHARD, ErrCode::ZMQ, ErrCode::DATA_STORE] {
+            assert_eq!(net_err_to_http(code), Status::ServiceUnavailable);
+        }
+    }
+}
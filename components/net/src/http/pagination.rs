@@ -0,0 +1,215 @@
+// Copyright (c) 2016-2017 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::isize;
+
+use iron::headers::ContentType;
+use iron::mime::{Attr, Mime, TopLevel, SubLevel, Value};
+use iron::prelude::*;
+use iron::status;
+use serde::Serialize;
+use serde_json;
+use urlencoded::UrlEncodedQuery;
+
+use error::Error;
+
+/// Page returned when a request doesn't ask for one
+pub const PAGE_DEFAULT: usize = 1;
+/// Number of items per page returned when a request doesn't ask for a page size
+pub const PER_PAGE_DEFAULT: usize = 20;
+/// Largest page size a request may ask for
+pub const PER_PAGE_MAX: usize = 100;
+
+#[derive(Serialize)]
+struct PaginatedResults<'a, T: 'a> {
+    range_start: isize,
+    range_end: isize,
+    total_count: isize,
+    package_list: &'a Vec<T>,
+}
+
+/// Returns the one-based page and the page size asked for by the `page` and `per_page` query
+/// parameters of the request, or `None` if the request carries neither.
+///
+/// Missing or malformed values fall back to the defaults and the page size is clamped to
+/// `PER_PAGE_MAX`.
+pub fn parse_pagination(req: &mut Request) -> Option<(usize, usize)> {
+    match req.get_ref::<UrlEncodedQuery>() {
+        Ok(query) => pagination_from_query(query),
+        Err(_) => None,
+    }
+}
+
+/// Returns the zero-based, inclusive range of items on the given page, or `None` if the page
+/// lies beyond the items that can be addressed.
+pub fn page_range(page: usize, per_page: usize) -> Option<(usize, usize)> {
+    let per_page = per_page.max(1);
+    let start = match (page.max(1) - 1).checked_mul(per_page) {
+        Some(start) => start,
+        None => return None,
+    };
+    match start.checked_add(per_page - 1) {
+        Some(end) if end <= isize::MAX as usize => Some((start, end)),
+        _ => None,
+    }
+}
+
+/// Render one page of a listing. The zero-based range covered by the page is derived from the
+/// given page and page size.
+///
+/// # Errors
+///
+/// * The page lies beyond the items that can be addressed, answered with a `400 Bad Request`
+pub fn render_paginated<T: Serialize>(items: &Vec<T>,
+                                      page: usize,
+                                      per_page: usize,
+                                      total: usize)
+                                      -> IronResult<Response> {
+    match page_range(page, per_page) {
+        Some((start, end)) => Ok(render_paginated_range(items, start, end, total)),
+        None => {
+            Err(IronError::new(Error::PageOutOfRange(page, per_page),
+                               status::BadRequest))
+        }
+    }
+}
+
+/// Render a slice of a listing covering the given zero-based, inclusive range.
+///
+/// The response is a `206 Partial Content` if items remain beyond the end of the range and a
+/// `200 OK` otherwise.
+pub fn render_paginated_range<T: Serialize>(items: &Vec<T>,
+                                            start: usize,
+                                            end: usize,
+                                            total: usize)
+                                            -> Response {
+    let results = PaginatedResults {
+        range_start: start as isize,
+        range_end: end as isize,
+        total_count: total as isize,
+        package_list: items,
+    };
+    let body = serde_json::to_string(&results).unwrap();
+    let mut response = if total > end + 1 {
+        Response::with((status::PartialContent, body))
+    } else {
+        Response::with((status::Ok, body))
+    };
+    response
+        .headers
+        .set(ContentType(Mime(TopLevel::Application,
+                              SubLevel::Json,
+                              vec![(Attr::Charset, Value::Utf8)])));
+    response
+}
+
+fn pagination_from_query(query: &HashMap<String, Vec<String>>) -> Option<(usize, usize)> {
+    if !query.contains_key("page") && !query.contains_key("per_page") {
+        return None;
+    }
+    let page = match first_value(query, "page").and_then(|v| v.parse::<usize>().ok()) {
+        Some(page) if page > 0 => page,
+        _ => PAGE_DEFAULT,
+    };
+    let per_page = match first_value(query, "per_page").and_then(|v| v.parse::<usize>().ok()) {
+        Some(per_page) if per_page > 0 => per_page.min(PER_PAGE_MAX),
+        _ => PER_PAGE_DEFAULT,
+    };
+    Some((page, per_page))
+}
+
+fn first_value<'a>(query: &'a HashMap<String, Vec<String>>, key: &str) -> Option<&'a String> {
+    query.get(key).and_then(|values| values.first())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::usize;
+
+    use iron::status;
+
+    use super::*;
+
+    fn query(pairs: &[(&str, &str)]) -> HashMap<String, Vec<String>> {
+        let mut query = HashMap::new();
+        for &(key, value) in pairs {
+            query
+                .entry(key.to_string())
+                .or_insert(vec![])
+                .push(value.to_string());
+        }
+        query
+    }
+
+    #[test]
+    fn pagination_absent() {
+        assert_eq!(pagination_from_query(&query(&[])), None);
+        assert_eq!(pagination_from_query(&query(&[("query", "nginx")])), None);
+        assert_eq!(pagination_from_query(&query(&[("range", "50")])), None);
+    }
+
+    #[test]
+    fn pagination_from_params() {
+        assert_eq!(pagination_from_query(&query(&[("page", "3")])), Some((3, 20)));
+        assert_eq!(pagination_from_query(&query(&[("per_page", "50")])), Some((1, 50)));
+        assert_eq!(pagination_from_query(&query(&[("page", "2"), ("per_page", "10")])),
+                   Some((2, 10)));
+    }
+
+    #[test]
+    fn pagination_rejects_bad_values() {
+        assert_eq!(pagination_from_query(&query(&[("page", "0"), ("per_page", "0")])),
+                   Some((1, 20)));
+        assert_eq!(pagination_from_query(&query(&[("page", "-1"), ("per_page", "ten")])),
+                   Some((1, 20)));
+        assert_eq!(pagination_from_query(&query(&[("page", ""), ("per_page", "")])),
+                   Some((1, 20)));
+    }
+
+    #[test]
+    fn pagination_clamps_page_size() {
+        assert_eq!(pagination_from_query(&query(&[("per_page", "101")])), Some((1, 100)));
+        assert_eq!(pagination_from_query(&query(&[("per_page", "100")])), Some((1, 100)));
+    }
+
+    #[test]
+    fn page_range_bounds() {
+        assert_eq!(page_range(1, 20), Some((0, 19)));
+        assert_eq!(page_range(3, 10), Some((20, 29)));
+        assert_eq!(page_range(usize::MAX, 100), None);
+        assert_eq!(page_range(usize::MAX / 50, 100), None);
+    }
+
+    #[test]
+    fn render_paginated_status() {
+        let items = vec!["a", "b"];
+        assert_eq!(render_paginated(&items, 1, 2, 5).unwrap().status,
+                   Some(status::PartialContent));
+        assert_eq!(render_paginated(&items, 3, 2, 5).unwrap().status,
+                   Some(status::Ok));
+        assert_eq!(render_paginated(&items, 1, 2, 2).unwrap().status,
+                   Some(status::Ok));
+        assert_eq!(render_paginated_range(&items, 0, 1, 3).status,
+                   Some(status::PartialContent));
+    }
+
+    #[test]
+    fn render_paginated_out_of_range() {
+        let items = vec!["a", "b"];
+        let err = render_paginated(&items, usize::MAX, 100, 5).unwrap_err();
+        assert_eq!(err.response.status, Some(status::BadRequest));
+    }
+}
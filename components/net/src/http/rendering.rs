@@ -17,18 +17,67 @@ use iron::mime::{Mime, TopLevel, SubLevel};
 use iron::modifiers::Header;
 use iron::prelude::*;
 use iron::status;
+use protobuf::Message;
 use protocol::net::NetError;
 use serde::Serialize;
 use serde_json;
 
+use super::middleware::AcceptHeader;
 use super::net_err_to_http;
 
+/// Wire format a client asked to receive response bodies in
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ResponseFormat {
+    Json,
+    Protobuf,
+}
+
+impl ResponseFormat {
+    /// Returns the response format matching the given mime type, if it is one we can render
+    pub fn from_mime(mime: &Mime) -> Option<Self> {
+        match *mime {
+            Mime(TopLevel::Application, SubLevel::Json, _) => Some(ResponseFormat::Json),
+            Mime(TopLevel::Application, SubLevel::Ext(ref sub), _) if sub == "x-protobuf" => {
+                Some(ResponseFormat::Protobuf)
+            }
+            Mime(TopLevel::Application, SubLevel::Star, _) |
+            Mime(TopLevel::Star, SubLevel::Star, _) => Some(ResponseFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+impl Default for ResponseFormat {
+    fn default() -> Self {
+        ResponseFormat::Json
+    }
+}
+
 pub fn render_json<T: Serialize>(status: status::Status, response: &T) -> Response {
     let encoded = serde_json::to_string(response).unwrap();
     let headers = Header(ContentType(Mime(TopLevel::Application, SubLevel::Json, vec![])));
     Response::with((status, encoded, headers))
 }
 
+pub fn render_proto<M: Message>(status: status::Status, response: &M) -> Response {
+    let encoded = response.write_to_bytes().unwrap();
+    let headers = Header(ContentType(Mime(TopLevel::Application,
+                                          SubLevel::Ext("x-protobuf".to_string()),
+                                          vec![])));
+    Response::with((status, encoded, headers))
+}
+
+/// Render the given message in the format negotiated by the `AcceptHeader` middleware. JSON is
+/// returned if the middleware was not linked for this request.
+pub fn render_message<M>(req: &Request, status: status::Status, response: &M) -> Response
+    where M: Message + Serialize
+{
+    match req.extensions.get::<AcceptHeader>() {
+        Some(&ResponseFormat::Protobuf) => render_proto(status, response),
+        _ => render_json(status, response),
+    }
+}
+
 /// Return an IronResult containing the body of a NetError and the appropriate HTTP response status
 /// for the corresponding NetError.
 ///
@@ -43,3 +92,9 @@ pub fn render_json<T: Serialize>(status: status::Status, response: &T) -> Respon
 pub fn render_net_error(err: &NetError) -> Response {
     render_json(net_err_to_http(err.get_code()), err)
 }
+
+/// Like `render_net_error` but the body is encoded in the format negotiated by the `AcceptHeader`
+/// middleware.
+pub fn render_net_error_message(req: &Request, err: &NetError) -> Response {
+    render_message(req, net_err_to_http(err.get_code()), err)
+}
@@ -12,16 +12,21 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+extern crate base64;
 #[macro_use]
 extern crate bitflags;
 extern crate fnv;
 extern crate habitat_builder_protocol as protocol;
 extern crate habitat_core as core;
+#[cfg(test)]
+extern crate habitat_http_test as http_test;
 #[macro_use]
 extern crate hyper;
 extern crate hyper_openssl;
 #[macro_use]
 extern crate iron;
+#[cfg(test)]
+extern crate iron_test;
 #[macro_use]
 extern crate lazy_static;
 #[macro_use]
@@ -35,6 +40,7 @@ extern crate serde_derive;
 extern crate serde_json;
 extern crate time;
 extern crate unicase;
+extern crate urlencoded;
 extern crate zmq;
 
 pub mod config;
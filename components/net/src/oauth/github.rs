@@ -15,17 +15,20 @@
 use std::error::Error as StdError;
 use std::collections::HashMap;
 use std::fmt;
-use std::io::Read;
+use std::io::{self, Read};
 use std::result::Result as StdResult;
-use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
+use base64;
 use hyper::{self, Url};
 use hyper::status::StatusCode;
-use hyper::header::{Authorization, Accept, Bearer, UserAgent, qitem};
+use hyper::header::{Authorization, Accept, Bearer, Headers, Link, RelationType, UserAgent, qitem};
 use hyper::mime::{Mime, TopLevel, SubLevel};
 use hyper::net::HttpsConnector;
 use hyper_openssl::OpensslClient;
 use protocol::{net, sessionsrv};
+use serde::de::DeserializeOwned;
 use serde_json;
 
 use config;
@@ -33,6 +36,12 @@ use error::{Error, Result};
 
 const USER_AGENT: &'static str = "Habitat-Builder";
 const HTTP_TIMEOUT: u64 = 3_000;
+// How long, in seconds, the result of an organization membership check is cached for.
+const ORG_MEMBER_CACHE_TTL: u64 = 300;
+// How long, in seconds, the repositories listed for an organization are cached for.
+const ORG_REPOS_CACHE_TTL: u64 = 60;
+// Most pages of a list endpoint fetched by `paginate()`.
+const MAX_PAGES: usize = 100;
 // These OAuth scopes are required for a user to be authenticated. If this list is updated, then
 // the front-end also needs to be updated in `components/builder-web/app/util.ts`. Both the
 // front-end app and back-end app should have identical requirements to make things easier for
@@ -40,19 +49,28 @@ const HTTP_TIMEOUT: u64 = 3_000;
 // https://developer.github.com/v3/oauth/#scopes
 const AUTH_SCOPES: &'static [&'static str] = &["user:email", "read:org"];
 
+type OrgMemberCache = HashMap<(String, String, String), (bool, Instant)>;
+type OrgReposCache = HashMap<(String, String), (Vec<GitHubRepo>, Instant)>;
+
 #[derive(Clone)]
 pub struct GitHubClient {
     pub url: String,
     pub client_id: String,
     pub client_secret: String,
+    org_members: Arc<Mutex<OrgMemberCache>>,
+    org_repos: Arc<Mutex<OrgReposCache>>,
+    idle_timeout: Duration,
 }
 
 impl GitHubClient {
     pub fn new<T: config::GitHubOAuth>(config: &T) -> Self {
         GitHubClient {
-            url: config.github_url().to_string(),
+            url: config.github_url().api_base(),
             client_id: config.github_client_id().to_string(),
             client_secret: config.github_client_secret().to_string(),
+            org_members: Arc::new(Mutex::new(HashMap::new())),
+            org_repos: Arc::new(Mutex::new(HashMap::new())),
+            idle_timeout: Duration::from_millis(config.github_idle_timeout()),
         }
     }
 
@@ -63,10 +81,9 @@ impl GitHubClient {
                                       self.client_secret,
                                       code))
                 .unwrap();
-        let mut rep = try!(http_post(url));
+        let mut rep = try!(self.http_post(url));
         if rep.status.is_success() {
-            let mut encoded = String::new();
-            try!(rep.read_to_string(&mut encoded));
+            let encoded = try!(read_body(&mut rep));
             match serde_json::from_str::<AuthOk>(&encoded) {
                 Ok(msg) => {
                     let missing = msg.missing_auth_scopes();
@@ -100,9 +117,8 @@ impl GitHubClient {
     pub fn contents(&self, token: &str, owner: &str, repo: &str, path: &str) -> Result<Contents> {
         let url = Url::parse(&format!("{}/repos/{}/{}/contents/{}", self.url, owner, repo, path))
             .unwrap();
-        let mut rep = try!(http_get(url, token));
-        let mut body = String::new();
-        try!(rep.read_to_string(&mut body));
+        let mut rep = try!(self.http_get(url, token));
+        let body = try!(read_body(&mut rep));
         if rep.status != StatusCode::Ok {
             let err: HashMap<String, String> = try!(serde_json::from_str(&body));
             return Err(Error::GitHubAPI(rep.status, err));
@@ -120,30 +136,47 @@ impl GitHubClient {
 
     pub fn repo(&self, token: &str, owner: &str, repo: &str) -> Result<Repo> {
         let url = Url::parse(&format!("{}/repos/{}/{}", self.url, owner, repo)).unwrap();
-        let mut rep = try!(http_get(url, token));
-        let mut body = String::new();
-        try!(rep.read_to_string(&mut body));
+        let mut rep = try!(self.http_get(url, token));
+        let body = try!(read_body(&mut rep));
         if rep.status != StatusCode::Ok {
             let err: HashMap<String, String> = try!(serde_json::from_str(&body));
             return Err(Error::GitHubAPI(rep.status, err));
         }
+        decode_repo(&body)
+    }
 
-        let repo: Repo = match serde_json::from_str(&body) {
-            Ok(r) => r,
-            Err(e) => {
-                debug!("github repo decode failed: {}. response body: {}", e, body);
-                return Err(Error::from(e));
-            }
-        };
+    /// Returns whether a repository exists and is visible to the token's owner. Any answer from
+    /// GitHub other than found or not found, such as a rate limit, is returned as an error.
+    pub fn repo_exists(&self, token: &str, owner: &str, repo: &str) -> Result<bool> {
+        self.repo_body(token, owner, repo).map(|body| body.is_some())
+    }
 
-        Ok(repo)
+    /// Like `repo_exists()`, but returns the repository when it is found.
+    pub fn find_repo(&self, token: &str, owner: &str, repo: &str) -> Result<Option<Repo>> {
+        match try!(self.repo_body(token, owner, repo)) {
+            Some(body) => decode_repo(&body).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn repo_body(&self, token: &str, owner: &str, repo: &str) -> Result<Option<String>> {
+        let url = Url::parse(&format!("{}/repos/{}/{}", self.url, owner, repo)).unwrap();
+        let mut rep = try!(self.http_get(url, token));
+        let body = try!(read_body(&mut rep));
+        match rep.status {
+            StatusCode::Ok => Ok(Some(body)),
+            StatusCode::NotFound => Ok(None),
+            status => {
+                let err: HashMap<String, String> = try!(serde_json::from_str(&body));
+                Err(Error::GitHubAPI(status, err))
+            }
+        }
     }
 
     pub fn user(&self, token: &str) -> Result<User> {
         let url = Url::parse(&format!("{}/user", self.url)).unwrap();
-        let mut rep = try!(http_get(url, token));
-        let mut body = String::new();
-        try!(rep.read_to_string(&mut body));
+        let mut rep = try!(self.http_get(url, token));
+        let body = try!(read_body(&mut rep));
         if rep.status != StatusCode::Ok {
             let err: HashMap<String, String> = try!(serde_json::from_str(&body));
             return Err(Error::GitHubAPI(rep.status, err));
@@ -154,9 +187,8 @@ impl GitHubClient {
 
     pub fn other_user(&self, token: &str, username: &str) -> Result<User> {
         let url = Url::parse(&format!("{}/users/{}", self.url, username)).unwrap();
-        let mut rep = try!(http_get(url, token));
-        let mut body = String::new();
-        try!(rep.read_to_string(&mut body));
+        let mut rep = try!(self.http_get(url, token));
+        let body = try!(read_body(&mut rep));
         if rep.status != StatusCode::Ok {
             let err: HashMap<String, String> = try!(serde_json::from_str(&body));
             return Err(Error::GitHubAPI(rep.status, err));
@@ -167,9 +199,8 @@ impl GitHubClient {
 
     pub fn emails(&self, token: &str) -> Result<Vec<Email>> {
         let url = Url::parse(&format!("{}/user/emails", self.url)).unwrap();
-        let mut rep = try!(http_get(url, token));
-        let mut body = String::new();
-        try!(rep.read_to_string(&mut body));
+        let mut rep = try!(self.http_get(url, token));
+        let body = try!(read_body(&mut rep));
         if rep.status != StatusCode::Ok {
             let err: HashMap<String, String> = try!(serde_json::from_str(&body));
             return Err(Error::GitHubAPI(rep.status, err));
@@ -180,9 +211,8 @@ impl GitHubClient {
 
     pub fn orgs(&self, token: &str) -> Result<Vec<Organization>> {
         let url = Url::parse(&format!("{}/user/orgs", self.url)).unwrap();
-        let mut rep = try!(http_get(url, token));
-        let mut body = String::new();
-        try!(rep.read_to_string(&mut body));
+        let mut rep = try!(self.http_get(url, token));
+        let body = try!(read_body(&mut rep));
         if rep.status != StatusCode::Ok {
             let err: HashMap<String, String> = try!(serde_json::from_str(&body));
             return Err(Error::GitHubAPI(rep.status, err));
@@ -193,9 +223,8 @@ impl GitHubClient {
 
     pub fn teams(&self, token: &str) -> Result<Vec<Team>> {
         let url = Url::parse(&format!("{}/user/teams", self.url)).unwrap();
-        let mut rep = try!(http_get(url, token));
-        let mut body = String::new();
-        try!(rep.read_to_string(&mut body));
+        let mut rep = try!(self.http_get(url, token));
+        let body = try!(read_body(&mut rep));
         if rep.status != StatusCode::Ok {
             let err: HashMap<String, String> = try!(serde_json::from_str(&body));
             return Err(Error::GitHubAPI(rep.status, err));
@@ -203,6 +232,145 @@ impl GitHubClient {
         let teams: Vec<Team> = try!(serde_json::from_str(&body));
         Ok(teams)
     }
+
+    /// Returns true if the given user is a member of the given organization. Results are cached
+    /// per token, organization, and user for a few minutes.
+    pub fn is_org_member(&self, token: &str, org: &str, username: &str) -> Result<bool> {
+        let key = (token.to_string(), org.to_string(), username.to_string());
+        {
+            let cache = self.org_members.lock().expect("org member cache lock is poisoned");
+            if let Some(&(member, checked_at)) = cache.get(&key) {
+                if checked_at.elapsed() < Duration::from_secs(ORG_MEMBER_CACHE_TTL) {
+                    return Ok(member);
+                }
+            }
+        }
+        let url = try!(self.api_url(&["orgs", org, "members", username]));
+        let mut rep = try!(self.http_get(url, token));
+        let member = match rep.status {
+            StatusCode::NoContent => true,
+            StatusCode::NotFound => false,
+            status => {
+                let body = try!(read_body(&mut rep));
+                let err: HashMap<String, String> = try!(serde_json::from_str(&body));
+                return Err(Error::GitHubAPI(status, err));
+            }
+        };
+        let mut cache = self.org_members.lock().expect("org member cache lock is poisoned");
+        let expired: Vec<(String, String, String)> = cache
+            .iter()
+            .filter(|&(_, &(_, checked_at))| {
+                        checked_at.elapsed() >= Duration::from_secs(ORG_MEMBER_CACHE_TTL)
+                    })
+            .map(|(k, _)| k.clone())
+            .collect();
+        for k in expired {
+            cache.remove(&k);
+        }
+        cache.insert(key, (member, Instant::now()));
+        Ok(member)
+    }
+
+    /// Returns every repository of the given organization visible to the token's owner. Results
+    /// are cached per token and organization for a minute.
+    pub fn list_repos(&self, token: &str, org: &str) -> Result<Vec<GitHubRepo>> {
+        let key = (token.to_string(), org.to_string());
+        {
+            let cache = self.org_repos.lock().expect("org repos cache lock is poisoned");
+            if let Some(&(ref repos, fetched_at)) = cache.get(&key) {
+                if fetched_at.elapsed() < Duration::from_secs(ORG_REPOS_CACHE_TTL) {
+                    return Ok(repos.clone());
+                }
+            }
+        }
+        let mut url = try!(self.api_url(&["orgs", org, "repos"]));
+        url.set_query(Some("type=all&per_page=100"));
+        let repos: Vec<GitHubRepo> = try!(self.paginate(url, token));
+        let mut cache = self.org_repos.lock().expect("org repos cache lock is poisoned");
+        let expired: Vec<(String, String)> = cache
+            .iter()
+            .filter(|&(_, &(_, fetched_at))| {
+                        fetched_at.elapsed() >= Duration::from_secs(ORG_REPOS_CACHE_TTL)
+                    })
+            .map(|(k, _)| k.clone())
+            .collect();
+        for k in expired {
+            cache.remove(&k);
+        }
+        cache.insert(key, (repos.clone(), Instant::now()));
+        Ok(repos)
+    }
+
+    /// Returns the URL of the API endpoint at the given path segments, percent-encoding each of
+    /// them.
+    fn api_url(&self, segments: &[&str]) -> Result<Url> {
+        let mut url = match Url::parse(&self.url) {
+            Ok(url) => url,
+            Err(e) => return Err(Error::InvalidGitHubUrl(format!("{}, {}", self.url, e))),
+        };
+        match url.path_segments_mut() {
+            Ok(mut path) => {
+                path.pop_if_empty().extend(segments);
+            }
+            Err(_) => return Err(Error::InvalidGitHubUrl(self.url.clone())),
+        }
+        Ok(url)
+    }
+
+    /// Fetches every page of a list endpoint, starting at the given URL and following the `next`
+    /// links GitHub returns in the `Link` header.
+    ///
+    /// Links are only followed to the configured API's scheme, host and port, so that the token
+    /// isn't sent anywhere else, and at most `MAX_PAGES` pages are fetched.
+    pub fn paginate<T: DeserializeOwned>(&self, url: Url, token: &str) -> Result<Vec<T>> {
+        let api = Url::parse(&self.url).unwrap().origin();
+        let mut items = Vec::new();
+        let mut next = Some(url);
+        let mut pages = 0;
+        while let Some(url) = next {
+            if url.origin() != api {
+                warn!("Not following GitHub page link to another host, url={}", url);
+                break;
+            }
+            if pages == MAX_PAGES {
+                warn!("Stopped listing GitHub pages after {} pages, url={}", MAX_PAGES, url);
+                break;
+            }
+            pages += 1;
+            let mut rep = try!(self.http_get(url, token));
+            let body = try!(read_body(&mut rep));
+            if rep.status != StatusCode::Ok {
+                let err: HashMap<String, String> = try!(serde_json::from_str(&body));
+                return Err(Error::GitHubAPI(rep.status, err));
+            }
+            let mut page: Vec<T> = try!(serde_json::from_str(&body));
+            items.append(&mut page);
+            next = next_page(&rep.headers);
+        }
+        Ok(items)
+    }
+
+    fn http_get(&self,
+                url: Url,
+                token: &str)
+                -> StdResult<hyper::client::response::Response, net::NetError> {
+        hyper_client(self.idle_timeout)
+            .get(url)
+            .header(Accept(vec![qitem(Mime(TopLevel::Application, SubLevel::Json, vec![]))]))
+            .header(Authorization(Bearer { token: token.to_owned() }))
+            .header(UserAgent(USER_AGENT.to_string()))
+            .send()
+            .map_err(hyper_to_net_err)
+    }
+
+    fn http_post(&self, url: Url) -> StdResult<hyper::client::response::Response, net::NetError> {
+        hyper_client(self.idle_timeout)
+            .post(url)
+            .header(Accept(vec![qitem(Mime(TopLevel::Application, SubLevel::Json, vec![]))]))
+            .header(UserAgent(USER_AGENT.to_string()))
+            .send()
+            .map_err(hyper_to_net_err)
+    }
 }
 
 
@@ -220,6 +388,27 @@ pub struct Contents {
     pub encoding: String,
 }
 
+impl Contents {
+    /// Decode the file's content into raw bytes.
+    ///
+    /// An empty file is valid and yields an empty vector, regardless of the reported encoding.
+    ///
+    /// # Errors
+    ///
+    /// * Content was encoded with something other than base64
+    /// * Content was not valid base64, for example if the response was truncated
+    pub fn decode(&self) -> Result<Vec<u8>> {
+        let content: String = self.content.chars().filter(|c| !c.is_whitespace()).collect();
+        if content.is_empty() {
+            return Ok(vec![]);
+        }
+        if self.encoding != "base64" {
+            return Err(Error::ContentDecode(format!("unsupported encoding '{}'", self.encoding)));
+        }
+        base64::decode(&content).map_err(|e| Error::ContentDecode(e.to_string()))
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Repo {
     pub id: u64,
@@ -296,6 +485,19 @@ pub struct Repo {
     pub subscribers_count: u32,
 }
 
+/// A repository as returned when listing the repositories of an organization.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GitHubRepo {
+    pub id: u64,
+    pub name: String,
+    pub full_name: String,
+    pub private: bool,
+    pub html_url: String,
+    pub clone_url: String,
+    pub description: Option<String>,
+    pub default_branch: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Organization {
     pub login: String,
@@ -440,34 +642,354 @@ pub enum AuthResp {
     AuthErr,
 }
 
-fn http_get(url: Url, token: &str) -> StdResult<hyper::client::response::Response, net::NetError> {
-    hyper_client()
-        .get(url)
-        .header(Accept(vec![qitem(Mime(TopLevel::Application, SubLevel::Json, vec![]))]))
-        .header(Authorization(Bearer { token: token.to_owned() }))
-        .header(UserAgent(USER_AGENT.to_string()))
-        .send()
-        .map_err(hyper_to_net_err)
+// Reads a response body, reporting a connection which stalls for longer than the idle timeout as
+// a bad reply from GitHub rather than an I/O error of our own.
+fn read_body(rep: &mut hyper::client::response::Response) -> Result<String> {
+    let mut body = String::new();
+    match rep.read_to_string(&mut body) {
+        Ok(_) => Ok(body),
+        Err(ref err) if is_idle_timeout(err) => Err(Error::Net(idle_timeout_err())),
+        Err(err) => Err(Error::IO(err)),
+    }
+}
+
+fn decode_repo(body: &str) -> Result<Repo> {
+    match serde_json::from_str(body) {
+        Ok(repo) => Ok(repo),
+        Err(e) => {
+            debug!("github repo decode failed: {}. response body: {}", e, body);
+            Err(Error::from(e))
+        }
+    }
+}
+
+// A read on a socket with a read timeout fails with `WouldBlock` on Unix and `TimedOut` on
+// Windows once the timeout elapses.
+fn is_idle_timeout(err: &io::Error) -> bool {
+    match err.kind() {
+        io::ErrorKind::WouldBlock |
+        io::ErrorKind::TimedOut => true,
+        _ => false,
+    }
+}
+
+fn idle_timeout_err() -> net::NetError {
+    net::err(net::ErrCode::BAD_REMOTE_REPLY, "net:github:1")
 }
 
-fn http_post(url: Url) -> StdResult<hyper::client::response::Response, net::NetError> {
-    hyper_client()
-        .post(url)
-        .header(Accept(vec![qitem(Mime(TopLevel::Application, SubLevel::Json, vec![]))]))
-        .header(UserAgent(USER_AGENT.to_string()))
-        .send()
-        .map_err(hyper_to_net_err)
+// Returns the URL of the next page of results, if the response says there is one.
+fn next_page(headers: &Headers) -> Option<Url> {
+    let link = match headers.get::<Link>() {
+        Some(link) => link,
+        None => return None,
+    };
+    link.values()
+        .iter()
+        .find(|value| value.rel().map_or(false, |rel| rel.contains(&RelationType::Next)))
+        .and_then(|value| Url::parse(value.link()).ok())
 }
 
-fn hyper_client() -> hyper::Client {
+// The read timeout bounds how long a request may go without receiving any data, whether waiting
+// for the response head or part way through the body.
+fn hyper_client(idle_timeout: Duration) -> hyper::Client {
     let ssl = OpensslClient::new().unwrap();
     let connector = HttpsConnector::new(ssl);
     let mut client = hyper::Client::with_connector(connector);
-    client.set_read_timeout(Some(Duration::from_millis(HTTP_TIMEOUT)));
+    client.set_read_timeout(Some(idle_timeout));
     client.set_write_timeout(Some(Duration::from_millis(HTTP_TIMEOUT)));
     client
 }
 
 fn hyper_to_net_err(err: hyper::error::Error) -> net::NetError {
+    if let hyper::error::Error::Io(ref e) = err {
+        if is_idle_timeout(e) {
+            return idle_timeout_err();
+        }
+    }
     net::err(net::ErrCode::BAD_REMOTE_REPLY, err.description())
 }
+
+#[cfg(test)]
+mod test {
+    use http_test::{self, MockServer, Response};
+
+    use super::*;
+    use http::net_err_to_http;
+
+    const REPOS_PAGE_1: &'static str = r#"[{"id":1,"name":"core-plans","full_name":"habitat-sh/core-plans","private":false,"html_url":"https://github.com/habitat-sh/core-plans","clone_url":"https://github.com/habitat-sh/core-plans.git","description":"Core Habitat plans","default_branch":"master"}]"#;
+    const REPOS_PAGE_2: &'static str = r#"[{"id":2,"name":"habitat","full_name":"habitat-sh/habitat","private":true,"html_url":"https://github.com/habitat-sh/habitat","clone_url":"https://github.com/habitat-sh/habitat.git","description":null,"default_branch":"master"}]"#;
+
+    fn client(url: &str) -> GitHubClient {
+        GitHubClient {
+            url: url.to_string(),
+            client_id: "".to_string(),
+            client_secret: "".to_string(),
+            org_members: Arc::new(Mutex::new(HashMap::new())),
+            org_repos: Arc::new(Mutex::new(HashMap::new())),
+            idle_timeout: Duration::from_millis(HTTP_TIMEOUT),
+        }
+    }
+
+    // Answers with a response head which promises a body, then stalls without sending it.
+    fn stalled_github() -> MockServer {
+        MockServer::start(|_| {
+            Response::new("200 OK")
+                .header("Content-Type", "application/json")
+                .header("Content-Length", "1024")
+                .body("{\"name\":")
+                .stall()
+        })
+    }
+
+    // Serves the two pages of an organization's repositories.
+    fn mock_github() -> MockServer {
+        MockServer::start(|req| {
+            if req.path() != "/orgs/habitat-sh/repos" {
+                return Response::json("200 OK", REPOS_PAGE_2);
+            }
+            let next = format!("http://{}/organizations/1/repos?type=all&per_page=100&page=2",
+                               req.header("Host").unwrap());
+            let link = format!("<{}>; rel=\"next\", <{}>; rel=\"last\"", next, next);
+            Response::json("200 OK", REPOS_PAGE_1).header("Link", &link)
+        })
+    }
+
+    fn contents(content: &str, encoding: &str) -> Contents {
+        Contents {
+            name: "plan.sh".to_string(),
+            path: "habitat/plan.sh".to_string(),
+            sha: "".to_string(),
+            size: content.len(),
+            url: "".to_string(),
+            html_url: "".to_string(),
+            git_url: "".to_string(),
+            download_url: "".to_string(),
+            content: content.to_string(),
+            encoding: encoding.to_string(),
+        }
+    }
+
+    #[test]
+    fn decode_base64_contents() {
+        let c = contents("cGtnX25hbWU9\nZm9v\n", "base64");
+        assert_eq!(c.decode().unwrap(), b"pkg_name=foo".to_vec());
+    }
+
+    #[test]
+    fn decode_empty_contents() {
+        assert!(contents("", "base64").decode().unwrap().is_empty());
+        assert!(contents("\n", "").decode().unwrap().is_empty());
+    }
+
+    #[test]
+    fn decode_malformed_contents() {
+        match contents("cGtnX25hbWU9Zm9v!!", "base64").decode() {
+            Err(Error::ContentDecode(_)) => (),
+            e => panic!("Unexpected result: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn decode_unsupported_encoding() {
+        match contents("pkg_name=foo", "utf-8").decode() {
+            Err(Error::ContentDecode(_)) => (),
+            e => panic!("Unexpected result: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn list_repos_follows_pagination() {
+        let github = mock_github();
+        let repos = client(github.url()).list_repos("token", "habitat-sh").unwrap();
+
+        assert_eq!(github.request_lines(),
+                   vec!["GET /orgs/habitat-sh/repos?type=all&per_page=100 HTTP/1.1",
+                        "GET /organizations/1/repos?type=all&per_page=100&page=2 HTTP/1.1"]);
+        let names: Vec<&str> = repos.iter().map(|r| r.full_name.as_str()).collect();
+        assert_eq!(names, vec!["habitat-sh/core-plans", "habitat-sh/habitat"]);
+        assert_eq!(repos[1].description, None);
+    }
+
+    #[test]
+    fn list_repos_encodes_organization() {
+        let github = MockServer::respond_with(vec![Response::json("200 OK", "[]")]);
+        client(github.url()).list_repos("token", "habitat/sh?x").unwrap();
+        assert_eq!(github.request_lines(),
+                   vec!["GET /orgs/habitat%2Fsh%3Fx/repos?type=all&per_page=100 HTTP/1.1"]);
+    }
+
+    #[test]
+    fn list_repos_ignores_links_to_other_hosts() {
+        let link = "<http://github.invalid/organizations/1/repos?page=2>; rel=\"next\"";
+        let github =
+            MockServer::start(move |_| Response::json("200 OK", REPOS_PAGE_1).header("Link", link));
+        let repos = client(github.url()).list_repos("token", "habitat-sh").unwrap();
+        assert_eq!(github.requests().len(), 1);
+        assert_eq!(repos.len(), 1);
+    }
+
+    #[test]
+    fn list_repos_stops_after_max_pages() {
+        let github = MockServer::start(|req| {
+            let link = format!("<http://{}/orgs/habitat-sh/repos?page=next>; rel=\"next\"",
+                               req.header("Host").unwrap());
+            Response::json("200 OK", REPOS_PAGE_1).header("Link", &link)
+        });
+        let repos = client(github.url()).list_repos("token", "habitat-sh").unwrap();
+        assert_eq!(github.requests().len(), MAX_PAGES);
+        assert_eq!(repos.len(), MAX_PAGES);
+    }
+
+    #[test]
+    fn list_repos_is_cached() {
+        let github = mock_github();
+        let client = client(github.url());
+        let repos = client.list_repos("token", "habitat-sh").unwrap();
+        let cached = client.list_repos("token", "habitat-sh").unwrap();
+
+        assert_eq!(cached.len(), repos.len());
+        assert_eq!(github.requests().len(), 2);
+    }
+
+    #[test]
+    fn contents_idle_timeout() {
+        let github = stalled_github();
+        let mut client = client(github.url());
+        client.idle_timeout = Duration::from_millis(100);
+
+        let started = Instant::now();
+        match client.contents("token", "habitat-sh", "core-plans", "redis/plan.sh") {
+            Err(Error::Net(ref e)) => {
+                assert_eq!(e.get_code(), net::ErrCode::BAD_REMOTE_REPLY);
+                assert_eq!(net_err_to_http(e.get_code()), StatusCode::BadGateway);
+            }
+            e => panic!("Unexpected result: {:?}", e),
+        }
+        assert!(started.elapsed() < Duration::from_millis(HTTP_TIMEOUT));
+    }
+
+    #[test]
+    fn repo_exists_found() {
+        let github = MockServer::respond_with(vec![Response::json("200 OK", r#"{"id":1}"#)]);
+        assert!(client(github.url()).repo_exists("token", "habitat-sh", "core-plans").unwrap());
+    }
+
+    #[test]
+    fn repo_exists_not_found() {
+        let github = MockServer::respond_with(vec![Response::json("404 Not Found",
+                                                                 r#"{"message":"Not Found"}"#)]);
+        assert!(!client(github.url()).repo_exists("token", "habitat-sh", "nope").unwrap());
+    }
+
+    #[test]
+    fn repo_exists_api_error() {
+        let body = r#"{"message":"API rate limit exceeded"}"#;
+        let github = MockServer::respond_with(vec![Response::json("403 Forbidden", body)]);
+        match client(github.url()).repo_exists("token", "habitat-sh", "core-plans") {
+            Err(Error::GitHubAPI(StatusCode::Forbidden, ref msg)) => {
+                assert_eq!(msg.get("message").unwrap(), "API rate limit exceeded")
+            }
+            e => panic!("Unexpected result: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn repo_exists_network_error() {
+        let url = http_test::unreachable_url();
+        match client(&url).repo_exists("token", "habitat-sh", "core-plans") {
+            Err(Error::Net(_)) => (),
+            e => panic!("Unexpected result: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn repo_not_found() {
+        let github = MockServer::respond_with(vec![Response::json("404 Not Found",
+                                                                 r#"{"message":"Not Found"}"#)]);
+        match client(github.url()).repo("token", "habitat-sh", "nope") {
+            Err(Error::GitHubAPI(StatusCode::NotFound, _)) => (),
+            e => panic!("Unexpected result: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn repo_api_error() {
+        let body = r#"{"message":"API rate limit exceeded"}"#;
+        let github = MockServer::respond_with(vec![Response::json("403 Forbidden", body)]);
+        match client(github.url()).repo("token", "habitat-sh", "core-plans") {
+            Err(Error::GitHubAPI(StatusCode::Forbidden, ref msg)) => {
+                assert_eq!(msg.get("message").unwrap(), "API rate limit exceeded")
+            }
+            e => panic!("Unexpected result: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn repo_network_error() {
+        match client(&http_test::unreachable_url()).repo("token", "habitat-sh", "core-plans") {
+            Err(Error::Net(_)) => (),
+            e => panic!("Unexpected result: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn is_org_member_member() {
+        let github = MockServer::respond_with(vec![Response::new("204 No Content")]);
+        let client = client(github.url());
+        assert!(client.is_org_member("token", "habitat-sh", "bobo").unwrap());
+        // The second answer is served from the cache
+        assert!(client.is_org_member("token", "habitat-sh", "bobo").unwrap());
+        assert_eq!(github.request_lines(),
+                   vec!["GET /orgs/habitat-sh/members/bobo HTTP/1.1"]);
+    }
+
+    #[test]
+    fn is_org_member_not_member() {
+        let github = MockServer::respond_with(vec![Response::new("404 Not Found")]);
+        let client = client(github.url());
+        assert!(!client.is_org_member("token", "habitat-sh", "logan").unwrap());
+        assert!(!client.is_org_member("token", "habitat-sh", "logan").unwrap());
+        assert_eq!(github.requests().len(), 1);
+    }
+
+    #[test]
+    fn is_org_member_api_error() {
+        let body = r#"{"message":"Requires authentication"}"#;
+        let github = MockServer::respond_with(vec![Response::json("401 Unauthorized", body),
+                                                   Response::new("204 No Content")]);
+        let client = client(github.url());
+        match client.is_org_member("token", "habitat-sh", "bobo") {
+            Err(Error::GitHubAPI(StatusCode::Unauthorized, ref msg)) => {
+                assert_eq!(msg.get("message").unwrap(), "Requires authentication")
+            }
+            e => panic!("Unexpected result: {:?}", e),
+        }
+        // Failures aren't cached
+        assert!(client.is_org_member("token", "habitat-sh", "bobo").unwrap());
+        assert_eq!(github.requests().len(), 2);
+    }
+
+    #[test]
+    fn is_org_member_encodes_path() {
+        let github = MockServer::respond_with(vec![Response::new("204 No Content")]);
+        assert!(client(github.url()).is_org_member("token", "habitat/sh", "bo?bo").unwrap());
+        assert_eq!(github.request_lines(),
+                   vec!["GET /orgs/habitat%2Fsh/members/bo%3Fbo HTTP/1.1"]);
+    }
+
+    #[test]
+    fn is_org_member_invalid_url() {
+        match client("not a url").is_org_member("token", "habitat-sh", "bobo") {
+            Err(Error::InvalidGitHubUrl(_)) => (),
+            e => panic!("Unexpected result: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn is_org_member_network_error() {
+        match client(&http_test::unreachable_url()).is_org_member("token", "habitat-sh", "bobo") {
+            Err(Error::Net(_)) => (),
+            e => panic!("Unexpected result: {:?}", e),
+        }
+    }
+}
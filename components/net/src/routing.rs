@@ -19,6 +19,7 @@
 use std::result;
 use std::sync::mpsc;
 use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
 use fnv::FnvHasher;
 use protobuf::{self, parse_from_bytes, Message};
@@ -36,6 +37,8 @@ pub type RouteResult<T> = result::Result<T, NetError>;
 pub const RECV_TIMEOUT_MS: i32 = 5_000;
 /// Time to wait before timing out a message send for a `Broker` to a router.
 pub const SEND_TIMEOUT_MS: i32 = 5_000;
+/// Time to wait for a router to answer a `Ping` when verifying router connectivity on startup.
+pub const PING_TIMEOUT_MS: u64 = 5_000;
 // ZeroMQ address for the application's Broker's queue.
 const ROUTE_INPROC_ADDR: &'static str = "inproc://route-broker";
 
@@ -43,6 +46,7 @@ const ROUTE_INPROC_ADDR: &'static str = "inproc://route-broker";
 /// a running `Broker`.
 pub struct BrokerConn {
     sock: zmq::Socket,
+    addr: Option<String>,
 }
 
 impl BrokerConn {
@@ -57,7 +61,10 @@ impl BrokerConn {
         try!(socket.set_rcvtimeo(RECV_TIMEOUT_MS));
         try!(socket.set_sndtimeo(SEND_TIMEOUT_MS));
         try!(socket.set_immediate(true));
-        Ok(BrokerConn { sock: socket })
+        Ok(BrokerConn {
+               sock: socket,
+               addr: None,
+           })
     }
 
     /// Connect to a running `Broker` with the given ZeroMQ address.
@@ -67,6 +74,7 @@ impl BrokerConn {
     /// * A connection cannot be established to a socket at the given address
     pub fn connect(&mut self, addr: &str) -> Result<()> {
         try!(self.sock.connect(addr));
+        self.addr = Some(addr.to_string());
         Ok(())
     }
 
@@ -112,6 +120,52 @@ impl BrokerConn {
         }
     }
 
+    /// Like `route`, but a message which fails with a transient error, such as a timeout or an
+    /// unavailable service, is routed again until it succeeds or the given timeout elapses.
+    ///
+    /// # Errors
+    ///
+    /// * The message failed with an error which isn't transient
+    /// * The message still failed once the timeout elapsed
+    ///
+    /// # Panics
+    ///
+    /// * Could not serialize message
+    pub fn route_timeout<M: Routable, R: protobuf::MessageStatic>(&mut self,
+                                                                  msg: &M,
+                                                                  timeout: Duration)
+                                                                  -> RouteResult<R> {
+        let started = Instant::now();
+        loop {
+            match self.route::<M, R>(msg) {
+                Err(err) => {
+                    if started.elapsed() >= timeout || !Error::Net(err.clone()).is_transient() {
+                        return Err(err);
+                    }
+                    warn!("route failed, retrying, err={}", err);
+                    // A request socket which timed out waiting on a reply can't send another
+                    // request, so it's replaced.
+                    if err.get_code() == ErrCode::TIMEOUT {
+                        if let Err(e) = self.reconnect() {
+                            error!("route-reconnect, err={:?}", e);
+                            return Err(err);
+                        }
+                    }
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn reconnect(&mut self) -> Result<()> {
+        let mut conn = try!(BrokerConn::new());
+        if let Some(ref addr) = self.addr {
+            try!(conn.connect(addr));
+        }
+        *self = conn;
+        Ok(())
+    }
+
     /// Asynchronously routes a message to the connected broker, through a router, and to
     /// appropriate service.
     ///
@@ -132,6 +186,29 @@ impl BrokerConn {
         Ok(())
     }
 
+    /// Returns true if a router answers a `Ping` sent through this connection before the receive
+    /// timeout elapses.
+    ///
+    /// A connection which fails this check is left waiting on a reply and should be dropped.
+    pub fn is_connected(&self) -> bool {
+        let req = protocol::Message::new(&protocol::net::Ping::new())
+            .routing(None)
+            .build();
+        let bytes = req.write_to_bytes().unwrap();
+        if self.sock.send_str("RQ", zmq::SNDMORE).is_err() || self.sock.send(&bytes, 0).is_err() {
+            return false;
+        }
+        match self.sock.recv_msg(0) {
+            Ok(envelope) => {
+                match parse_from_bytes::<protocol::net::Msg>(&envelope) {
+                    Ok(rep) => rep.get_message_id() == "Pong",
+                    Err(_) => false,
+                }
+            }
+            Err(_) => false,
+        }
+    }
+
     /// Receives a message from the connected broker. This function will block the calling thread
     /// until a message is received or a timeout occurs.
     ///
@@ -193,6 +270,47 @@ impl Broker {
         Ok(conn)
     }
 
+    /// Like `connect()` but returns `None` instead of an error if the socket could not be created
+    /// or connected, for callers which report an unavailable service rather than fail.
+    ///
+    /// # Panics
+    ///
+    /// * Could not read `zmq::Context` due to deadlock or poisoning
+    pub fn try_connect() -> Option<BrokerConn> {
+        match Self::connect() {
+            Ok(conn) => Some(conn),
+            Err(e) => {
+                error!("Unable to connect to the route broker, err={}", e);
+                None
+            }
+        }
+    }
+
+    /// Helper function for creating a new `BrokerConn` and connecting to the application's
+    /// `Broker` after verifying that each of the given routers is reachable.
+    ///
+    /// ZeroMQ connections are established asynchronously and never fail for a bad address, so
+    /// each router is sent a `Ping` and must answer with a `Pong` within the given timeout.
+    ///
+    /// # Errors
+    ///
+    /// * A router did not answer within the given timeout
+    /// * A router answered with an error or an unexpected message
+    /// * Could not connect to `Broker`
+    /// * Could not create socket
+    ///
+    /// # Panics
+    ///
+    /// * Could not read `zmq::Context` due to deadlock or poisoning
+    pub fn connect_with_timeout(routers: &Vec<RouterAddr>,
+                                timeout: Duration)
+                                -> Result<BrokerConn> {
+        for addr in routers.iter().map(|a| a.to_addr_string()) {
+            try!(ping_router(&addr, timeout));
+        }
+        Self::connect()
+    }
+
     /// Create a new `Broker` and run it in a separate thread. This function will block the calling
     /// thread until the new broker has successfully started.
     ///
@@ -229,3 +347,115 @@ impl Broker {
         Ok(())
     }
 }
+
+// Sends a `Ping` directly to the router at the given address and waits up to `timeout` for a
+// `Pong` in return. The probe socket is closed without lingering once this function returns.
+fn ping_router(addr: &str, timeout: Duration) -> Result<()> {
+    let timeout_ms = (timeout.as_secs() * 1_000) as i64 +
+                     (timeout.subsec_nanos() / 1_000_000) as i64;
+    let socket = try!((**ZMQ_CONTEXT).as_mut().socket(zmq::REQ));
+    try!(socket.set_linger(0));
+    try!(socket.set_sndtimeo(timeout_ms as i32));
+    try!(socket.connect(addr));
+    let req = protocol::Message::new(&protocol::net::Ping::new())
+        .routing(None)
+        .build();
+    try!(socket.send_str("RQ", zmq::SNDMORE));
+    try!(socket.send(&req.write_to_bytes().unwrap(), 0));
+    {
+        let mut items = [socket.as_poll_item(zmq::POLLIN)];
+        try!(zmq::poll(&mut items, timeout_ms));
+        if (items[0].get_revents() & zmq::POLLIN) == 0 {
+            return Err(Error::Timeout(addr.to_string()));
+        }
+    }
+    let rep: protocol::net::Msg = try!(parse_from_bytes(&try!(socket.recv_msg(0))));
+    match rep.get_message_id() {
+        "Pong" => Ok(()),
+        "NetError" => Err(Error::Net(try!(parse_from_bytes(rep.get_body())))),
+        id => {
+            warn!("unexpected reply to router ping, addr={}, msg={}", addr, id);
+            Err(Error::Sys)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use http_test;
+    use protocol::net;
+    use protocol::originsrv::{Origin, OriginGet};
+
+    use super::*;
+
+    // Answers each request routed to the given address with the next of the given replies.
+    fn mock_broker(addr: &'static str, replies: Vec<protocol::net::Msg>) -> JoinHandle<()> {
+        let sock = (**ZMQ_CONTEXT).as_mut().socket(zmq::ROUTER).unwrap();
+        sock.bind(addr).unwrap();
+        thread::spawn(move || for reply in replies {
+                          let ident = sock.recv_msg(0).unwrap();
+                          sock.recv_msg(0).unwrap(); // delimiter
+                          sock.recv_msg(0).unwrap(); // "RQ"
+                          sock.recv_msg(0).unwrap(); // request
+                          sock.send(&ident, zmq::SNDMORE).unwrap();
+                          sock.send(&[], zmq::SNDMORE).unwrap();
+                          sock.send(&reply.write_to_bytes().unwrap(), 0).unwrap();
+                      })
+    }
+
+    fn error_reply(code: ErrCode) -> protocol::net::Msg {
+        protocol::Message::new(&net::err(code, "test")).build()
+    }
+
+    #[test]
+    fn route_timeout_retries_transient_errors() {
+        let addr = "inproc://route-timeout-transient";
+        let mut origin = Origin::new();
+        origin.set_name("core".to_string());
+        let broker = mock_broker(addr,
+                                 vec![error_reply(ErrCode::ZMQ),
+                                      protocol::Message::new(&origin).build()]);
+        let mut conn = BrokerConn::new().unwrap();
+        conn.connect(addr).unwrap();
+
+        let reply: Origin = conn.route_timeout(&OriginGet::new(), Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(reply.get_name(), "core");
+        broker.join().unwrap();
+    }
+
+    #[test]
+    fn route_timeout_fails_other_errors() {
+        let addr = "inproc://route-timeout-not-found";
+        let broker = mock_broker(addr, vec![error_reply(ErrCode::ENTITY_NOT_FOUND)]);
+        let mut conn = BrokerConn::new().unwrap();
+        conn.connect(addr).unwrap();
+
+        match conn.route_timeout::<OriginGet, Origin>(&OriginGet::new(), Duration::from_secs(5)) {
+            Err(ref err) => assert_eq!(err.get_code(), ErrCode::ENTITY_NOT_FOUND),
+            Ok(_) => panic!("Routed a message which failed"),
+        }
+        broker.join().unwrap();
+    }
+
+    #[test]
+    fn try_connect_without_broker() {
+        // Inproc connections may be made before the broker binds, so this only fails to connect
+        // on socket errors, but nothing answers a ping.
+        let conn = Broker::try_connect().unwrap();
+        assert!(!conn.is_connected());
+    }
+
+    #[test]
+    fn connect_with_timeout_unreachable_router() {
+        let mut router = RouterAddr::default();
+        router.port = http_test::free_port();
+        let started = Instant::now();
+        match Broker::connect_with_timeout(&vec![router.clone()], Duration::from_millis(200)) {
+            Err(Error::Timeout(ref addr)) => assert_eq!(addr, &router.to_addr_string()),
+            Err(e) => panic!("Unexpected error: {:?}", e),
+            Ok(_) => panic!("Connected to an unreachable router"),
+        }
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}